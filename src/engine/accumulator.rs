@@ -0,0 +1,96 @@
+//! Incrementally updated material and piece-square scores.
+//!
+//! Summing the material and piece-square tables over every piece is the bulk of the work
+//! of a simple evaluation. Instead, the evaluator keeps an accumulator of these sums for
+//! its weights, adding and removing each piece as it moves, is captured or promotes, so
+//! the evaluation can read them directly.
+
+use board::piece::Piece;
+use board::player::Player;
+use board::square::Square;
+use engine::heuristic::Score;
+use engine::params::EvalParams;
+use rules::game_state::GameState;
+use rules::move_application::PieceChanges;
+
+/// The material and piece-square scores of both players, for a set of weights.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Accumulator {
+    material: [Score; 2],
+    squares: [[Score; 6]; 2],
+}
+
+impl Accumulator {
+    /// Computes the scores of the pieces of the given state from scratch.
+    pub fn new(params: &EvalParams, state: &GameState) -> Self {
+        let mut accumulator = Accumulator::default();
+        for player in [Player::White, Player::Black].iter() {
+            let board = state.player_board(*player);
+            for piece in Piece::iter() {
+                for square in board.piece(piece).iter() {
+                    accumulator.add(params, piece, *player, square);
+                }
+            }
+        }
+        accumulator
+    }
+
+    /// Updates the scores for the pieces a move added and removed.
+    pub fn update(&mut self, params: &EvalParams, changes: &PieceChanges) {
+        for change in changes.iter() {
+            if change.added {
+                self.add(params, change.piece, change.player, change.square);
+            } else {
+                self.remove(params, change.piece, change.player, change.square);
+            }
+        }
+    }
+
+    /// Adds a piece of the given player on the given square.
+    pub fn add(&mut self, params: &EvalParams, piece: Piece, player: Player, square: Square) {
+        let side = player as usize;
+        self.material[side] = self.material[side] + params.material.value(piece);
+        self.squares[side][piece as usize] =
+            self.squares[side][piece as usize] + square_score(params, piece, player, square);
+    }
+
+    /// Removes a piece of the given player from the given square.
+    pub fn remove(&mut self, params: &EvalParams, piece: Piece, player: Player, square: Square) {
+        let side = player as usize;
+        self.material[side] = self.material[side] - params.material.value(piece);
+        self.squares[side][piece as usize] =
+            self.squares[side][piece as usize] - square_score(params, piece, player, square);
+    }
+
+    /// Returns the material on the board of the given player.
+    pub fn material(&self, player: Player) -> Score {
+        self.material[player as usize]
+    }
+
+    /// Returns the piece-square score of the given player's pieces of the given kind.
+    pub fn squares(&self, player: Player, piece: Piece) -> Score {
+        self.squares[player as usize][piece as usize]
+    }
+
+    /// Returns the sum of the material and piece-square scores. Positive values favour
+    /// white.
+    pub fn total(&self) -> Score {
+        let side = |player: Player| {
+            Piece::iter().fold(self.material(player), |score, piece| {
+                score + self.squares(player, piece)
+            })
+        };
+        side(Player::White) - side(Player::Black)
+    }
+}
+
+// returns the piece-square score of a piece, from tables which list the eighth rank first
+// and are written from white's point of view, so black's pieces read them directly
+fn square_score(params: &EvalParams, piece: Piece, player: Player, square: Square) -> Score {
+    let table = params.squares.table(piece);
+    let index = match player {
+        Player::White => square.to_index() as usize ^ 56,
+        Player::Black => square.to_index() as usize,
+    };
+    Score::new(table.mg[index], table.eg[index])
+}
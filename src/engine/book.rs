@@ -0,0 +1,321 @@
+//! Polyglot opening books.
+//!
+//! A book is a file of 16-byte entries, sorted by key, each giving a move played from a
+//! position and how strongly it is recommended. Every field is big-endian:
+//!
+//! ```text
+//! u64    the Polyglot key of the position, from GameState::polyglot_key
+//! u16    the move
+//! u16    the weight of the move, relative to the other moves from the position
+//! u32    learning data, which is ignored
+//! ```
+//!
+//! A move is packed as its target file and rank in bits 0-5, its origin file and rank in
+//! bits 6-11 and its promotion in bits 12-14, from 1 for a knight to 4 for a queen.
+//! Castling is written as the king moving onto its rook, such as e1h1.
+//!
+//! Books are built from games with a [BookBuilder], which weights each move by the
+//! results of the games it was played in: two points for a win and one for a draw, for
+//! the player making the move.
+
+use board::piece::Piece;
+use rules::game_state::GameState;
+use rules::move_application::Move;
+use rules::variant::Outcome;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+
+/// The size of an entry in bytes.
+pub const ENTRY_SIZE: usize = 16;
+
+/// A single entry of a book.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BookEntry {
+    /// The Polyglot key of the position.
+    pub key: u64,
+
+    /// The move, packed as described in the module documentation.
+    pub mv: u16,
+
+    /// The weight of the move.
+    pub weight: u16,
+
+    /// The learning data, kept as read.
+    pub learn: u32,
+}
+
+impl BookEntry {
+    /// Reads an entry from its 16 bytes.
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        let mut key = [0; 8];
+        key.copy_from_slice(&bytes[0..8]);
+        BookEntry {
+            key: u64::from_be_bytes(key),
+            mv: u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn: u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        }
+    }
+
+    /// Returns the 16 bytes of the entry.
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// How a move is chosen from those a book gives for a position.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BookSelection {
+    /// The move with the greatest weight.
+    Best,
+
+    /// A move chosen at random, in proportion to its weight.
+    Weighted,
+}
+
+/// An opening book.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Book {
+    /// The entries, sorted by key.
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Returns an empty book.
+    pub fn new() -> Self {
+        Book::default()
+    }
+
+    /// Returns a book of the given entries, sorting them by key and keeping the order of
+    /// entries with the same key.
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.key);
+        Book { entries }
+    }
+
+    /// Reads a book from the format described in the module documentation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(format!(
+                "book size {} is not a multiple of {} bytes",
+                bytes.len(),
+                ENTRY_SIZE
+            ));
+        }
+        let entries = bytes
+            .chunks(ENTRY_SIZE)
+            .map(|chunk| {
+                let mut entry = [0; ENTRY_SIZE];
+                entry.copy_from_slice(chunk);
+                BookEntry::from_bytes(&entry)
+            })
+            .collect();
+        Ok(Book::from_entries(entries))
+    }
+
+    /// Loads a book from the file at the given path.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        Book::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Returns the book in the format described in the module documentation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.to_bytes().to_vec())
+            .collect()
+    }
+
+    /// Writes the book to the file at the given path.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the book has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns all the entries, sorted by key.
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    /// Returns the entries of the position with the given key.
+    pub fn probe(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Returns the legal moves the book gives for a position, with their weights, in the
+    /// order they are stored.
+    ///
+    /// Moves which are not legal in the position, as can happen when two positions share
+    /// a key, are left out.
+    pub fn moves(&self, state: &GameState) -> Vec<(Move, u16)> {
+        let legal_moves = state.legal_moves();
+        self.probe(state.polyglot_key())
+            .iter()
+            .filter_map(|entry| {
+                legal_moves
+                    .iter()
+                    .find(|mv| encode_move(state, mv) == entry.mv)
+                    .map(|mv| (mv.clone(), entry.weight))
+            })
+            .collect()
+    }
+
+    /// Chooses a move for a position, if the book has any.
+    ///
+    /// Weighted selection uses the given random value to pick among the moves, so that
+    /// moves with no weight are never chosen unless every move has none. Ties for the
+    /// best move go to the first one stored.
+    pub fn choose_move(
+        &self,
+        state: &GameState,
+        selection: BookSelection,
+        random: u64,
+    ) -> Option<Move> {
+        let moves = self.moves(state);
+        let total: u64 = moves.iter().map(|&(_, weight)| u64::from(weight)).sum();
+        if selection == BookSelection::Best || total == 0 {
+            let best = moves.iter().map(|&(_, weight)| weight).max()?;
+            return moves
+                .into_iter()
+                .find(|&(_, weight)| weight == best)
+                .map(|(mv, _)| mv);
+        }
+        let mut roll = random % total;
+        for (mv, weight) in moves {
+            if roll < u64::from(weight) {
+                return Some(mv);
+            }
+            roll -= u64::from(weight);
+        }
+        None
+    }
+}
+
+/// Packs a move in the given state into the Polyglot format.
+pub fn encode_move(state: &GameState, mv: &Move) -> u16 {
+    let target = match mv.castle {
+        Some(castle_move) => state.castle_rook_squares(castle_move, state.player_turn).0,
+        None => mv.target,
+    };
+    let promotion = match mv.promotion {
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(Piece::Queen) => 4,
+        _ => 0,
+    };
+    promotion << 12 | u16::from(mv.origin.to_index()) << 6 | u16::from(target.to_index())
+}
+
+/// Collects the moves of games and builds a book from them.
+#[derive(Clone, Debug, Default)]
+pub struct BookBuilder {
+    /// The number of times each move was played and the points it scored, by the key of
+    /// the position and the packed move.
+    moves: HashMap<(u64, u16), (u32, u32)>,
+
+    /// The order in which each key and move was first seen, so that books are built the
+    /// same way every time.
+    order: Vec<(u64, u16)>,
+}
+
+impl BookBuilder {
+    /// Returns a builder with no games.
+    pub fn new() -> Self {
+        BookBuilder::default()
+    }
+
+    /// Adds the first plies of a game, played from the given start position, up to the
+    /// given number of plies.
+    ///
+    /// Moves score two points for a win and one for a draw for the player making them.
+    /// Games with no known result add no points, but still count towards how often each
+    /// move was played.
+    pub fn add_game(
+        &mut self,
+        start: &GameState,
+        moves: &[Move],
+        outcome: Option<Outcome>,
+        max_plies: usize,
+    ) {
+        let mut state = start.clone();
+        for mv in moves.iter().take(max_plies) {
+            let key = (state.polyglot_key(), encode_move(&state, mv));
+            let points = match outcome {
+                Some(Outcome::Win(player)) if player == state.player_turn => 2,
+                Some(Outcome::Draw) => 1,
+                _ => 0,
+            };
+            let order = &mut self.order;
+            let counts = self.moves.entry(key).or_insert_with(|| {
+                order.push(key);
+                (0, 0)
+            });
+            counts.0 += 1;
+            counts.1 += points;
+            state.make_move(mv);
+        }
+    }
+
+    /// Returns the number of distinct moves collected.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns true if no moves have been collected.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Builds a book of the moves played at least the given number of times, with the
+    /// moves of each position stored from the greatest weight down.
+    ///
+    /// Moves which scored no points are left out, and weights are scaled down together if
+    /// any of them would not fit in 16 bits.
+    pub fn build(&self, min_count: u32) -> Book {
+        let kept: Vec<(u64, u16, u32)> = self
+            .order
+            .iter()
+            .map(|&(key, mv)| (key, mv, self.moves[&(key, mv)]))
+            .filter(|&(_, _, (count, points))| count >= min_count && points > 0)
+            .map(|(key, mv, (_, points))| (key, mv, points))
+            .collect();
+        let max_points = kept.iter().map(|&(_, _, points)| points).max().unwrap_or(0);
+        let scale = |points: u32| {
+            if max_points > u32::from(u16::MAX) {
+                (u64::from(points) * u64::from(u16::MAX) / u64::from(max_points)).max(1)
+            } else {
+                u64::from(points)
+            }
+        };
+        let mut entries: Vec<BookEntry> = kept
+            .into_iter()
+            .map(|(key, mv, points)| BookEntry {
+                key,
+                mv,
+                weight: scale(points) as u16,
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.key, Reverse(entry.weight)));
+        Book::from_entries(entries)
+    }
+}
@@ -1,13 +1,118 @@
 //! The alpha-beta tree-searching code.
 
 use board::player::Player;
-use engine::heuristic;
+use engine::evaluator::Evaluator;
+use engine::params::EvalParams;
+use engine::time_manager::{Iteration, TimeManager};
 use rules::game_state::GameState;
 use rules::move_application::Move;
+use rules::variant::Outcome;
 use std::cmp;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+/// The score of a position won by white. Black's wins score its negation.
+pub const WIN_SCORE: i32 = 200000;
+
+/// The deepest search made by a node or time limited search.
+pub const MAX_DEPTH: usize = 64;
+
+/// The number of nodes visited between checks of the clock in a timed search.
+pub const NODES_PER_CLOCK_CHECK: u64 = 32;
+
+/// How far a search may go.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchLimit {
+    /// Search to the given depth.
+    Depth(usize),
+
+    /// Deepen the search one ply at a time until the given number of nodes have been
+    /// visited.
+    Nodes(u64),
+
+    /// Deepen the search one ply at a time for as long as the time manager allows.
+    Time(TimeManager),
+
+    /// Deepen the search one ply at a time until it is stopped.
+    Infinite,
+}
+
+/// Flags through which a search can be controlled from another thread.
+///
+/// A pondering search ignores its time limit until [SearchSignals::ponderhit] is called,
+/// when its clock is restarted and it carries on as a timed search. A stopped search
+/// returns the best move it has found so far.
+#[derive(Debug, Default)]
+pub struct SearchSignals {
+    /// Set once the search should stop.
+    stop: AtomicBool,
+
+    /// Set while the search is pondering.
+    ponder: AtomicBool,
+
+    /// Held while a signal is set or waited on.
+    lock: Mutex<()>,
+
+    /// Notified whenever a signal is set.
+    changed: Condvar,
+}
+
+impl SearchSignals {
+    /// Returns signals for a search which is not pondering.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns signals for a search which starts by pondering.
+    pub fn pondering() -> Self {
+        SearchSignals {
+            ponder: AtomicBool::new(true),
+            ..SearchSignals::default()
+        }
+    }
+
+    /// Stops the search.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.notify();
+    }
+
+    /// Ends pondering, the opponent having played the move pondered on.
+    pub fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::SeqCst);
+        self.notify();
+    }
+
+    /// Blocks until the search is stopped or, unless it is infinite, no longer pondering,
+    /// as a search which ends by itself must wait for before it is reported.
+    pub fn wait(&self, infinite: bool) {
+        let mut guard = self.lock.lock().expect("Poisoned search signals");
+        while !self.is_stopped() && (infinite || self.is_pondering()) {
+            guard = self.changed.wait(guard).expect("Poisoned search signals");
+        }
+    }
+
+    // wakes any thread waiting for a signal, taking the lock so that the signal cannot be
+    // set between a waiting thread checking it and starting to wait
+    fn notify(&self) {
+        let _guard = self.lock.lock().expect("Poisoned search signals");
+        self.changed.notify_all();
+    }
+
+    /// Returns true once the search has been stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    /// Returns true while the search is pondering.
+    pub fn is_pondering(&self) -> bool {
+        self.ponder.load(Ordering::SeqCst)
+    }
+}
 
 /// A sequence of turns with a given score.
 ///
@@ -23,6 +128,85 @@ pub struct ScoredSequence {
 
     /// The sequence of moves.
     moves: Vec<Move>,
+
+    /// The rank of the sequence among those found for a multi-PV search, from 1 for the
+    /// best.
+    rank: usize,
+
+    /// The depth to which the sequence was searched.
+    depth: usize,
+
+    /// The number of nodes the search visited in total.
+    nodes: u64,
+}
+
+impl ScoredSequence {
+    // a best sequence searched to the given depth, from a state with the given player to
+    // move
+    //
+    // A sequence scoring a win ends in the end of the game, so the mate is counted from
+    // its moves.
+    fn new(score: i32, moves: Vec<Move>, depth: usize, player_turn: Player) -> Self {
+        let winner = match score {
+            WIN_SCORE => Some(Player::White),
+            _ if score == -WIN_SCORE => Some(Player::Black),
+            _ => None,
+        };
+        let mate = winner.map(|player| MateSummary {
+            player,
+            turns: if player == player_turn {
+                moves.len().div_ceil(2)
+            } else {
+                moves.len() / 2
+            } as u8,
+        });
+        ScoredSequence {
+            score,
+            mate,
+            moves,
+            rank: 1,
+            depth,
+            nodes: 0,
+        }
+    }
+
+    /// Returns the sequence score in centipawns. Positive means white is winning.
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    /// Returns the rank of the sequence among those found for a multi-PV search, from 1
+    /// for the best.
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// Returns the depth to which the sequence was searched.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the number of nodes the search visited in total, up to the end of the
+    /// iteration which found the sequence.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Returns the forced mate the sequence ends in, if there is one.
+    pub fn mate(&self) -> Option<&MateSummary> {
+        self.mate.as_ref()
+    }
+
+    /// Returns the sequence of moves.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    // the first move of the sequence and its score
+    fn into_move(self) -> (Move, i32) {
+        let score = self.score;
+        (self.moves.into_iter().next().unwrap(), score)
+    }
 }
 
 impl Display for ScoredSequence {
@@ -46,51 +230,153 @@ pub struct MateSummary {
     turns: u8,
 }
 
-// ---------------------------------------------------------------------
-/// Returns the score of the given position using the alpha-beta algorithm.
-fn alpha_beta_internal(state: &GameState, depth: usize, mut alpha: i32, mut beta: i32) -> i32 {
-    // if mate, exit immediately
-    if state.is_mate(Player::White) {
-        return -200000;
+impl MateSummary {
+    /// Returns the player who will mate.
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    /// Returns the number of turns in which the player will mate.
+    pub fn turns(&self) -> u8 {
+        self.turns
+    }
+}
+
+/// The progress of a search, shared by all of its nodes.
+#[derive(Debug, Default)]
+struct Progress<'a> {
+    /// The number of nodes visited.
+    nodes: u64,
+
+    /// The number of nodes after which the search is stopped.
+    max_nodes: u64,
+
+    /// The time manager of a timed search.
+    manager: Option<TimeManager>,
+
+    /// True once the node limit applies, after the first iteration.
+    limited: bool,
+
+    /// The signals controlling the search, if it can be stopped from another thread.
+    signals: Option<&'a SearchSignals>,
+
+    /// True while the search is pondering.
+    pondering: bool,
+
+    /// True once the search has been stopped.
+    aborted: bool,
+}
+
+impl<'a> Progress<'a> {
+    // counts a node, returning true if the search has been stopped
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        if self.limited && self.nodes >= self.max_nodes {
+            self.aborted = true;
+        }
+        if !self.aborted && self.nodes.is_multiple_of(NODES_PER_CLOCK_CHECK) {
+            self.check();
+        }
+        self.aborted
     }
-    if state.is_mate(Player::Black) {
-        return 200000;
+
+    // checks the signals and the clock, stopping the search if it is out of time
+    fn check(&mut self) {
+        if let Some(signals) = self.signals {
+            if signals.is_stopped() {
+                self.aborted = true;
+                return;
+            }
+            if self.pondering && !signals.is_pondering() {
+                // the pondered move was played, so the clock starts now
+                self.pondering = false;
+                if let Some(ref mut manager) = self.manager {
+                    manager.restart();
+                }
+            }
+        }
+        if !self.pondering {
+            self.aborted = self
+                .manager
+                .is_some_and(|manager| Instant::now() >= manager.deadline());
+        }
     }
+}
 
-    // if stalemate, also exit immediately
-    if state.is_stale_mate(state.player_turn) {
+// ---------------------------------------------------------------------
+/// Returns the score of the given position using the alpha-beta algorithm, filling in
+/// the principal variation: the line of best play found from it.
+///
+/// Once the search has been stopped, the scores returned are meaningless.
+fn alpha_beta_internal(
+    state: &mut GameState,
+    evaluator: &mut Evaluator,
+    progress: &mut Progress,
+    pv: &mut Vec<Move>,
+    depth: usize,
+    mut alpha: i32,
+    mut beta: i32,
+) -> i32 {
+    pv.clear();
+    if progress.visit() {
         return 0;
     }
 
+    // if the game is over, through mate, stalemate or a variant rule, exit immediately
+    match state.outcome() {
+        Some(Outcome::Win(Player::White)) => return WIN_SCORE,
+        Some(Outcome::Win(Player::Black)) => return -WIN_SCORE,
+        Some(Outcome::Draw) => return 0,
+        None => {}
+    }
+
+    // positions with few pieces have known results
+    if let Some(score) = evaluator.probe(state, depth) {
+        return score;
+    }
+
     if depth == 0 {
-        return heuristic::score(&state);
+        return evaluator.evaluate(state);
     }
 
     let moves = state.legal_moves();
+    let mut line = Vec::new();
     if state.player_turn == Player::White {
-        let mut max_eval = i32::min_value();
+        let mut max_eval = i32::MIN;
         for mv in moves {
             // TODO order move search checks + capturers -> checks normal -> captures -> normal
-            let new_state = state.apply_move(&mv);
-            let eval = alpha_beta_internal(&new_state, depth - 1, alpha, beta);
-            //            println!("{}, {}: {}", mv, new_state, eval);
-            max_eval = cmp::max(max_eval, eval);
+            let undo = evaluator.make_move(state, &mv);
+            let eval =
+                alpha_beta_internal(state, evaluator, progress, &mut line, depth - 1, alpha, beta);
+            evaluator.unmake_move(state, &mv, &undo);
+            if pv.is_empty() || eval > max_eval {
+                max_eval = eval;
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut line);
+            }
             alpha = cmp::max(alpha, eval);
-            if beta <= alpha {
+            if beta <= alpha || progress.aborted {
                 break;
             }
         }
         return max_eval;
     }
 
-    let mut min_eval = i32::max_value();
+    let mut min_eval = i32::MAX;
     for mv in moves {
-        let new_state = state.apply_move(&mv);
-        let eval = alpha_beta_internal(&new_state, depth - 1, alpha, beta);
-        //        println!("{}, {}: {}", mv, new_state, eval);
-        min_eval = cmp::min(min_eval, eval);
+        let undo = evaluator.make_move(state, &mv);
+        let eval =
+            alpha_beta_internal(state, evaluator, progress, &mut line, depth - 1, alpha, beta);
+        evaluator.unmake_move(state, &mv, &undo);
+        if pv.is_empty() || eval < min_eval {
+            min_eval = eval;
+            pv.clear();
+            pv.push(mv);
+            pv.append(&mut line);
+        }
         beta = cmp::min(beta, eval);
-        if beta <= alpha {
+        if beta <= alpha || progress.aborted {
             break;
         }
     }
@@ -98,6 +384,277 @@ fn alpha_beta_internal(state: &GameState, depth: usize, mut alpha: i32, mut beta
 }
 
 ///
-pub fn alpha_beta(state: &GameState, depth: usize) -> i32 {
-    alpha_beta_internal(state, depth, i32::min_value(), i32::max_value())
+pub fn alpha_beta(state: &GameState, params: &EvalParams, depth: usize) -> i32 {
+    alpha_beta_internal(
+        &mut state.clone(),
+        &mut Evaluator::handcrafted(params, state),
+        &mut Progress::default(),
+        &mut Vec::new(),
+        depth,
+        i32::MIN,
+        i32::MAX,
+    )
+}
+
+/// Returns the best move for the player to move and its score, searching to the given depth.
+///
+/// The score is in centipawns, positive meaning white is winning. Returns `None` if the
+/// player to move has no legal moves.
+pub fn best_move(state: &GameState, params: &EvalParams, depth: usize) -> Option<(Move, i32)> {
+    search(state, &mut Evaluator::handcrafted(params, state), depth)
+}
+
+/// Returns the best move for the player to move and its score as [best_move], using the
+/// given evaluator, which must start from the given state.
+///
+/// If the evaluator's tablebase covers the state, its best move is played without
+/// searching. If its Syzygy tables cover the state, only the moves they find best are
+/// searched.
+pub fn search(state: &GameState, evaluator: &mut Evaluator, depth: usize) -> Option<(Move, i32)> {
+    let mut lines = search_depth(state, evaluator, &mut Progress::default(), depth, &[], 1);
+    rank(&mut lines, state.player_turn);
+    lines.into_iter().next().map(ScoredSequence::into_move)
+}
+
+/// Returns the best move for the player to move and its score as [search], searching
+/// within the given limit.
+///
+/// A node limit applies once the first iteration of the deepening search is complete,
+/// and a time limit from the start, stopping the search as soon as they are reached. If
+/// an iteration is stopped, its best move is still played if it beat the best move of the
+/// previous iteration, which is searched first. If the first iteration is stopped before
+/// any move is scored, the moves are scored by their evaluation alone.
+pub fn search_limited(
+    state: &GameState,
+    evaluator: &mut Evaluator,
+    limit: SearchLimit,
+) -> Option<(Move, i32)> {
+    match limit {
+        SearchLimit::Depth(depth) => search(state, evaluator, depth),
+        _ => deepen(state, evaluator, limit, None, 1)
+            .into_iter()
+            .next()
+            .map(ScoredSequence::into_move),
+    }
+}
+
+/// Returns the principal variation of a deepening search within the given limit, as
+/// [search_limited], which can be stopped or taken off pondering through the given
+/// signals.
+///
+/// While pondering, the search deepens without regard to its time limit. Once pondering
+/// ends, the same search carries on under its time limit, the clock starting from then.
+pub fn search_signalled(
+    state: &GameState,
+    evaluator: &mut Evaluator,
+    limit: SearchLimit,
+    signals: &SearchSignals,
+) -> Option<ScoredSequence> {
+    search_multi_pv(state, evaluator, limit, signals, 1)
+        .into_iter()
+        .next()
+}
+
+/// Returns up to the given number of principal variations of a deepening search as
+/// [search_signalled], ranked from the best.
+///
+/// Each variation after the best is found by searching the root moves again without
+/// those of the variations found before it. The variations of the previous iteration are
+/// searched first. If an iteration is stopped, the variations it completed rank first,
+/// followed by the rest from the previous iteration.
+pub fn search_multi_pv(
+    state: &GameState,
+    evaluator: &mut Evaluator,
+    limit: SearchLimit,
+    signals: &SearchSignals,
+    multi_pv: usize,
+) -> Vec<ScoredSequence> {
+    deepen(state, evaluator, limit, Some(signals), multi_pv)
+}
+
+// searches one ply deeper at a time within the given limit, returning up to the given
+// number of principal variations of the last iteration, ranked from the best
+fn deepen(
+    state: &GameState,
+    evaluator: &mut Evaluator,
+    limit: SearchLimit,
+    signals: Option<&SearchSignals>,
+    multi_pv: usize,
+) -> Vec<ScoredSequence> {
+    let (max_depth, max_nodes) = match limit {
+        SearchLimit::Depth(depth) => (depth, u64::MAX),
+        SearchLimit::Nodes(max_nodes) => (MAX_DEPTH, max_nodes),
+        SearchLimit::Time(_) | SearchLimit::Infinite => (MAX_DEPTH, u64::MAX),
+    };
+    let mut progress = Progress {
+        max_nodes,
+        manager: match limit {
+            SearchLimit::Time(manager) => Some(manager),
+            _ => None,
+        },
+        signals,
+        pondering: signals.is_some_and(SearchSignals::is_pondering),
+        ..Progress::default()
+    };
+    let mut best: Vec<ScoredSequence> = Vec::new();
+    for depth in 1..max_depth + 1 {
+        let start = Instant::now();
+        let first: Vec<Move> = best.iter().map(|line| line.moves[0].clone()).collect();
+        let mut lines = search_depth(state, evaluator, &mut progress, depth, &first, multi_pv);
+        if lines.is_empty() && progress.aborted && best.is_empty() {
+            // stopped before any move was scored, which a one ply search does quickly
+            lines = search_depth(state, evaluator, &mut Progress::default(), 1, &[], 1);
+        }
+        let moves = lines.len();
+        let margin = root_margin(&lines, state.player_turn);
+        if lines.is_empty() {
+            break;
+        }
+        rank(&mut lines, state.player_turn);
+        // the variations of a stopped iteration are followed by the rest from the
+        // previous one
+        best.retain(|previous| lines.iter().all(|line| line.moves[0] != previous.moves[0]));
+        lines.append(&mut best);
+        lines.truncate(multi_pv.max(1));
+        for (index, line) in lines.iter_mut().enumerate() {
+            line.rank = index + 1;
+            line.nodes = progress.nodes;
+        }
+        best = lines;
+        if progress.aborted || progress.nodes >= max_nodes {
+            break;
+        }
+        progress.check();
+        if progress.aborted {
+            break;
+        }
+        if let Some(ref mut manager) = progress.manager {
+            let score = best[0].score;
+            let iteration = Iteration {
+                score: if state.player_turn == Player::White { score } else { -score },
+                best_move_changed: first.first().is_some_and(|first| *first != best[0].moves[0]),
+                margin,
+                moves,
+                duration: start.elapsed(),
+            };
+            // a pondering search keeps deepening until the pondered move is played
+            if manager.iteration_done(&iteration) && !progress.pondering {
+                break;
+            }
+        }
+        progress.limited = true;
+    }
+    best
+}
+
+// searches to the given depth, counting the nodes visited, and returns the principal
+// variations of up to the given number of best moves, or of every move searched if only
+// the best is asked for
+//
+// The given moves are searched first, in order. If the search is stopped, only the moves
+// searched before it stopped are returned.
+fn search_depth(
+    state: &GameState,
+    evaluator: &mut Evaluator,
+    progress: &mut Progress,
+    depth: usize,
+    first: &[Move],
+    multi_pv: usize,
+) -> Vec<ScoredSequence> {
+    if let Some((best, score)) = evaluator.root_move(state) {
+        return vec![ScoredSequence::new(score, vec![best], depth, state.player_turn)];
+    }
+    let mut state = state.clone();
+    let mut moves = evaluator.root_moves(&state);
+    for (index, first) in first.iter().enumerate() {
+        if let Some(position) = moves.iter().position(|mv| mv == first) {
+            let mv = moves.remove(position);
+            moves.insert(index.min(moves.len()), mv);
+        }
+    }
+    if multi_pv <= 1 {
+        return search_root(&mut state, evaluator, progress, depth, &moves);
+    }
+
+    // each variation is the best of the moves not already in one
+    let mut lines: Vec<ScoredSequence> = Vec::with_capacity(multi_pv);
+    while lines.len() < multi_pv && !moves.is_empty() {
+        let mut searched = search_root(&mut state, evaluator, progress, depth, &moves);
+        if searched.is_empty() || (progress.aborted && !lines.is_empty()) {
+            break;
+        }
+        rank(&mut searched, state.player_turn);
+        let best = searched.swap_remove(0);
+        moves.retain(|mv| *mv != best.moves[0]);
+        lines.push(best);
+        if progress.aborted {
+            break;
+        }
+    }
+    lines
+}
+
+// searches the given moves to the given depth, and returns the principal variations of
+// the moves searched with their scores
+//
+// Moves are only searched to find whether they beat the best so far, so the scores of
+// all but the best are bounds, no better than the moves' true scores. If the search is
+// stopped, only the moves searched before it stopped are returned.
+fn search_root(
+    state: &mut GameState,
+    evaluator: &mut Evaluator,
+    progress: &mut Progress,
+    depth: usize,
+    moves: &[Move],
+) -> Vec<ScoredSequence> {
+    let (mut alpha, mut beta) = (i32::MIN, i32::MAX);
+    let mut lines = Vec::with_capacity(moves.len());
+    let mut pv = Vec::new();
+    for mv in moves {
+        let undo = evaluator.make_move(state, mv);
+        let eval = alpha_beta_internal(
+            state,
+            evaluator,
+            progress,
+            &mut pv,
+            depth.saturating_sub(1),
+            alpha,
+            beta,
+        );
+        evaluator.unmake_move(state, mv, &undo);
+        if progress.aborted {
+            break;
+        }
+        match state.player_turn {
+            Player::White => alpha = cmp::max(alpha, eval),
+            Player::Black => beta = cmp::min(beta, eval),
+        }
+        let mut line = vec![mv.clone()];
+        line.append(&mut pv);
+        lines.push(ScoredSequence::new(eval, line, depth, state.player_turn));
+    }
+    lines
+}
+
+// sorts the scored lines from the best for the given player to move, keeping the first
+// found first among equals
+fn rank(lines: &mut [ScoredSequence], player: Player) {
+    match player {
+        Player::White => lines.sort_by_key(|line| cmp::Reverse(line.score)),
+        Player::Black => lines.sort_by_key(|line| line.score),
+    }
+}
+
+// returns how far the best score is ahead of the second best for the given player to move,
+// or a lower bound on it if the second best score is a bound
+fn root_margin(lines: &[ScoredSequence], player: Player) -> Option<i32> {
+    let mut evals: Vec<i32> = lines.iter().map(|line| line.score).collect();
+    evals.sort_unstable();
+    if player == Player::White {
+        evals.reverse();
+    }
+    match evals.as_slice() {
+        [best, second, ..] => Some((best - second).abs()),
+        _ => None,
+    }
 }
@@ -0,0 +1,220 @@
+//! Endgame knowledge.
+//!
+//! Some endgames are poorly served by the general evaluation, which counts material
+//! without knowing whether it is enough to win. This module recognises positions which
+//! are drawn by insufficient material, evaluates a few endgames with known results
+//! directly, and scales down the endgame score of drawish material balances.
+//!
+//! The knowledge only applies to standard chess, as the variants have their own ways of
+//! winning.
+
+use board::bitboard::BitBoard;
+use board::piece::Piece;
+use board::player::Player;
+use board::square::Square;
+use engine::heuristic::DARK_SQUARES;
+use engine::kpk;
+use engine::params::EvalParams;
+use rules::game_state::GameState;
+use rules::player_board::PlayerBoard;
+use rules::variant::Variant;
+
+/// The scale factor which leaves the endgame score unchanged.
+pub const SCALE_NORMAL: i32 = 64;
+
+/// The base score of an endgame known to be won, in centipawns.
+pub const KNOWN_WIN: i32 = 10000;
+
+/// The scale factor of endgames with bishops of opposite colours and only pawns.
+const SCALE_OPPOSITE_BISHOPS: i32 = 32;
+
+/// The scale factor when the stronger side has no pawns and is only a minor piece
+/// ahead, with at least a rook.
+const SCALE_MINOR_AHEAD: i32 = 14;
+
+/// The scale factor when the stronger side has no pawns and is only a minor piece
+/// ahead, against at most a minor piece.
+const SCALE_MINOR_AHEAD_OF_MINOR: i32 = 4;
+
+/// Returns true if neither player has enough material to checkmate: each side has at
+/// most a single minor piece and no pawns, or only bishops on squares of one colour.
+pub fn is_insufficient_material(state: &GameState) -> bool {
+    let (white, black) = (state.player_board(Player::White), state.player_board(Player::Black));
+    let majors_and_pawns =
+        white.pawns | white.rooks | white.queens | black.pawns | black.rooks | black.queens;
+    if !majors_and_pawns.is_empty() {
+        return false;
+    }
+    let minors = |board: PlayerBoard| board.knights.count() + board.bishops.count();
+    if minors(white) <= 1 && minors(black) <= 1 {
+        return true;
+    }
+
+    // any number of bishops on one colour cannot mate
+    let bishops = white.bishops | black.bishops;
+    let dark = BitBoard::new(DARK_SQUARES);
+    (white.knights | black.knights).is_empty()
+        && ((bishops & dark).is_empty() || (bishops & !dark).is_empty())
+}
+
+/// Returns the score of an endgame whose result is known, in centipawns from white's
+/// point of view, or `None` if the general evaluation should be used.
+///
+/// Drawn positions score zero. Won positions score at least [KNOWN_WIN], with bonuses
+/// which lead the stronger side towards the win.
+pub fn evaluate(state: &GameState, params: &EvalParams) -> Option<i32> {
+    let has_king = |board: PlayerBoard| board.king.count() == 1;
+    if !applies(state)
+        || !has_king(state.player_board(Player::White))
+        || !has_king(state.player_board(Player::Black))
+    {
+        return None;
+    }
+    if is_insufficient_material(state) {
+        return Some(0);
+    }
+    for &strong in [Player::White, Player::Black].iter() {
+        let weak_board = state.player_board(strong.other());
+        if weak_board.all() != weak_board.king {
+            continue;
+        }
+        let score = lone_king(state, params, strong)?;
+        return Some(match strong {
+            Player::White => score,
+            Player::Black => -score,
+        });
+    }
+    None
+}
+
+/// Returns the factor out of [SCALE_NORMAL] by which to scale the endgame score, given
+/// the player it favours.
+pub fn scale_factor(state: &GameState, params: &EvalParams, strong: Player) -> i32 {
+    if !applies(state) {
+        return SCALE_NORMAL;
+    }
+    let strong_board = state.player_board(strong);
+    let weak_board = state.player_board(strong.other());
+
+    // material alone does not win without pawns when only a minor piece ahead
+    if strong_board.pawns.is_empty() {
+        let strong_material = non_pawn_material(&strong_board, params);
+        let weak_material = non_pawn_material(&weak_board, params);
+        if strong_material - weak_material <= params.material.bishop.eg {
+            return if strong_material < params.material.rook.eg {
+                0
+            } else if weak_material <= params.material.bishop.eg {
+                SCALE_MINOR_AHEAD_OF_MINOR
+            } else {
+                SCALE_MINOR_AHEAD
+            };
+        }
+    }
+
+    if has_opposite_bishops(state) {
+        return SCALE_OPPOSITE_BISHOPS;
+    }
+    SCALE_NORMAL
+}
+
+// returns true if endgame knowledge applies to the state's variant
+fn applies(state: &GameState) -> bool {
+    state.variant == Variant::Standard
+}
+
+// returns true if each player has a single bishop, on squares of opposite colours, and
+// no other pieces but pawns
+fn has_opposite_bishops(state: &GameState) -> bool {
+    let only_bishop = |board: PlayerBoard| {
+        board.bishops.count() == 1
+            && (board.knights | board.rooks | board.queens).is_empty()
+    };
+    if !only_bishop(state.player_board(Player::White))
+        || !only_bishop(state.player_board(Player::Black))
+    {
+        return false;
+    }
+    let dark = BitBoard::new(DARK_SQUARES);
+    let white_dark = !(state.player_board(Player::White).bishops & dark).is_empty();
+    let black_dark = !(state.player_board(Player::Black).bishops & dark).is_empty();
+    white_dark != black_dark
+}
+
+// evaluates the endgames against a lone king from the strong side's point of view, if
+// the result is known
+fn lone_king(state: &GameState, params: &EvalParams, strong: Player) -> Option<i32> {
+    let board = state.player_board(strong);
+    let strong_king = board.king.to_square();
+    let weak_king = state.player_board(strong.other()).king.to_square();
+    let closeness = 10 * (7 - distance(strong_king, weak_king));
+
+    let only = |pieces: BitBoard, count: u32| {
+        pieces.count() == count && board.all() == pieces | board.king
+    };
+
+    // two knights cannot force mate
+    if only(board.knights, 2) {
+        return Some(0);
+    }
+
+    // king and pawn against king is looked up in the bitbase
+    if only(board.pawns, 1) {
+        let pawn = board.pawns.to_square();
+        if !kpk::is_win(strong, strong_king, weak_king, pawn, state.player_turn) {
+            return Some(0);
+        }
+        let rank = pawn.rank().to_index() as i32;
+        let advance = match strong {
+            Player::White => rank,
+            Player::Black => 7 - rank,
+        };
+        return Some(KNOWN_WIN + params.material.pawn.eg + 10 * advance);
+    }
+
+    // the lone king is driven to a corner of the bishop's colour
+    if only(board.bishops | board.knights, 2) && board.bishops.count() == 1 {
+        let dark = BitBoard::new(DARK_SQUARES);
+        let corners = if (board.bishops & dark).is_empty() {
+            [Square::new(7), Square::new(56)]
+        } else {
+            [Square::new(0), Square::new(63)]
+        };
+        let corner_distance = corners
+            .iter()
+            .map(|&corner| distance(weak_king, corner))
+            .min()
+            .unwrap_or(0);
+        return Some(KNOWN_WIN + 20 * (7 - corner_distance) + closeness);
+    }
+
+    // a queen or rook mates by driving the lone king to the edge
+    if (board.queens | board.rooks).is_empty() {
+        return None;
+    }
+    let material = non_pawn_material(&board, params) + params.material.pawn.eg
+        * board.pawns.count() as i32;
+    Some(KNOWN_WIN + material + 20 * (6 - edge_distance(weak_king)) + closeness)
+}
+
+// returns the endgame value of the pieces other than pawns and the king
+fn non_pawn_material(board: &PlayerBoard, params: &EvalParams) -> i32 {
+    [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .iter()
+        .map(|&piece| params.material.value(piece).eg * board.piece(piece).count() as i32)
+        .sum()
+}
+
+// returns the number of king moves between two squares
+fn distance(first: Square, second: Square) -> i32 {
+    let files = first.file().to_index() as i32 - second.file().to_index() as i32;
+    let ranks = first.rank().to_index() as i32 - second.rank().to_index() as i32;
+    files.abs().max(ranks.abs())
+}
+
+// returns the number of files and ranks between a square and the nearest edges, from 0
+// in a corner to 6 in the centre
+fn edge_distance(square: Square) -> i32 {
+    let file = square.file().to_index() as i32;
+    let rank = square.rank().to_index() as i32;
+    file.min(7 - file) + rank.min(7 - rank)
+}
@@ -0,0 +1,165 @@
+//! The choice between the handcrafted and network evaluations.
+
+use board::player::Player;
+use engine::accumulator::Accumulator;
+use engine::heuristic;
+use engine::nnue::{Network, NetworkAccumulator};
+use engine::params::EvalParams;
+use engine::syzygy::Syzygy;
+use engine::tablebase::Tablebase;
+use rules::game_state::GameState;
+use rules::move_application::{Move, MoveUndo};
+
+/// Evaluates the positions reached by a search.
+///
+/// Moves made during the search go through the evaluator, so that the material and
+/// piece-square scores, or the network's hidden layer, can be updated as they are made
+/// and restored as they are unmade. Positions covered by the evaluator's tablebase or
+/// Syzygy tables, if any, are scored from their tables instead.
+#[derive(Clone, Debug)]
+pub struct Evaluator<'a> {
+    evaluation: Evaluation<'a>,
+    tablebase: Option<&'a Tablebase>,
+    // the Syzygy tables and the least remaining depth at which they are probed
+    syzygy: Option<(&'a Syzygy, usize)>,
+}
+
+// the evaluation of positions not found in a tablebase
+#[derive(Clone, Debug)]
+enum Evaluation<'a> {
+    // the handcrafted evaluation, with the given weights and a stack of material and
+    // piece-square scores for the moves made so far
+    Handcrafted(&'a EvalParams, Vec<Accumulator>),
+
+    // the given network, with a stack of hidden layers for the moves made so far
+    Network(&'a Network, Vec<NetworkAccumulator>),
+}
+
+impl<'a> Evaluator<'a> {
+    /// Returns an evaluator using the handcrafted evaluation with the given weights,
+    /// starting from the given state.
+    pub fn handcrafted(params: &'a EvalParams, state: &GameState) -> Self {
+        Evaluator {
+            evaluation: Evaluation::Handcrafted(params, vec![Accumulator::new(params, state)]),
+            tablebase: None,
+            syzygy: None,
+        }
+    }
+
+    /// Returns an evaluator using the given network, starting from the given state.
+    pub fn network(network: &'a Network, state: &GameState) -> Self {
+        Evaluator {
+            evaluation: Evaluation::Network(network, vec![NetworkAccumulator::new(network, state)]),
+            tablebase: None,
+            syzygy: None,
+        }
+    }
+
+    /// Returns the evaluator probing the given tablebase.
+    pub fn with_tablebase(mut self, tablebase: &'a Tablebase) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Returns the evaluator probing the given Syzygy tables, in searches with at least
+    /// the given depth left.
+    pub fn with_syzygy(mut self, syzygy: &'a Syzygy, probe_depth: usize) -> Self {
+        self.syzygy = Some((syzygy, probe_depth));
+        self
+    }
+
+    /// Makes a move on the given state, updating the evaluation to match.
+    pub fn make_move(&mut self, state: &mut GameState, mv: &Move) -> MoveUndo {
+        match self.evaluation {
+            Evaluation::Handcrafted(params, ref mut stack) => {
+                let undo = state.make_move(mv);
+                let mut accumulator = *stack.last().expect("Empty accumulator stack");
+                accumulator.update(params, &undo.changes);
+                debug_assert!(accumulator == Accumulator::new(params, state));
+                stack.push(accumulator);
+                undo
+            }
+            Evaluation::Network(network, ref mut stack) => {
+                let undo = state.make_move(mv);
+                let mut accumulator = stack.last().expect("Empty accumulator stack").clone();
+                accumulator.update(network, &undo.changes);
+                debug_assert!(accumulator == NetworkAccumulator::new(network, state));
+                stack.push(accumulator);
+                undo
+            }
+        }
+    }
+
+    /// Unmakes a move made with [Evaluator::make_move].
+    pub fn unmake_move(&mut self, state: &mut GameState, mv: &Move, undo: &MoveUndo) {
+        state.unmake_move(mv, undo);
+        match self.evaluation {
+            Evaluation::Handcrafted(_, ref mut stack) => {
+                stack.pop();
+            }
+            Evaluation::Network(_, ref mut stack) => {
+                stack.pop();
+            }
+        }
+    }
+
+    /// Evaluates the given state, which must be the one reached by the moves made, in
+    /// centipawns. Positive values favour white.
+    pub fn evaluate(&self, state: &GameState) -> i32 {
+        if let Some(score) = self.probe(state, 0) {
+            return score;
+        }
+        match self.evaluation {
+            Evaluation::Handcrafted(params, ref stack) => heuristic::accumulated_score(
+                state,
+                params,
+                stack.last().expect("Empty accumulator stack"),
+            ),
+            Evaluation::Network(network, ref stack) => stack
+                .last()
+                .expect("Empty accumulator stack")
+                .evaluate(network, state.player_turn),
+        }
+    }
+
+    /// Returns the score of the given state from the tablebase, if it is covered, in
+    /// centipawns. Positive values favour white.
+    ///
+    /// With the given depth left to search, the Syzygy tables are also probed if it is
+    /// at least their probe depth and the last move was a capture or pawn move, as they
+    /// ignore the moves played towards the fifty-move rule.
+    pub fn probe(&self, state: &GameState, depth: usize) -> Option<i32> {
+        let score = match self.tablebase.and_then(|tablebase| tablebase.probe(state)) {
+            Some(result) => result.score(),
+            None => {
+                let (syzygy, probe_depth) = self.syzygy?;
+                if depth < probe_depth || state.draw_plies != 0 {
+                    return None;
+                }
+                syzygy.probe_wdl(state)?.score()
+            }
+        };
+        Some(match state.player_turn {
+            Player::White => score,
+            Player::Black => -score,
+        })
+    }
+
+    /// Returns the best move of the given state from the tablebase and its score as
+    /// [Evaluator::probe], if the state and its moves are covered.
+    pub fn root_move(&self, state: &GameState) -> Option<(Move, i32)> {
+        let (mv, result) = self.tablebase?.best_move(state)?;
+        Some(match state.player_turn {
+            Player::White => (mv, result.score()),
+            Player::Black => (mv, -result.score()),
+        })
+    }
+
+    /// Returns the moves of the given state to search: those the Syzygy tables find
+    /// best if they cover the state, or else every legal move.
+    pub fn root_moves(&self, state: &GameState) -> Vec<Move> {
+        self.syzygy
+            .and_then(|(syzygy, _)| syzygy.root_moves(state))
+            .unwrap_or_else(|| state.legal_moves())
+    }
+}
@@ -1,214 +1,644 @@
 //! The heuristic function used for the engine.
 
 use board::bitboard::BitBoard;
-use board::file::File;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use engine::accumulator::Accumulator;
+use engine::endgame;
+use engine::endgame::SCALE_NORMAL;
+use engine::king_safety;
+use engine::params::{EvalParams, SquareTable};
+use engine::pawn_structure;
+use engine::pawn_structure::{PawnEntry, PawnTable};
+use rules::basic_moves;
 use rules::game_state::GameState;
+use rules::pocket::Pocket;
+use std::cell::RefCell;
+use std::fmt;
 use std::i32;
+use std::ops::{Add, Mul, Sub};
+
+/// The game phase of a position with all of its starting pieces.
+pub const MAX_PHASE: i32 = 24;
+
+/// A pair of middlegame and endgame scores, in centipawns.
+///
+/// The two are blended according to the game phase by [Score::taper]. Scores are
+/// written to files as a pair, such as `[100, 120]`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(from = "(i32, i32)", into = "(i32, i32)")]
+pub struct Score {
+    /// The score when all pieces are on the board.
+    pub mg: i32,
+
+    /// The score when only kings and pawns remain.
+    pub eg: i32,
+}
+
+impl Score {
+    /// Returns a new score from its middlegame and endgame values.
+    pub fn new(mg: i32, eg: i32) -> Self {
+        Score { mg, eg }
+    }
+
+    /// Interpolates between the middlegame and endgame values for the given phase,
+    /// which ranges from 0 (endgame) to [MAX_PHASE] (middlegame).
+    pub fn taper(&self, phase: i32) -> i32 {
+        let phase = phase.clamp(0, MAX_PHASE);
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl From<(i32, i32)> for Score {
+    fn from((mg, eg): (i32, i32)) -> Self {
+        Score::new(mg, eg)
+    }
+}
+
+impl From<Score> for (i32, i32) {
+    fn from(score: Score) -> Self {
+        (score.mg, score.eg)
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+
+    fn mul(self, factor: i32) -> Score {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+/// The number of pawn structures cached by each thread.
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+thread_local! {
+    static PAWN_TABLE: RefCell<PawnTable> = RefCell::new(PawnTable::new(PAWN_TABLE_SIZE));
+}
+
+/// A game state being evaluated, with the parts of its evaluation shared by several
+/// terms computed once.
+#[derive(Clone, Copy, Debug)]
+pub struct Position<'a> {
+    /// The game state.
+    pub state: &'a GameState,
+
+    /// The evaluation of the state's pawn structure, from the pawn table.
+    pub pawns: PawnEntry,
+
+    // the squares attacked by each player's pieces, including their own, white first
+    attacks: [BitBoard; 2],
+}
+
+impl<'a> Position<'a> {
+    /// Probes the pawn table and computes the attacks of both players for the given state.
+    pub fn new(state: &'a GameState) -> Self {
+        Position {
+            state,
+            pawns: PAWN_TABLE.with(|table| table.borrow_mut().probe(state)),
+            attacks: [attacks(state, Player::White), attacks(state, Player::Black)],
+        }
+    }
+
+    /// Returns all squares attacked by the given player's pieces, including their own.
+    pub fn attacks(&self, player: Player) -> BitBoard {
+        self.attacks[player as usize]
+    }
+}
+
+/// The dark squares of the board, such as A1.
+pub const DARK_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
 
 /// Quickly computes the score of the given game state, in centipawns.
 /// Positive values indicate that white is winning, negative values indicate that
 /// black is winning. 0 indicates a drawn position.
 /// A marginal score of 100 roughly indicates an advantage of a pawn.
-pub fn score(state: &GameState) -> i32 {
-    pawns(state) + knights(state) + bishops(state) + rooks(state) + queens(state) + king(state)
-        + mobility(state) + defended_pieces(state)
+///
+/// Endgames with known results are scored directly, and the endgame score of drawish
+/// material balances is scaled down.
+pub fn score(state: &GameState, params: &EvalParams) -> i32 {
+    if let Some(score) = endgame::evaluate(state, params) {
+        return score;
+    }
+    let score = tapered_score(state, params);
+    scale(score, endgame_scale(state, params, score)).taper(phase(state))
 }
 
-// ---------------------------------------------------------------------
-fn pawns(state: &GameState) -> i32 {
-    let diff: i32 = state.white_board.pawns.count() as i32 - state.black_board.pawns.count() as i32;
-    let material = diff * 100;
-
-    // penalties for doubled pawns
-    let black_doubled = File::iter()
-        .map(|file| file.to_bitboard())
-        .map(|bb| bb & state.black_board.pawns)
-        .filter(|bb| bb.count() > 1)
-        .count() as i32;
-
-    let white_doubled = File::iter()
-        .map(|file| file.to_bitboard())
-        .map(|bb| bb & state.white_board.pawns)
-        .filter(|bb| bb.count() > 1)
-        .count() as i32;
-
-    // penalties for isolated pawns
-    let black_isolated = File::iter()
-        .filter(|file| {
-            let pawns_on_file = state.black_board.pawns & file.to_bitboard();
-            !pawns_on_file.is_empty()
-        })
-        .map(|file| {
-            let next = file.next()
-                .map_or(BitBoard::empty(), |file| file.to_bitboard());
-            let prev = file.prev()
-                .map_or(BitBoard::empty(), |file| file.to_bitboard());
-            next | prev
-        })
-        .filter(|&bb| {
-            let adjacent_pawns = state.black_board.pawns & bb;
-            adjacent_pawns.is_empty()
-        })
-        .count() as i32;
+/// Computes [score] with the material and piece-square scores read from the given
+/// accumulator, which must hold them for the same state and weights, rather than summed
+/// over every piece.
+pub fn accumulated_score(state: &GameState, params: &EvalParams, accumulator: &Accumulator) -> i32 {
+    if let Some(score) = endgame::evaluate(state, params) {
+        return score;
+    }
+    let position = Position::new(state);
+    let score = sum_terms(&TERMS[ACCUMULATED_TERMS..], &position, params, accumulator.total());
+    scale(score, endgame_scale(state, params, score)).taper(phase(state))
+}
+
+/// Computes the middlegame and endgame scores of the given game state, before they
+/// are blended by the game phase.
+pub fn tapered_score(state: &GameState, params: &EvalParams) -> Score {
+    sum_terms(&TERMS, &Position::new(state), params, Score::default())
+}
+
+// adds the scores of the given terms to a score
+fn sum_terms(
+    terms: &[(&str, Term)],
+    position: &Position,
+    params: &EvalParams,
+    score: Score,
+) -> Score {
+    terms.iter().fold(score, |score, &(_, term)| {
+        score + term(position, params, Player::White) - term(position, params, Player::Black)
+    })
+}
+
+/// A term of the evaluation, scoring a position for one of the players.
+pub type Term = fn(&Position, &EvalParams, Player) -> Score;
+
+// the number of terms at the start of TERMS whose scores are kept by an accumulator
+const ACCUMULATED_TERMS: usize = 7;
 
-    let white_isolated = File::iter()
-        .filter(|file| {
-            let pawns_on_file = state.white_board.pawns & file.to_bitboard();
-            !pawns_on_file.is_empty()
+/// The terms of the evaluation, with the names they are traced under.
+///
+/// The material and piece-square terms come first, as these are the scores kept by an
+/// [Accumulator].
+pub static TERMS: [(&str, Term); 30] = [
+    ("Material", material),
+    ("Pawn squares", pawn_squares),
+    ("Knight squares", knight_squares),
+    ("Bishop squares", bishop_squares),
+    ("Rook squares", rook_squares),
+    ("Queen squares", queen_squares),
+    ("King squares", king_squares),
+    ("Pockets", pockets),
+    ("Doubled pawns", doubled_pawns),
+    ("Isolated pawns", isolated_pawns),
+    ("Pawn islands", pawn_islands),
+    ("Passed pawns", passed_pawns),
+    ("Blocked passers", blocked_passers),
+    ("Candidate passers", candidate_passers),
+    ("Connected pawns", connected_pawns),
+    ("Phalanx pawns", phalanx_pawns),
+    ("Backward pawns", backward_pawns),
+    ("Knight pawns", knight_pawns),
+    ("Bishop pair", bishop_pair),
+    ("Bad bishops", bad_bishops),
+    ("Rook files", rook_files),
+    ("Rook on seventh", rook_seventh),
+    ("Rook on queen file", rook_queen_file),
+    ("Connected rooks", connected_rooks),
+    ("Early queen", early_queen),
+    ("Mobility", mobility),
+    ("Undefended minors", defended_pieces),
+    ("King shelter", king_safety::shelter),
+    ("King attackers", king_safety::attackers),
+    ("Lost castling", king_safety::castling),
+];
+
+/// The scores of one term of the evaluation for each player.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TraceTerm {
+    /// The name of the term.
+    pub name: &'static str,
+
+    /// White's score for the term.
+    pub white: Score,
+
+    /// Black's score for the term.
+    pub black: Score,
+}
+
+impl TraceTerm {
+    /// Returns the score of the term. Positive values favour white.
+    pub fn total(&self) -> Score {
+        self.white - self.black
+    }
+}
+
+/// A breakdown of the evaluation of a position into its terms.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Trace {
+    /// The scores of each term, in the order of [TERMS].
+    pub terms: Vec<TraceTerm>,
+
+    /// The game phase of the position.
+    pub phase: i32,
+
+    /// The factor out of [SCALE_NORMAL] applied to the endgame score.
+    pub scale: i32,
+
+    /// The score of an endgame with a known result, which replaces the terms.
+    pub known: Option<i32>,
+}
+
+impl Trace {
+    /// Returns the sum of all of the terms, before it is tapered.
+    pub fn total(&self) -> Score {
+        self.terms
+            .iter()
+            .fold(Score::default(), |score, term| score + term.total())
+    }
+
+    /// Returns the final score, which is the same as [score] for the traced position.
+    pub fn score(&self) -> i32 {
+        match self.known {
+            Some(score) => score,
+            None => scale(self.total(), self.scale).taper(self.phase),
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter, name: &str, white: &str, black: &str, total: &str| {
+            let row = format!("{:<20}|{:^15}|{:^15}|{:^15}", name, white, black, total);
+            writeln!(f, "{}", row.trim_end())
+        };
+        let pair = |score: Score| format!("{:>6} {:>6}", score.mg, score.eg);
+        let header = format!("{:>6} {:>6}", "MG", "EG");
+        let rule = "-".repeat(20 + 3 * 16);
+
+        line(f, "Term", "White", "Black", "Total")?;
+        line(f, "", &header, &header, &header)?;
+        writeln!(f, "{}", rule)?;
+        for term in self.terms.iter() {
+            line(
+                f,
+                term.name,
+                &pair(term.white),
+                &pair(term.black),
+                &pair(term.total()),
+            )?;
+        }
+        writeln!(f, "{}", rule)?;
+        line(f, "Total", "", "", &pair(self.total()))?;
+        writeln!(f)?;
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        if self.scale != SCALE_NORMAL {
+            writeln!(f, "Endgame scale: {}/{}", self.scale, SCALE_NORMAL)?;
+        }
+        if self.known.is_some() {
+            writeln!(f, "Known endgame")?;
+        }
+        write!(f, "Score: {} (white side)", self.score())
+    }
+}
+
+/// Breaks down the evaluation of the given game state into the scores of each term.
+pub fn trace(state: &GameState, params: &EvalParams) -> Trace {
+    let position = Position::new(state);
+    let terms = TERMS
+        .iter()
+        .map(|&(name, term)| TraceTerm {
+            name,
+            white: term(&position, params, Player::White),
+            black: term(&position, params, Player::Black),
         })
-        .map(|file| {
-            let next = file.next()
-                .map_or(BitBoard::empty(), |file| file.to_bitboard());
-            let prev = file.prev()
-                .map_or(BitBoard::empty(), |file| file.to_bitboard());
-            next | prev
+        .collect::<Vec<TraceTerm>>();
+    let total = terms
+        .iter()
+        .fold(Score::default(), |score, term| score + term.total());
+    Trace {
+        terms,
+        phase: phase(state),
+        scale: endgame_scale(state, params, total),
+        known: endgame::evaluate(state, params),
+    }
+}
+
+// returns the scale factor of the endgame score, for the player it favours
+fn endgame_scale(state: &GameState, params: &EvalParams, score: Score) -> i32 {
+    let strong = if score.eg >= 0 { Player::White } else { Player::Black };
+    endgame::scale_factor(state, params, strong)
+}
+
+// scales the endgame part of a score by a factor out of SCALE_NORMAL
+fn scale(score: Score, factor: i32) -> Score {
+    Score::new(score.mg, score.eg * factor / SCALE_NORMAL)
+}
+
+/// Returns the game phase of the given state, from 0 when only kings and pawns remain
+/// to [MAX_PHASE] when all pieces are present.
+///
+/// Minor pieces count 1 towards the phase, rooks 2 and queens 4, including pieces held
+/// in hand.
+pub fn phase(state: &GameState) -> i32 {
+    let weight = |minors: u32, rooks: u32, queens: u32| (minors + rooks * 2 + queens * 4) as i32;
+    let board_phase: i32 = [state.player_board(Player::White), state.player_board(Player::Black)]
+        .iter()
+        .map(|board| {
+            weight(
+                board.knights.count() + board.bishops.count(),
+                board.rooks.count(),
+                board.queens.count(),
+            )
         })
-        .filter(|&bb| {
-            let adjacent_pawns = state.white_board.pawns & bb;
-            adjacent_pawns.is_empty()
+        .sum();
+    let pocket_phase: i32 = [state.white_pocket, state.black_pocket]
+        .iter()
+        .map(|pocket| {
+            weight(
+                (pocket.knights + pocket.bishops) as u32,
+                pocket.rooks as u32,
+                pocket.queens as u32,
+            )
         })
-        .count() as i32;
+        .sum();
+    (board_phase + pocket_phase).min(MAX_PHASE)
+}
 
-    // position mask
-    static PAWN_MASK: [i32; 64] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 50, 50, 50, 50, 50, 50, 50, 50, 10, 10, 20, 30, 30, 20, 10, 10, 5,
-        5, 10, 25, 25, 10, 5, 5, 0, 0, 0, 20, 20, 0, 0, 0, 5, -5, -10, 0, 0, -10, -5, 5, 5, 10, 10,
-        -20, -20, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0,
-    ];
+// ---------------------------------------------------------------------
+fn material(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let values = &params.material;
+    let board = position.state.player_board(player);
+    values.pawn * board.pawns.count() as i32 + values.knight * board.knights.count() as i32
+        + values.bishop * board.bishops.count() as i32
+        + values.rook * board.rooks.count() as i32
+        + values.queen * board.queens.count() as i32
+}
 
-    let white_mask = apply_mask(state.white_board.pawns, &PAWN_MASK);
-    let black_mask = apply_mask_flipped(state.black_board.pawns, &PAWN_MASK);
+// held pieces are worth their material, as they can be dropped anywhere
+fn pockets(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let values = &params.material;
+    let pocket: Pocket = position.state.pocket(player);
+    values.pawn * pocket.pawns as i32 + values.knight * pocket.knights as i32
+        + values.bishop * pocket.bishops as i32 + values.rook * pocket.rooks as i32
+        + values.queen * pocket.queens as i32
+}
 
-    material + (black_doubled * 50) + (white_doubled * -50) + (black_isolated * 50)
-        + (white_isolated * -50) + white_mask - black_mask
+// ---------------------------------------------------------------------
+fn pawn_squares(position: &Position, params: &EvalParams, player: Player) -> Score {
+    apply_masks(position.state.player_board(player).pawns, player, &params.squares.pawn)
 
     // TODO
-    // bonus for chain and center control
+    // bonus for center control
     // penalty for no pawns
 }
 
-fn knights(state: &GameState) -> i32 {
-    let diff: i32 =
-        state.white_board.knights.count() as i32 - state.black_board.knights.count() as i32;
-    let material = diff * 300;
+fn knight_squares(position: &Position, params: &EvalParams, player: Player) -> Score {
+    apply_masks(position.state.player_board(player).knights, player, &params.squares.knight)
+    // knight pair penalty (second knight is worth less)
+}
 
-    // position mask
-    static KNIGHT_MASK: [i32; 64] = [
-        -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15,
-        10, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 10, 15,
-        15, 10, 5, -30, -40, -20, 0, 5, 5, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
-    ];
+fn bishop_squares(position: &Position, params: &EvalParams, player: Player) -> Score {
+    apply_masks(position.state.player_board(player).bishops, player, &params.squares.bishop)
+}
 
-    let white_mask = apply_mask(state.white_board.knights, &KNIGHT_MASK);
-    let black_mask = apply_mask_flipped(state.black_board.knights, &KNIGHT_MASK);
+fn rook_squares(position: &Position, params: &EvalParams, player: Player) -> Score {
+    apply_masks(position.state.player_board(player).rooks, player, &params.squares.rook)
+    // penalty for rook pair
+    // bonus as pawns disappear
+}
 
-    material + white_mask - black_mask
-    // decrease in value if fewer pawns
-    // knight pair penalty (second knight is worth less)
+fn queen_squares(position: &Position, params: &EvalParams, player: Player) -> Score {
+    apply_masks(position.state.player_board(player).queens, player, &params.squares.queen)
 }
 
-fn bishops(state: &GameState) -> i32 {
-    let diff: i32 =
-        state.white_board.bishops.count() as i32 - state.black_board.bishops.count() as i32;
-    let material = diff * 300;
+fn king_squares(position: &Position, params: &EvalParams, player: Player) -> Score {
+    apply_masks(position.state.player_board(player).king, player, &params.squares.king)
+}
 
-    // position mask
-    static BISHOP_MASK: [i32; 64] = [
-        -20, -10, -10, -10, -10, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 10, 10, 5,
-        0, -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 10, 10, 10, 10,
-        10, 10, -10, -10, 5, 0, 0, 0, 0, 5, -10, -20, -10, -10, -10, -10, -10, -10, -20,
-    ];
+// ---------------------------------------------------------------------
+/// Adjusts the value of knights by the number of their own pawns, as knights lose value
+/// when pawns disappear and the board opens up.
+pub fn knight_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let board = position.state.player_board(player);
+    params.pieces.knight_pawns * (board.knights.count() as i32 * (board.pawns.count() as i32 - 5))
+}
+
+/// Gives a bonus for a pair of bishops covering both colours of square.
+pub fn bishop_pair(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let bishops = position.state.player_board(player).bishops;
+    let dark = BitBoard::new(DARK_SQUARES);
+    if !(bishops & dark).is_empty() && !(bishops & !dark).is_empty() {
+        params.pieces.bishop_pair
+    } else {
+        Score::default()
+    }
+}
 
-    let white_mask = apply_mask(state.white_board.bishops, &BISHOP_MASK);
-    let black_mask = apply_mask_flipped(state.black_board.bishops, &BISHOP_MASK);
+/// Penalises bishops for each of their own pawns on squares of their colour, which
+/// hamper them.
+pub fn bad_bishops(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let board = position.state.player_board(player);
+    let dark = BitBoard::new(DARK_SQUARES);
+    board.bishops.iter().fold(Score::default(), |score, square| {
+        let colour = if dark.is_square_set(square) { dark } else { !dark };
+        score + params.pieces.bad_bishop * (board.pawns & colour).count() as i32
+    })
+}
 
-    material + white_mask - black_mask
-    // bonus for bishop pair
-    // penalty for bad bishop
-    //
+/// Gives a bonus for rooks on files without their own pawns, which is larger if the file
+/// has no pawns at all.
+pub fn rook_files(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let board = position.state.player_board(player);
+    let opponent_pawns = position.state.player_board(player.other()).pawns;
+    board.rooks.iter().fold(Score::default(), |score, square| {
+        let file = square.file().to_bitboard();
+        if !(board.pawns & file).is_empty() {
+            score
+        } else if (opponent_pawns & file).is_empty() {
+            score + params.pieces.rook_open_file
+        } else {
+            score + params.pieces.rook_semi_open_file
+        }
+    })
 }
 
-fn rooks(state: &GameState) -> i32 {
-    let diff: i32 = state.white_board.rooks.count() as i32 - state.black_board.rooks.count() as i32;
-    let material = diff * 500;
+/// Gives a bonus for rooks on the seventh rank, if the opponent's king is trapped on the
+/// eighth or it has pawns there to attack.
+pub fn rook_seventh(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let board = position.state.player_board(player);
+    let opponent_board = position.state.player_board(player.other());
+    let (seventh, eighth) = match player {
+        Player::White => (Rank::Seven, Rank::Eight),
+        Player::Black => (Rank::Two, Rank::One),
+    };
+    let targets = !(opponent_board.king & eighth.to_bitboard()).is_empty()
+        || !(opponent_board.pawns & seventh.to_bitboard()).is_empty();
+    if targets {
+        params.pieces.rook_seventh * (board.rooks & seventh.to_bitboard()).count() as i32
+    } else {
+        Score::default()
+    }
+}
 
-    // position mask
-    static ROOK_MASK: [i32; 64] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, 10, 10, 10, 10, 5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0,
-        0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0,
-        -5, 0, 0, 0, 5, 5, 0, 0, 0,
-    ];
+/// Gives a bonus for rooks on the same file as an enemy queen.
+pub fn rook_queen_file(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let opponent_queens = position.state.player_board(player.other()).queens;
+    let rooks = position
+        .state
+        .player_board(player)
+        .rooks
+        .iter()
+        .filter(|square| !(opponent_queens & square.file().to_bitboard()).is_empty())
+        .count();
+    params.pieces.rook_queen_file * rooks as i32
+}
 
-    let white_mask = apply_mask(state.white_board.rooks, &ROOK_MASK);
-    let black_mask = apply_mask_flipped(state.black_board.rooks, &ROOK_MASK);
+/// Gives a bonus for each pair of rooks defending each other along a rank or file.
+pub fn connected_rooks(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let rooks = position.state.player_board(player).rooks;
+    let blockers = position.state.occupied();
+    // each pair is counted from the rook on the lower square
+    let pairs = rooks
+        .iter()
+        .filter(|&square| {
+            (Piece::Rook.attacked_squares(square, player, blockers) & rooks)
+                .iter()
+                .any(|other| other.to_index() > square.to_index())
+        })
+        .count();
+    params.pieces.connected_rooks * pairs as i32
+}
 
-    material + white_mask - black_mask
-    // penalty for rook pair
-    // bonus as pawns disappear
-    // bonus for open file
-    // bonus for 7th rank
-    // bonus if enemy queen on same file
-    // bonus for connected rooks
-}
-
-fn queens(state: &GameState) -> i32 {
-    let diff: i32 =
-        state.white_board.queens.count() as i32 - state.black_board.queens.count() as i32;
-    let material = diff * 900;
-
-    // position mask
-    static QUEEN_MASK: [i32; 64] = [
-        -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 5, 5, 5, 0,
-        -10, -5, 0, 5, 5, 5, 5, 0, -5, 0, 0, 5, 5, 5, 5, 0, -5, -10, 5, 5, 5, 5, 5, 0, -10, -10, 0,
-        5, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
+/// Penalises a queen which has left the back rank, for each of its own knights and
+/// bishops still there.
+pub fn early_queen(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let board = position.state.player_board(player);
+    let back_rank = match player {
+        Player::White => Rank::One,
+        Player::Black => Rank::Eight,
+    }.to_bitboard();
+    if board.queens.is_empty() || !(board.queens & back_rank).is_empty() {
+        return Score::default();
+    }
+    params.pieces.early_queen * ((board.knights | board.bishops) & back_rank).count() as i32
+}
+
+// ---------------------------------------------------------------------
+/// Scores the mobility of the pieces other than pawns and kings.
+///
+/// Each piece earns a bonus for every square it attacks which is neither occupied by
+/// its own pieces nor attacked by an enemy pawn, relative to a typical number of such
+/// squares. Pieces in the opponent's half with no safe squares are penalised as trapped.
+pub fn mobility(position: &Position, params: &EvalParams, player: Player) -> Score {
+    // each piece's weight and typical number of safe squares
+    let weights = &params.mobility;
+    let pieces = [
+        (Piece::Knight, weights.knight, 4),
+        (Piece::Bishop, weights.bishop, 6),
+        (Piece::Rook, weights.rook, 7),
+        (Piece::Queen, weights.queen, 13),
     ];
 
-    let white_mask = apply_mask(state.white_board.queens, &QUEEN_MASK);
-    let black_mask = apply_mask_flipped(state.black_board.queens, &QUEEN_MASK);
+    let own_board = position.state.player_board(player);
+    let opponent_board = position.state.player_board(player.other());
+    let blockers = own_board.all() | opponent_board.all();
+    let safe = !own_board.all() & !pawn_attacks(opponent_board.pawns, player.other());
+    let opponent_half = match player {
+        Player::White => BitBoard::new(0xFFFF_FFFF_0000_0000),
+        Player::Black => BitBoard::new(0x0000_0000_FFFF_FFFF),
+    };
+
+    let mut score = Score::default();
+    for &(piece, weight, typical) in pieces.iter() {
+        for square in own_board.piece(piece).iter() {
+            let targets = piece.attacked_squares(square, player, blockers) & safe;
+            let count = targets.count() as i32;
+            score = score + weight * (count - typical);
+            if count == 0 && opponent_half.is_square_set(square) {
+                score = score + weights.trapped;
+            }
+        }
+    }
+    score
+    // mobility for knights/bishops is more important early
+}
 
-    material + white_mask - black_mask
-    // penalty for early development
+/// Penalises knights and bishops which are not protected by any of their own pieces.
+pub fn defended_pieces(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let board = position.state.player_board(player);
+    let minors = board.knights | board.bishops;
+    params.pieces.undefended_minor * (minors & !position.attacks(player)).count() as i32
 }
 
-fn king(state: &GameState) -> i32 {
-    // position mask
-    static KING_MASK: [i32; 64] = [
-        -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40,
-        -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40,
-        -40, -30, -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20,
-        30, 10, 0, 0, 10, 30, 20,
-    ];
+// ---------------------------------------------------------------------
+fn doubled_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).doubled
+}
 
-    static KING_LATE_MASK: [i32; 64] = [
-        -50, -40, -30, -20, -20, -30, -40, -50, -30, -20, -10, 0, 0, -10, -20, -30, -30, -10, 20,
-        30, 30, 20, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 30, 40, 40, 30, -10,
-        -30, -30, -10, 20, 30, 30, 20, -10, -30, -30, -30, 0, 0, 0, 0, -30, -30, -50, -30, -30,
-        -30, -30, -30, -30, -50,
-    ];
+fn isolated_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).isolated
+}
 
-    let white_mask = apply_mask(state.white_board.king, &KING_MASK);
-    let black_mask = apply_mask_flipped(state.black_board.king, &KING_MASK);
+fn pawn_islands(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).islands
+}
 
-    white_mask - black_mask
-    // king should be safe in early/mid game
-    // king should be active late game
-    // castling should be encouraged in this method
+fn passed_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).passed
 }
 
-// ---------------------------------------------------------------------
-fn mobility(_state: &GameState) -> i32 {
-    0
-    // +0.1 for each legal move
-    // trapped pieces?
-    // mobility for knights/bishops is more important early
+fn blocked_passers(position: &Position, params: &EvalParams, player: Player) -> Score {
+    pawn_structure::blocked_passers(position.state, params, &position.pawns, player)
+}
+
+fn candidate_passers(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).candidate
+}
+
+fn connected_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).connected
+}
+
+fn phalanx_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).phalanx
 }
 
-fn defended_pieces(_state: &GameState) -> i32 {
-    0
-    // penalty for undefended minor piece
+fn backward_pawns(position: &Position, params: &EvalParams, player: Player) -> Score {
+    position.pawns.scores(params, player).backward
+}
+
+// returns the squares attacked by the given pawns
+fn pawn_attacks(pawns: BitBoard, player: Player) -> BitBoard {
+    pawns.iter().fold(BitBoard::empty(), |attacks, square| {
+        attacks | basic_moves::pawn_attacks(square, player)
+    })
+}
+
+// returns all squares attacked by the given player's pieces, including their own
+fn attacks(state: &GameState, player: Player) -> BitBoard {
+    let board = state.player_board(player);
+    let blockers = state.occupied();
+    Piece::iter().fold(BitBoard::empty(), |attacks, piece| {
+        board.piece(piece).iter().fold(attacks, |attacks, square| {
+            attacks | piece.attacked_squares(square, player, blockers)
+        })
+    })
 }
 
 // ---------------------------------------------------------------------
+// applies a pair of middlegame and endgame masks to the given player's pieces
+fn apply_masks(pieces: BitBoard, player: Player, table: &SquareTable) -> Score {
+    match player {
+        Player::White => Score::new(apply_mask(pieces, &table.mg), apply_mask(pieces, &table.eg)),
+        Player::Black => Score::new(
+            apply_mask_flipped(pieces, &table.mg),
+            apply_mask_flipped(pieces, &table.eg),
+        ),
+    }
+}
+
 /// Applies a mask to the given bitboard.
 ///
 /// The mask is given as an array of ranks from 8 to 1, ie
@@ -218,7 +648,7 @@ fn defended_pieces(_state: &GameState) -> i32 {
 /// ...
 /// ]
 /// ```
-fn apply_mask_flipped(bitboard: BitBoard, mask: &[i32; 64]) -> i32 {
+fn apply_mask_flipped(bitboard: BitBoard, mask: &[i32]) -> i32 {
     let mut iterator = bitboard.to_u64();
     let mut sum: i32 = 0;
     while iterator > 0 {
@@ -231,6 +661,6 @@ fn apply_mask_flipped(bitboard: BitBoard, mask: &[i32; 64]) -> i32 {
 
 // the mask is flipped by default.
 /// Applies a mask to the given bitboard.
-pub fn apply_mask(bitboard: BitBoard, mask: &[i32; 64]) -> i32 {
+pub fn apply_mask(bitboard: BitBoard, mask: &[i32]) -> i32 {
     apply_mask_flipped(bitboard.mirror_horizontal(), mask)
 }
@@ -0,0 +1,152 @@
+//! Evaluation of the safety of each king.
+//!
+//! A king is safer behind a shield of its own pawns, away from open files and out of
+//! reach of enemy pieces. These terms only have middlegame values, so they fade out as
+//! pieces are exchanged and the king becomes an active piece.
+
+use board::file::File;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use board::square::Square;
+use engine::heuristic::{Position, Score};
+use engine::params::EvalParams;
+use rules::basic_moves;
+use rules::game_state::GameState;
+
+/// Scores the safety of both kings. Positive values favour white.
+pub fn king_safety(state: &GameState, params: &EvalParams) -> Score {
+    let position = Position::new(state);
+    let safety = |player: Player| {
+        shelter(&position, params, player) + attackers(&position, params, player)
+            + castling(&position, params, player)
+    };
+    safety(Player::White) - safety(Player::Black)
+}
+
+/// Scores the pawn shield, pawn storm and open files on the given player's king file and
+/// the files either side.
+pub fn shelter(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let state = position.state;
+    let king = match king_square(state, player) {
+        Some(king) => king,
+        None => return Score::default(),
+    };
+    let own_pawns = state.player_board(player).pawns;
+    let opponent_pawns = state.player_board(player.other()).pawns;
+    let weights = &params.king;
+    let files = [king.file().prev(), Some(king.file()), king.file().next()];
+    files
+        .iter()
+        .filter_map(|&file| file)
+        .fold(Score::default(), |mut score, file| {
+            let file_board = file.to_bitboard();
+            if (own_pawns & file_board).is_empty() {
+                score = score + if (opponent_pawns & file_board).is_empty() {
+                    weights.open_file
+                } else {
+                    weights.semi_open_file
+                };
+            }
+
+            for (distance, &bonus) in weights.shield.iter().enumerate().skip(1) {
+                if let Some(square) = ahead(king, file, distance, player) {
+                    if own_pawns.is_square_set(square) {
+                        score = score + bonus;
+                    }
+                }
+            }
+
+            // only the closest storming pawn on each file counts
+            let storm = (1..weights.storm.len()).find(|&distance| {
+                ahead(king, file, distance, player)
+                    .is_some_and(|square| opponent_pawns.is_square_set(square))
+            });
+            if let Some(distance) = storm {
+                score = score + weights.storm[distance];
+            }
+            score
+        })
+}
+
+/// Scores the enemy pieces attacking the squares around the given player's king.
+pub fn attackers(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let state = position.state;
+    let king = match king_square(state, player) {
+        Some(king) => king,
+        None => return Score::default(),
+    };
+    let zone = basic_moves::king(king) | king.to_bitboard();
+    let blockers = state.occupied();
+    let opponent_board = state.player_board(player.other());
+    let weights = &params.king;
+    let attack_weights = [
+        (Piece::Knight, weights.knight_attack),
+        (Piece::Bishop, weights.bishop_attack),
+        (Piece::Rook, weights.rook_attack),
+        (Piece::Queen, weights.queen_attack),
+    ];
+
+    let mut count = 0;
+    let mut weight = 0;
+    for &(piece, piece_weight) in attack_weights.iter() {
+        for square in opponent_board.piece(piece).iter() {
+            let attacked = piece.attacked_squares(square, player.other(), blockers) & zone;
+            if !attacked.is_empty() {
+                count += 1;
+                weight += piece_weight * attacked.count() as i32;
+            }
+        }
+    }
+
+    let scale = weights.attacker_scale[count.min(weights.attacker_scale.len() - 1)];
+    Score::new(-weight * weights.attack_unit * scale / 100, 0)
+}
+
+/// Penalises the given player's king if it can no longer castle, unless it has already
+/// reached one of the wings of its back rank.
+pub fn castling(position: &Position, params: &EvalParams, player: Player) -> Score {
+    let state = position.state;
+    let king = match king_square(state, player) {
+        Some(king) => king,
+        None => return Score::default(),
+    };
+    let rights = state.castle_rights(player);
+    if rights.is_king_side_available() || rights.is_queen_side_available() {
+        return Score::default();
+    }
+    let back_rank = match player {
+        Player::White => Rank::One,
+        Player::Black => Rank::Eight,
+    };
+    let castled = king.rank() == back_rank
+        && [File::A, File::B, File::C, File::G, File::H].contains(&king.file());
+    if castled {
+        Score::default()
+    } else {
+        params.king.lost_castling
+    }
+}
+
+// returns the square of the given player's king, if it has one
+fn king_square(state: &GameState, player: Player) -> Option<Square> {
+    let king = state.player_board(player).king;
+    if king.is_empty() {
+        None
+    } else {
+        Some(king.to_square())
+    }
+}
+
+// returns the square on the given file, the given number of ranks in front of the king
+fn ahead(king: Square, file: File, distance: usize, player: Player) -> Option<Square> {
+    let rank = king.rank().to_index() as usize;
+    let rank = match player {
+        Player::White => rank + distance,
+        Player::Black => rank.checked_sub(distance)?,
+    };
+    if rank > 7 {
+        return None;
+    }
+    Some(Square::from_coordinates(file, Rank::from_index(rank as u8)))
+}
@@ -0,0 +1,241 @@
+//! A bitbase of king and pawn against king endgames.
+//!
+//! The bitbase records, for every position with the pawn on the queen side and the
+//! strong side playing up the board, whether the side with the pawn wins. It is built
+//! once, the first time it is needed, by retrograde analysis: positions which are
+//! immediately won or drawn are classified first, and the rest are classified from the
+//! positions their moves lead to until nothing changes. Positions left unclassified
+//! cannot be won.
+
+use board::player::Player;
+use board::square::Square;
+use std::iter;
+use std::sync::OnceLock;
+
+/// The number of positions in the bitbase: each side to move, king square, king square
+/// and pawn square on files A to D and ranks 2 to 7.
+pub const POSITIONS: usize = 2 * 64 * 64 * 24;
+
+// the bitbase, with one bit per position set if the position is won
+static BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+
+// the classifications of positions while the bitbase is built
+const UNKNOWN: u8 = 0;
+const INVALID: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 3;
+
+/// Builds the bitbase, if it has not been built already.
+pub fn init() {
+    bitbase();
+}
+
+/// Returns true if the strong side wins the king and pawn endgame with the given
+/// squares and player to move.
+pub fn is_win(
+    strong: Player,
+    strong_king: Square,
+    weak_king: Square,
+    pawn: Square,
+    player_turn: Player,
+) -> bool {
+    // view the position with the strong side playing up the board on the queen side
+    let flip_ranks = if strong == Player::White { 0 } else { 56 };
+    let flip_files = if pawn.file().to_index() < 4 { 0 } else { 7 };
+    let normalise = |square: Square| (square.to_index() ^ flip_ranks ^ flip_files) as usize;
+    let pawn = normalise(pawn);
+    if !(8..56).contains(&pawn) {
+        return false;
+    }
+    let index = index(
+        player_turn != strong,
+        normalise(strong_king),
+        normalise(weak_king),
+        pawn,
+    );
+    bitbase()[index / 64] & (1 << (index % 64)) != 0
+}
+
+// returns the bitbase, building it first if needed
+fn bitbase() -> &'static Vec<u64> {
+    BITBASE.get_or_init(|| {
+        let results = generate();
+        let mut bits = vec![0u64; POSITIONS / 64];
+        for (index, &result) in results.iter().enumerate() {
+            if result == WIN {
+                bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+        bits
+    })
+}
+
+// classifies every position
+fn generate() -> Vec<u8> {
+    let mut results = vec![UNKNOWN; POSITIONS];
+    for_each_position(|weak_to_move, strong_king, weak_king, pawn| {
+        results[index(weak_to_move, strong_king, weak_king, pawn)] =
+            initial(weak_to_move, strong_king, weak_king, pawn);
+    });
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for_each_position(|weak_to_move, strong_king, weak_king, pawn| {
+            let index = index(weak_to_move, strong_king, weak_king, pawn);
+            if results[index] != UNKNOWN {
+                return;
+            }
+            let result = if weak_to_move {
+                weak_moves(&results, strong_king, weak_king, pawn)
+            } else {
+                strong_moves(&results, strong_king, weak_king, pawn)
+            };
+            if result != UNKNOWN {
+                results[index] = result;
+                changed = true;
+            }
+        });
+    }
+    results
+}
+
+// calls the given function with every combination of squares and player to move
+fn for_each_position<F: FnMut(bool, usize, usize, usize)>(mut f: F) {
+    for pawn in 8..56 {
+        if pawn % 8 >= 4 {
+            continue;
+        }
+        for strong_king in 0..64 {
+            for weak_king in 0..64 {
+                f(false, strong_king, weak_king, pawn);
+                f(true, strong_king, weak_king, pawn);
+            }
+        }
+    }
+}
+
+// classifies the positions whose result is known without looking at their moves
+fn initial(weak_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> u8 {
+    if strong_king == weak_king || strong_king == pawn || weak_king == pawn
+        || distance(strong_king, weak_king) <= 1
+        || (!weak_to_move && pawn_attacks(pawn) & (1 << weak_king) != 0)
+    {
+        return INVALID;
+    }
+
+    if !weak_to_move {
+        // the pawn promotes without being captured
+        let promotion = pawn + 8;
+        if pawn / 8 == 6 && promotion != strong_king && promotion != weak_king
+            && (distance(weak_king, promotion) > 1 || distance(strong_king, promotion) <= 1)
+        {
+            return WIN;
+        }
+        return UNKNOWN;
+    }
+
+    let safe = king_attacks(weak_king) & !king_attacks(strong_king) & !pawn_attacks(pawn);
+    if safe == 0 || safe & (1 << pawn) != 0 {
+        // stalemate, or the pawn is lost
+        DRAW
+    } else {
+        UNKNOWN
+    }
+}
+
+// classifies a position with the strong side to move from the positions it can reach
+fn strong_moves(results: &[u8], strong_king: usize, weak_king: usize, pawn: usize) -> u8 {
+    let king_moves = squares(king_attacks(strong_king))
+        .filter(|&target| target != pawn && distance(target, weak_king) > 1)
+        .map(|target| results[index(true, target, weak_king, pawn)]);
+
+    // promotions are only considered by the initial classification
+    let push = pawn + 8;
+    let double_push = push + 8;
+    let can_push = pawn / 8 < 6 && push != strong_king && push != weak_king;
+    let can_double_push =
+        can_push && pawn / 8 == 1 && double_push != strong_king && double_push != weak_king;
+    let pushes = [(can_push, push), (can_double_push, double_push)];
+    let pawn_moves = pushes
+        .iter()
+        .filter(|&&(legal, _)| legal)
+        .map(|&(_, target)| results[index(true, strong_king, weak_king, target)]);
+    combine(king_moves.chain(pawn_moves), WIN, DRAW)
+}
+
+// classifies a position with the weak side to move from the positions it can reach
+fn weak_moves(results: &[u8], strong_king: usize, weak_king: usize, pawn: usize) -> u8 {
+    let safe = king_attacks(weak_king) & !king_attacks(strong_king) & !pawn_attacks(pawn);
+    let children = squares(safe).map(|target| results[index(false, strong_king, target, pawn)]);
+    combine(children, DRAW, WIN)
+}
+
+// returns the best result for the side to move if any child has it, the worst if all
+// do, or unknown
+fn combine<I: Iterator<Item = u8>>(children: I, best: u8, worst: u8) -> u8 {
+    let mut all_worst = true;
+    for child in children.filter(|&child| child != INVALID) {
+        if child == best {
+            return best;
+        }
+        all_worst &= child == worst;
+    }
+    if all_worst {
+        worst
+    } else {
+        UNKNOWN
+    }
+}
+
+// returns the index of a position in the bitbase
+fn index(weak_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> usize {
+    let pawn = (pawn / 8 - 1) * 4 + pawn % 8;
+    ((pawn * 64 + strong_king) * 64 + weak_king) * 2 + weak_to_move as usize
+}
+
+// returns the squares a king attacks
+fn king_attacks(square: usize) -> u64 {
+    let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+    let mut attacks = 0;
+    for df in -1..2 {
+        for dr in -1..2 {
+            let (f, r) = (file + df, rank + dr);
+            if (df, dr) != (0, 0) && (0..8).contains(&f) && (0..8).contains(&r) {
+                attacks |= 1 << (r * 8 + f);
+            }
+        }
+    }
+    attacks
+}
+
+// returns the squares a pawn of the strong side attacks
+fn pawn_attacks(square: usize) -> u64 {
+    let mut attacks = 0;
+    if !square.is_multiple_of(8) {
+        attacks |= 1 << (square + 7);
+    }
+    if square % 8 < 7 {
+        attacks |= 1 << (square + 9);
+    }
+    attacks
+}
+
+// returns the number of king moves between two squares
+fn distance(first: usize, second: usize) -> usize {
+    let files = (first % 8) as i32 - (second % 8) as i32;
+    let ranks = (first / 8) as i32 - (second / 8) as i32;
+    files.abs().max(ranks.abs()) as usize
+}
+
+// iterates over the squares set in a mask
+fn squares(mut mask: u64) -> impl Iterator<Item = usize> {
+    iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let square = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(square)
+    })
+}
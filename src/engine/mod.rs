@@ -6,8 +6,20 @@
 #![allow(dead_code)]
 #![allow(unused_must_use)]
 
+pub mod accumulator;
+pub mod book;
 pub mod calculator;
+pub mod endgame;
+pub mod evaluator;
 pub mod heuristic;
+pub mod king_safety;
+pub mod kpk;
+pub mod nnue;
+pub mod params;
+pub mod pawn_structure;
+pub mod syzygy;
+pub mod tablebase;
+pub mod time_manager;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,224 @@
+//! An efficiently updatable neural network evaluation.
+//!
+//! The network has a single hidden layer, seen from both players' points of view. Its
+//! inputs are one feature per combination of piece, owner and square, with the owner
+//! given relative to the viewing player and the board flipped for black, so that both
+//! halves share one set of weights. As a move only changes a few features, the hidden
+//! layer is kept in a [NetworkAccumulator] and updated as moves are made, rather than
+//! being recomputed for each evaluation.
+//!
+//! The output is the sum of each half of the hidden layer, clipped to `0..=QA`,
+//! multiplied by its output weights, with the half of the player to move first. It is
+//! scaled to centipawns by `SCALE / (QA * QB)`. Only integer arithmetic is used.
+//!
+//! Networks are read from a little-endian binary file holding:
+//!
+//! ```text
+//! 4 bytes           the magic number "GNN1"
+//! u32               the hidden layer size H
+//! i16 × 768 × H     the feature weights, with the H weights of each feature together
+//! i16 × H           the hidden layer biases
+//! i16 × 2 × H       the output weights, for the player to move then the other player
+//! i32               the output bias
+//! ```
+//!
+//! Feature `f` of a piece seen by a player is `(relative_owner * 6 + piece) * 64 +
+//! square`, where the relative owner is 0 for the player's own pieces and 1 for the
+//! opponent's, pieces are numbered pawn, rook, knight, bishop, queen, king, and squares
+//! are numbered from A1 to H8, mirrored vertically for black.
+
+use board::piece::Piece;
+use board::player::Player;
+use board::square::Square;
+use rules::game_state::GameState;
+use rules::move_application::PieceChanges;
+use std::fs;
+
+/// The number of input features.
+pub const FEATURES: usize = 768;
+
+/// The largest hidden layer size accepted.
+pub const MAX_HIDDEN: usize = 4096;
+
+/// The value at which the hidden layer is clipped.
+pub const QA: i32 = 255;
+
+/// The scale of the output weights.
+pub const QB: i32 = 64;
+
+/// The scale of the output, in centipawns.
+pub const SCALE: i32 = 400;
+
+/// The magic number starting every network file.
+const MAGIC: &[u8; 4] = b"GNN1";
+
+/// The weights of a network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    /// Reads a network from the binary format described in the module documentation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 8 || &bytes[..4] != MAGIC {
+            return Err("Not a network file".to_string());
+        }
+        let hidden = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(format!("Invalid hidden layer size: {}", hidden));
+        }
+        let expected = 8 + 2 * (FEATURES * hidden + hidden + 2 * hidden) + 4;
+        if bytes.len() != expected {
+            return Err(format!(
+                "Expected {} bytes for a hidden layer of {}, found {}",
+                expected,
+                hidden,
+                bytes.len()
+            ));
+        }
+
+        let mut offset = 8;
+        let mut read_i16s = |count: usize| {
+            let values = bytes[offset..offset + 2 * count]
+                .chunks(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<i16>>();
+            offset += 2 * count;
+            values
+        };
+        let feature_weights = read_i16s(FEATURES * hidden);
+        let feature_biases = read_i16s(hidden);
+        let output_weights = read_i16s(2 * hidden);
+        let tail = &bytes[bytes.len() - 4..];
+        Ok(Network {
+            hidden,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias: i32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]),
+        })
+    }
+
+    /// Loads a network from the file at the given path.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        Network::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Returns the size of the hidden layer.
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    /// Evaluates the given game state from scratch, in centipawns. Positive values
+    /// favour white.
+    pub fn evaluate(&self, state: &GameState) -> i32 {
+        NetworkAccumulator::new(self, state).evaluate(self, state.player_turn)
+    }
+
+    // returns the weights of a feature
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+/// The hidden layer of a network for a given position, from both players' points of
+/// view.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NetworkAccumulator {
+    white: Vec<i32>,
+    black: Vec<i32>,
+}
+
+impl NetworkAccumulator {
+    /// Computes the hidden layer of the given game state from scratch.
+    pub fn new(network: &Network, state: &GameState) -> Self {
+        let biases: Vec<i32> = network.feature_biases.iter().map(|&bias| bias as i32).collect();
+        let mut accumulator = NetworkAccumulator {
+            white: biases.clone(),
+            black: biases,
+        };
+        for index in 0..64 {
+            let square = Square::new(index);
+            if let Some((piece, player)) = state.piece_at(square) {
+                accumulator.add(network, piece, player, square);
+            }
+        }
+        accumulator
+    }
+
+    /// Updates the hidden layer for the pieces a move added and removed.
+    pub fn update(&mut self, network: &Network, changes: &PieceChanges) {
+        for change in changes.iter() {
+            if change.added {
+                self.add(network, change.piece, change.player, change.square);
+            } else {
+                self.remove(network, change.piece, change.player, change.square);
+            }
+        }
+    }
+
+    /// Adds a piece of the given player on the given square.
+    pub fn add(&mut self, network: &Network, piece: Piece, player: Player, square: Square) {
+        let (white, black) = features(piece, player, square);
+        for (value, &weight) in self.white.iter_mut().zip(network.weights(white)) {
+            *value += weight as i32;
+        }
+        for (value, &weight) in self.black.iter_mut().zip(network.weights(black)) {
+            *value += weight as i32;
+        }
+    }
+
+    /// Removes a piece of the given player from the given square.
+    pub fn remove(&mut self, network: &Network, piece: Piece, player: Player, square: Square) {
+        let (white, black) = features(piece, player, square);
+        for (value, &weight) in self.white.iter_mut().zip(network.weights(white)) {
+            *value -= weight as i32;
+        }
+        for (value, &weight) in self.black.iter_mut().zip(network.weights(black)) {
+            *value -= weight as i32;
+        }
+    }
+
+    /// Computes the output of the network with the given player to move, in centipawns.
+    /// Positive values favour white.
+    pub fn evaluate(&self, network: &Network, player_turn: Player) -> i32 {
+        let (own, other) = match player_turn {
+            Player::White => (&self.white, &self.black),
+            Player::Black => (&self.black, &self.white),
+        };
+        let (own_weights, other_weights) = network.output_weights.split_at(network.hidden);
+        let sum = |values: &[i32], weights: &[i16]| {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(&value, &weight)| value.clamp(0, QA) as i64 * weight as i64)
+                .sum::<i64>()
+        };
+        let output = sum(own, own_weights) + sum(other, other_weights) + network.output_bias as i64;
+        let score = (output * SCALE as i64 / (QA * QB) as i64) as i32;
+        match player_turn {
+            Player::White => score,
+            Player::Black => -score,
+        }
+    }
+}
+
+// returns the feature of a piece as seen by white and by black
+fn features(piece: Piece, player: Player, square: Square) -> (usize, usize) {
+    let piece = piece as usize;
+    let square = square.to_index() as usize;
+    let (white_owner, black_owner) = match player {
+        Player::White => (0, 1),
+        Player::Black => (1, 0),
+    };
+    (
+        (white_owner * 6 + piece) * 64 + square,
+        (black_owner * 6 + piece) * 64 + (square ^ 56),
+    )
+}
@@ -0,0 +1,606 @@
+//! The weights of the evaluation.
+//!
+//! Every weight used by the heuristic is held in [EvalParams], so that the weights can be
+//! tuned, and loaded from a file without recompiling. A file only needs to list the
+//! weights it changes, which keep their default values otherwise. For example:
+//!
+//! ```toml
+//! [material]
+//! pawn = [90, 130]
+//!
+//! [pawns]
+//! doubled = [-40, -55]
+//! ```
+
+use board::piece::Piece;
+use engine::heuristic::Score;
+use std::fs;
+use toml;
+
+/// All of the weights of the evaluation, grouped by the area of the heuristic using them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvalParams {
+    /// The values of the pieces.
+    pub material: MaterialParams,
+
+    /// The piece-square tables.
+    pub squares: SquareParams,
+
+    /// The weights of the pawn structure terms.
+    pub pawns: PawnParams,
+
+    /// The weights of the piece-specific terms.
+    pub pieces: PieceParams,
+
+    /// The weights of the mobility terms.
+    pub mobility: MobilityParams,
+
+    /// The weights of the king safety terms.
+    pub king: KingParams,
+}
+
+impl EvalParams {
+    /// Parses parameters from TOML. Any weights which are not given keep their defaults.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let params: EvalParams = toml::from_str(text).map_err(|e| e.to_string())?;
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Loads parameters from the TOML file at the given path.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        EvalParams::from_toml(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Writes the parameters as TOML, in the form read by [EvalParams::from_toml].
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Returns every weight as a flat list, in a fixed order.
+    ///
+    /// This lets the weights be tuned without knowing what each one means. Both the
+    /// middlegame and endgame values of each score are included.
+    pub fn weights(&self) -> Vec<i32> {
+        let mut weights = Vec::new();
+        self.clone().visit_weights(|weight| weights.push(*weight));
+        weights
+    }
+
+    /// Sets every weight from a flat list in the order returned by [EvalParams::weights].
+    pub fn set_weights(&mut self, weights: &[i32]) {
+        let mut weights = weights.iter();
+        self.visit_weights(|weight| {
+            *weight = *weights.next().expect("Too few weights given");
+        });
+        assert!(weights.next().is_none(), "Too many weights given");
+    }
+
+    // calls the given function on every weight, in a fixed order
+    fn visit_weights<F: FnMut(&mut i32)>(&mut self, mut visit: F) {
+        {
+            let mut score = |score: &mut Score| {
+                visit(&mut score.mg);
+                visit(&mut score.eg);
+            };
+
+            let material = &mut self.material;
+            for value in [
+                &mut material.pawn,
+                &mut material.knight,
+                &mut material.bishop,
+                &mut material.rook,
+                &mut material.queen,
+            ].iter_mut()
+            {
+                score(value);
+            }
+
+            let pawns = &mut self.pawns;
+            for passed in pawns.passed.iter_mut() {
+                score(passed);
+            }
+            for value in [
+                &mut pawns.doubled,
+                &mut pawns.isolated,
+                &mut pawns.backward,
+                &mut pawns.connected,
+                &mut pawns.phalanx,
+                &mut pawns.island,
+                &mut pawns.candidate,
+            ].iter_mut()
+            {
+                score(value);
+            }
+
+            let pieces = &mut self.pieces;
+            for value in [
+                &mut pieces.knight_pawns,
+                &mut pieces.bishop_pair,
+                &mut pieces.bad_bishop,
+                &mut pieces.rook_open_file,
+                &mut pieces.rook_semi_open_file,
+                &mut pieces.rook_seventh,
+                &mut pieces.rook_queen_file,
+                &mut pieces.connected_rooks,
+                &mut pieces.early_queen,
+                &mut pieces.undefended_minor,
+            ].iter_mut()
+            {
+                score(value);
+            }
+
+            let mobility = &mut self.mobility;
+            for value in [
+                &mut mobility.knight,
+                &mut mobility.bishop,
+                &mut mobility.rook,
+                &mut mobility.queen,
+                &mut mobility.trapped,
+            ].iter_mut()
+            {
+                score(value);
+            }
+
+            let king = &mut self.king;
+            for shield in king.shield.iter_mut() {
+                score(shield);
+            }
+            for storm in king.storm.iter_mut() {
+                score(storm);
+            }
+            for value in [
+                &mut king.semi_open_file,
+                &mut king.open_file,
+                &mut king.lost_castling,
+            ].iter_mut()
+            {
+                score(value);
+            }
+        }
+
+        let king = &mut self.king;
+        for weight in [
+            &mut king.knight_attack,
+            &mut king.bishop_attack,
+            &mut king.rook_attack,
+            &mut king.queen_attack,
+            &mut king.attack_unit,
+        ].iter_mut()
+        {
+            visit(weight);
+        }
+        for scale in king.attacker_scale.iter_mut() {
+            visit(scale);
+        }
+
+        let squares = &mut self.squares;
+        for table in [
+            &mut squares.pawn,
+            &mut squares.knight,
+            &mut squares.bishop,
+            &mut squares.rook,
+            &mut squares.queen,
+            &mut squares.king,
+        ].iter_mut()
+        {
+            for weight in table.mg.iter_mut().chain(table.eg.iter_mut()) {
+                visit(weight);
+            }
+        }
+    }
+
+    // checks that every piece-square table has a value for every square
+    fn validate(&self) -> Result<(), String> {
+        let squares = &self.squares;
+        let tables = [
+            ("pawn", &squares.pawn),
+            ("knight", &squares.knight),
+            ("bishop", &squares.bishop),
+            ("rook", &squares.rook),
+            ("queen", &squares.queen),
+            ("king", &squares.king),
+        ];
+        for &(name, table) in tables.iter() {
+            if table.mg.len() != 64 || table.eg.len() != 64 {
+                return Err(format!("The {} square table must have 64 values", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The values of the pieces, whether on the board or in hand.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialParams {
+    /// The value of a pawn.
+    pub pawn: Score,
+
+    /// The value of a knight.
+    pub knight: Score,
+
+    /// The value of a bishop.
+    pub bishop: Score,
+
+    /// The value of a rook.
+    pub rook: Score,
+
+    /// The value of a queen.
+    pub queen: Score,
+}
+
+impl MaterialParams {
+    /// Returns the value of the given piece. Kings have no material value.
+    pub fn value(&self, piece: Piece) -> Score {
+        match piece {
+            Piece::Pawn => self.pawn,
+            Piece::Knight => self.knight,
+            Piece::Bishop => self.bishop,
+            Piece::Rook => self.rook,
+            Piece::Queen => self.queen,
+            Piece::King => Score::default(),
+        }
+    }
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        MaterialParams {
+            pawn: Score::new(100, 120),
+            knight: Score::new(300, 280),
+            bishop: Score::new(300, 300),
+            rook: Score::new(500, 520),
+            queen: Score::new(900, 940),
+        }
+    }
+}
+
+/// A pair of middlegame and endgame piece-square tables.
+///
+/// Each table holds a bonus for each square from white's point of view, as an array of
+/// ranks from 8 to 1, ie
+/// ```text
+/// [A8, B8, ... H8,
+/// A7, B7, ... H7,
+/// ...
+/// ]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SquareTable {
+    /// The middlegame bonuses.
+    pub mg: Vec<i32>,
+
+    /// The endgame bonuses.
+    pub eg: Vec<i32>,
+}
+
+impl SquareTable {
+    // returns a table from a pair of arrays
+    fn new(mg: &[i32; 64], eg: &[i32; 64]) -> Self {
+        SquareTable {
+            mg: mg.to_vec(),
+            eg: eg.to_vec(),
+        }
+    }
+}
+
+/// The piece-square tables of each piece.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SquareParams {
+    /// The pawn table.
+    pub pawn: SquareTable,
+
+    /// The knight table.
+    pub knight: SquareTable,
+
+    /// The bishop table.
+    pub bishop: SquareTable,
+
+    /// The rook table.
+    pub rook: SquareTable,
+
+    /// The queen table.
+    pub queen: SquareTable,
+
+    /// The king table.
+    pub king: SquareTable,
+}
+
+impl SquareParams {
+    /// Returns the table of the given piece.
+    pub fn table(&self, piece: Piece) -> &SquareTable {
+        match piece {
+            Piece::Pawn => &self.pawn,
+            Piece::Knight => &self.knight,
+            Piece::Bishop => &self.bishop,
+            Piece::Rook => &self.rook,
+            Piece::Queen => &self.queen,
+            Piece::King => &self.king,
+        }
+    }
+}
+
+impl Default for SquareParams {
+    fn default() -> Self {
+        SquareParams {
+            pawn: SquareTable::new(&PAWN_MG, &PAWN_EG),
+            knight: SquareTable::new(&KNIGHT_MG, &KNIGHT_EG),
+            bishop: SquareTable::new(&BISHOP_MG, &BISHOP_EG),
+            rook: SquareTable::new(&ROOK_MG, &ROOK_EG),
+            queen: SquareTable::new(&QUEEN_MG, &QUEEN_EG),
+            king: SquareTable::new(&KING_MG, &KING_EG),
+        }
+    }
+}
+
+/// The weights of the pawn structure terms, each given per pawn or per file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PawnParams {
+    /// The bonus for a passed pawn, indexed by its rank from its player's side of the
+    /// board.
+    pub passed: [Score; 8],
+
+    /// The penalty for a file with more than one pawn.
+    pub doubled: Score,
+
+    /// The penalty for a file with pawns but no pawns on either adjacent file.
+    pub isolated: Score,
+
+    /// The penalty for a pawn which cannot be supported or safely advance.
+    pub backward: Score,
+
+    /// The bonus for a pawn protected by another pawn.
+    pub connected: Score,
+
+    /// The bonus for a pawn beside another pawn.
+    pub phalanx: Score,
+
+    /// The penalty for each group of adjacent files with pawns beyond the first.
+    pub island: Score,
+
+    /// The bonus for a pawn which could become passed.
+    pub candidate: Score,
+}
+
+impl Default for PawnParams {
+    fn default() -> Self {
+        PawnParams {
+            passed: [
+                Score::new(0, 0),
+                Score::new(5, 10),
+                Score::new(10, 15),
+                Score::new(15, 25),
+                Score::new(25, 45),
+                Score::new(40, 75),
+                Score::new(60, 110),
+                Score::new(0, 0),
+            ],
+            doubled: Score::new(-50, -60),
+            isolated: Score::new(-50, -40),
+            backward: Score::new(-10, -15),
+            connected: Score::new(10, 10),
+            phalanx: Score::new(5, 8),
+            island: Score::new(-10, -15),
+            candidate: Score::new(10, 25),
+        }
+    }
+}
+
+/// The weights of the terms for individual kinds of piece.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PieceParams {
+    /// The adjustment to each knight per own pawn above five.
+    pub knight_pawns: Score,
+
+    /// The bonus for a pair of bishops covering both colours of square.
+    pub bishop_pair: Score,
+
+    /// The penalty to each bishop per own pawn on its colour of square.
+    pub bad_bishop: Score,
+
+    /// The bonus for a rook on a file without pawns.
+    pub rook_open_file: Score,
+
+    /// The bonus for a rook on a file with only enemy pawns.
+    pub rook_semi_open_file: Score,
+
+    /// The bonus for a rook on the seventh rank.
+    pub rook_seventh: Score,
+
+    /// The bonus for a rook on the same file as an enemy queen.
+    pub rook_queen_file: Score,
+
+    /// The bonus for each pair of rooks defending each other.
+    pub connected_rooks: Score,
+
+    /// The penalty to a developed queen per minor piece still on the back rank.
+    pub early_queen: Score,
+
+    /// The penalty for a knight or bishop not protected by its own pieces.
+    pub undefended_minor: Score,
+}
+
+impl Default for PieceParams {
+    fn default() -> Self {
+        PieceParams {
+            knight_pawns: Score::new(4, 6),
+            bishop_pair: Score::new(30, 50),
+            bad_bishop: Score::new(-3, -5),
+            rook_open_file: Score::new(25, 10),
+            rook_semi_open_file: Score::new(12, 6),
+            rook_seventh: Score::new(20, 30),
+            rook_queen_file: Score::new(8, 4),
+            connected_rooks: Score::new(10, 5),
+            early_queen: Score::new(-8, 0),
+            undefended_minor: Score::new(-15, -10),
+        }
+    }
+}
+
+/// The bonus for each safe square attacked by a piece, beyond a typical number of them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MobilityParams {
+    /// The bonus per square for a knight.
+    pub knight: Score,
+
+    /// The bonus per square for a bishop.
+    pub bishop: Score,
+
+    /// The bonus per square for a rook.
+    pub rook: Score,
+
+    /// The bonus per square for a queen.
+    pub queen: Score,
+
+    /// The penalty for a piece in the opponent's half with no safe squares.
+    pub trapped: Score,
+}
+
+impl Default for MobilityParams {
+    fn default() -> Self {
+        MobilityParams {
+            knight: Score::new(4, 4),
+            bishop: Score::new(5, 5),
+            rook: Score::new(2, 4),
+            queen: Score::new(1, 2),
+            trapped: Score::new(-50, -30),
+        }
+    }
+}
+
+/// The weights of the king safety terms.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KingParams {
+    /// The bonus for a pawn sheltering the king, indexed by its distance in front of it.
+    pub shield: [Score; 3],
+
+    /// The penalty for an enemy pawn advancing on the king, indexed by its distance in
+    /// front of it.
+    pub storm: [Score; 5],
+
+    /// The penalty for a file near the king with only enemy pawns.
+    pub semi_open_file: Score,
+
+    /// The penalty for a file near the king without pawns.
+    pub open_file: Score,
+
+    /// The penalty for a king which can no longer castle and has not reached a wing.
+    pub lost_castling: Score,
+
+    /// The attack weight of a knight, per square of the king zone it attacks.
+    pub knight_attack: i32,
+
+    /// The attack weight of a bishop, per square of the king zone it attacks.
+    pub bishop_attack: i32,
+
+    /// The attack weight of a rook, per square of the king zone it attacks.
+    pub rook_attack: i32,
+
+    /// The attack weight of a queen, per square of the king zone it attacks.
+    pub queen_attack: i32,
+
+    /// The middlegame penalty in centipawns for each unit of attack weight.
+    pub attack_unit: i32,
+
+    /// The percentage of the attack weight which counts, indexed by the number of
+    /// attacking pieces.
+    pub attacker_scale: [i32; 8],
+}
+
+impl Default for KingParams {
+    fn default() -> Self {
+        KingParams {
+            shield: [Score::new(0, 0), Score::new(15, 0), Score::new(8, 0)],
+            storm: [
+                Score::new(0, 0),
+                Score::new(-5, 0),
+                Score::new(-20, 0),
+                Score::new(-12, 0),
+                Score::new(-6, 0),
+            ],
+            semi_open_file: Score::new(-15, 0),
+            open_file: Score::new(-25, 0),
+            lost_castling: Score::new(-30, 0),
+            knight_attack: 2,
+            bishop_attack: 2,
+            rook_attack: 3,
+            queen_attack: 5,
+            attack_unit: 4,
+            attacker_scale: [0, 0, 50, 75, 88, 94, 97, 99],
+        }
+    }
+}
+
+// ---------------------------------------------------------------------
+// the default piece-square tables, from white's point of view
+const PAWN_MG: [i32; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 50, 50, 50, 50, 50, 50, 50, 50, 10, 10, 20, 30, 30, 20, 10, 10, 5, 5,
+    10, 25, 25, 10, 5, 5, 0, 0, 0, 20, 20, 0, 0, 0, 5, -5, -10, 0, 0, -10, -5, 5, 5, 10, 10, -20,
+    -20, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+const PAWN_EG: [i32; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 80, 80, 80, 80, 80, 80, 80, 80, 50, 50, 50, 50, 50, 50, 50, 50, 30, 30,
+    30, 30, 30, 30, 30, 30, 20, 20, 20, 20, 20, 20, 20, 20, 10, 10, 10, 10, 10, 10, 10, 10, 5, 5,
+    5, 5, 5, 5, 5, 5, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+const KNIGHT_MG: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15, 10,
+    0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 10, 15, 15, 10,
+    5, -30, -40, -20, 0, 5, 5, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
+];
+const KNIGHT_EG: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15, 10,
+    0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 10, 15, 15, 10,
+    0, -30, -40, -20, 0, 0, 0, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
+];
+const BISHOP_MG: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 10, 10, 5, 0,
+    -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 10, 10, 10, 10, 10, 10,
+    -10, -10, 5, 0, 0, 0, 0, 5, -10, -20, -10, -10, -10, -10, -10, -10, -20,
+];
+const BISHOP_EG: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 10, 10, 10, 10, 0,
+    -10, -10, 0, 10, 15, 15, 10, 0, -10, -10, 0, 10, 15, 15, 10, 0, -10, -10, 0, 10, 10, 10, 10, 0,
+    -10, -10, 0, 0, 0, 0, 0, 0, -10, -20, -10, -10, -10, -10, -10, -10, -20,
+];
+const ROOK_MG: [i32; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, 10, 10, 10, 10, 5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0,
+    0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 0, 0,
+    0, 5, 5, 0, 0, 0,
+];
+const ROOK_EG: [i32; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 10, 10, 10, 10, 10, 10, 10, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0,
+];
+const QUEEN_MG: [i32; 64] = [
+    -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 5, 5, 5, 0, -10,
+    -5, 0, 5, 5, 5, 5, 0, -5, 0, 0, 5, 5, 5, 5, 0, -5, -10, 5, 5, 5, 5, 5, 0, -10, -10, 0, 5, 0, 0,
+    0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
+];
+const QUEEN_EG: [i32; 64] = [
+    -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 5, 5, 5, 5, 0, -10, -10, 5, 10, 10, 10, 10, 5,
+    -10, -5, 5, 10, 15, 15, 10, 5, -5, -5, 5, 10, 15, 15, 10, 5, -5, -10, 5, 10, 10, 10, 10, 5,
+    -10, -10, 0, 5, 5, 5, 5, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
+];
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40,
+    -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40, -40, -30,
+    -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20, 30, 10, 0, 0,
+    10, 30, 20,
+];
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50, -30, -20, -10, 0, 0, -10, -20, -30, -30, -10, 20, 30,
+    30, 20, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30,
+    -10, 20, 30, 30, 20, -10, -30, -30, -30, 0, 0, 0, 0, -30, -30, -50, -30, -30, -30, -30, -30,
+    -30, -50,
+];
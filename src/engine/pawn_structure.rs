@@ -0,0 +1,314 @@
+//! Evaluation of the pawn structure.
+//!
+//! Pawns move rarely compared to the other pieces, so the same structure is seen many
+//! times during a search. Its evaluation is cached in a [PawnTable] keyed by
+//! [GameState::pawn_key], leaving only the terms which depend on other pieces to be
+//! computed for every position.
+//!
+//! The cache holds the number of pawns earning each bonus or penalty rather than the
+//! scores themselves, so it stays valid whatever the [EvalParams] used.
+
+use board::bitboard::BitBoard;
+use board::file::File;
+use board::player::Player;
+use board::square::Square;
+use engine::heuristic::Score;
+use engine::params::EvalParams;
+use rules::basic_moves;
+use rules::game_state::GameState;
+
+/// The number of pawns, files or islands earning each pawn structure term for one player.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PawnCounts {
+    /// The number of files with more than one pawn.
+    pub doubled: i32,
+
+    /// The number of files with pawns but no pawns on either adjacent file.
+    pub isolated: i32,
+
+    /// The number of groups of adjacent files with pawns beyond the first.
+    pub islands: i32,
+
+    /// The number of pawns protected by another pawn.
+    pub connected: i32,
+
+    /// The number of pawns beside another pawn.
+    pub phalanx: i32,
+
+    /// The number of pawns which cannot be supported or safely advance.
+    pub backward: i32,
+
+    /// The number of pawns which could become passed.
+    pub candidate: i32,
+}
+
+/// The scores of the pawn structure terms for one player.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PawnScores {
+    /// The penalty for files with more than one pawn.
+    pub doubled: Score,
+
+    /// The penalty for files with pawns but no pawns on either adjacent file.
+    pub isolated: Score,
+
+    /// The penalty for each group of adjacent files with pawns beyond the first.
+    pub islands: Score,
+
+    /// The bonus for passed pawns, by rank.
+    pub passed: Score,
+
+    /// The bonus for pawns protected by another pawn.
+    pub connected: Score,
+
+    /// The bonus for pawns beside another pawn.
+    pub phalanx: Score,
+
+    /// The penalty for pawns which cannot be supported or safely advance.
+    pub backward: Score,
+
+    /// The bonus for pawns which could become passed.
+    pub candidate: Score,
+}
+
+impl PawnScores {
+    /// Returns the sum of all of the terms.
+    pub fn total(&self) -> Score {
+        self.doubled + self.isolated + self.islands + self.passed + self.connected
+            + self.phalanx + self.backward + self.candidate
+    }
+}
+
+/// The evaluation of a pawn structure.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PawnEntry {
+    /// The pawn key of the structure.
+    pub key: u64,
+
+    /// White's pawn structure counts.
+    pub white: PawnCounts,
+
+    /// Black's pawn structure counts.
+    pub black: PawnCounts,
+
+    /// White's passed pawns.
+    pub white_passed: BitBoard,
+
+    /// Black's passed pawns.
+    pub black_passed: BitBoard,
+}
+
+impl PawnEntry {
+    /// Evaluates the pawn structure of the given state.
+    pub fn new(state: &GameState) -> Self {
+        let white = state.player_board(Player::White).pawns;
+        let black = state.player_board(Player::Black).pawns;
+        let (white_counts, white_passed) = evaluate(white, black, Player::White);
+        let (black_counts, black_passed) = evaluate(black, white, Player::Black);
+        PawnEntry {
+            key: state.pawn_key(),
+            white: white_counts,
+            black: black_counts,
+            white_passed,
+            black_passed,
+        }
+    }
+
+    /// Returns the total score of the structure. Positive values favour white.
+    pub fn score(&self, params: &EvalParams) -> Score {
+        self.scores(params, Player::White).total() - self.scores(params, Player::Black).total()
+    }
+
+    /// Returns the pawn structure scores of the given player.
+    pub fn scores(&self, params: &EvalParams, player: Player) -> PawnScores {
+        let weights = &params.pawns;
+        let counts = match player {
+            Player::White => self.white,
+            Player::Black => self.black,
+        };
+        let passed = self.passed(player).iter().fold(Score::default(), |score, square| {
+            score + weights.passed[relative_rank(square, player)]
+        });
+        PawnScores {
+            doubled: weights.doubled * counts.doubled,
+            isolated: weights.isolated * counts.isolated,
+            islands: weights.island * counts.islands,
+            passed,
+            connected: weights.connected * counts.connected,
+            phalanx: weights.phalanx * counts.phalanx,
+            backward: weights.backward * counts.backward,
+            candidate: weights.candidate * counts.candidate,
+        }
+    }
+
+    /// Returns the passed pawns of the given player.
+    pub fn passed(&self, player: Player) -> BitBoard {
+        match player {
+            Player::White => self.white_passed,
+            Player::Black => self.black_passed,
+        }
+    }
+}
+
+/// A fixed-size cache of pawn structure evaluations.
+#[derive(Debug)]
+pub struct PawnTable {
+    /// The cached evaluations, indexed by their key modulo the table size.
+    entries: Vec<Option<PawnEntry>>,
+}
+
+impl PawnTable {
+    /// Returns an empty table with the given number of entries.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "Pawn table must have at least one entry");
+        PawnTable {
+            entries: vec![None; size],
+        }
+    }
+
+    /// Returns the cached evaluation of the pawn structure of the given state, evaluating
+    /// and storing it if it is not present.
+    pub fn probe(&mut self, state: &GameState) -> PawnEntry {
+        let key = state.pawn_key();
+        let index = (key % self.entries.len() as u64) as usize;
+        match self.entries[index] {
+            Some(entry) if entry.key == key => entry,
+            _ => {
+                let entry = PawnEntry::new(state);
+                self.entries[index] = Some(entry);
+                entry
+            }
+        }
+    }
+}
+
+/// Scores the given player's passed pawns which cannot advance, as their next square is
+/// occupied. Such pawns earn only half of their bonus.
+///
+/// This depends on the pieces as well as the pawns, so it is not part of the entry.
+pub fn blocked_passers(
+    state: &GameState,
+    params: &EvalParams,
+    entry: &PawnEntry,
+    player: Player,
+) -> Score {
+    let occupied = state.occupied();
+    entry
+        .passed(player)
+        .iter()
+        .filter(|&square| {
+            let stop = next_rank(square, player) & square.file().to_bitboard();
+            !(stop & occupied).is_empty()
+        })
+        .fold(Score::default(), |score, square| {
+            let bonus = params.pawns.passed[relative_rank(square, player)];
+            score - Score::new(bonus.mg / 2, bonus.eg / 2)
+        })
+}
+
+// evaluates one player's pawns, returning their counts and passed pawns
+fn evaluate(own: BitBoard, opponent: BitBoard, player: Player) -> (PawnCounts, BitBoard) {
+    let mut counts = PawnCounts::default();
+    let mut passed = BitBoard::empty();
+
+    // doubled and isolated pawns and isolated pawns, counted by file
+    let occupied_files: Vec<File> = File::iter()
+        .cloned()
+        .filter(|file| !(own & file.to_bitboard()).is_empty())
+        .collect();
+    counts.doubled = occupied_files
+        .iter()
+        .filter(|file| (own & file.to_bitboard()).count() > 1)
+        .count() as i32;
+    counts.isolated = occupied_files
+        .iter()
+        .filter(|file| (own & adjacent_files(**file)).is_empty())
+        .count() as i32;
+
+    // each group of adjacent files with pawns beyond the first
+    let islands = occupied_files
+        .iter()
+        .filter(|file| {
+            file.prev()
+                .is_none_or(|prev| (own & prev.to_bitboard()).is_empty())
+        })
+        .count() as i32;
+    counts.islands = (islands - 1).max(0);
+
+    for square in own.iter() {
+        let file = square.file().to_bitboard();
+        let adjacent = adjacent_files(square.file());
+        let ahead = forward_ranks(square, player);
+
+        // a passed pawn has no opposing pawns ahead of it on its own or adjacent files,
+        // and no pawn of its own in front of it
+        let sentries = opponent & ahead & adjacent;
+        let is_passed = (opponent & ahead & file).is_empty() && sentries.is_empty()
+            && (own & ahead & file).is_empty();
+        if is_passed {
+            passed = passed.set_square(square);
+        }
+
+        // connected pawns are protected by another pawn, phalanx pawns stand beside one
+        if !(own & basic_moves::pawn_attacks(square, player.other())).is_empty() {
+            counts.connected += 1;
+        }
+        if !(own & adjacent & square.rank().to_bitboard()).is_empty() {
+            counts.phalanx += 1;
+        }
+
+        // a backward pawn has neighbours, but all are ahead of it so it cannot be
+        // supported, and it cannot safely advance
+        let supporters = own & adjacent & !ahead;
+        let stop = next_rank(square, player) & file;
+        if !(own & adjacent).is_empty() && supporters.is_empty() && !stop.is_empty() {
+            let stop_attacked = !(opponent
+                & basic_moves::pawn_attacks(stop.to_square(), player))
+                .is_empty();
+            if stop_attacked {
+                counts.backward += 1;
+            }
+        }
+
+        // a candidate passer has an open file ahead, and at least as many supporters as
+        // there are opposing pawns which could stop it
+        let open_file = ((opponent | own) & ahead & file).is_empty();
+        if !is_passed && open_file && supporters.count() >= sentries.count() {
+            counts.candidate += 1;
+        }
+    }
+
+    (counts, passed)
+}
+
+// returns the files either side of the given file
+fn adjacent_files(file: File) -> BitBoard {
+    let next = file.next().map_or(BitBoard::empty(), |file| file.to_bitboard());
+    let prev = file.prev().map_or(BitBoard::empty(), |file| file.to_bitboard());
+    next | prev
+}
+
+// returns all ranks ahead of the square, from the given player's point of view
+fn forward_ranks(square: Square, player: Player) -> BitBoard {
+    let rank = square.rank().to_index() as u32;
+    match player {
+        Player::White => BitBoard::new(u64::MAX.checked_shl(8 * (rank + 1)).unwrap_or(0)),
+        Player::Black => BitBoard::new((1u64 << (8 * rank)) - 1),
+    }
+}
+
+// returns the rank in front of the square, from the given player's point of view
+fn next_rank(square: Square, player: Player) -> BitBoard {
+    let rank = square.rank().to_bitboard();
+    match player {
+        Player::White => rank << 8,
+        Player::Black => rank >> 8,
+    }
+}
+
+// returns the index of the square's rank counted from the given player's side
+fn relative_rank(square: Square, player: Player) -> usize {
+    match player {
+        Player::White => square.rank().to_index() as usize,
+        Player::Black => 7 - square.rank().to_index() as usize,
+    }
+}
@@ -0,0 +1,1320 @@
+//! Probing of Syzygy endgame tablebases.
+//!
+//! Syzygy tables come in two kinds of files, named after their material like the
+//! generated tables of [tablebase](::engine::tablebase), such as "KQvKR.rtbw":
+//!
+//! * `.rtbw` files hold the win, draw or loss (WDL) of every position, taking the
+//!   fifty-move rule into account as cursed wins and blessed losses.
+//! * `.rtbz` files hold the distance to zeroing (DTZ) of every position: the number of
+//!   plies until the next capture or pawn move on the way to the best result.
+//!
+//! Both are compressed with a canonical Huffman code over symbols standing for runs of
+//! values. Files are opened the first time a position needs them, and only their start,
+//! which holds the header, is kept in memory: the compressed values of larger files are
+//! read a block at a time as positions are probed, as the files of six and seven pieces
+//! are far too large to hold.
+//!
+//! Positions whose best move is a capture, or a pawn move for DTZ, may be stored with
+//! any value that compresses well, so probing first searches those moves and only trusts
+//! the stored value when it is better. WDL probes are cheap enough to be made inside the
+//! search, right after captures and pawn moves reset the fifty-move counter. DTZ probes
+//! are made at the root, to keep only the moves which convert the best result fastest.
+
+use board::piece::Piece;
+use board::player::Player;
+use board::square::Square;
+use engine::tablebase::TABLEBASE_WIN;
+use rules::castle_rights::CastleRights;
+use rules::game_state::GameState;
+use rules::move_application::Move;
+use rules::variant::Variant;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+/// The score of a position won according to a Syzygy table. Wins of the generated
+/// tablebase score higher, as they also know the distance to mate.
+pub const SYZYGY_WIN: i32 = TABLEBASE_WIN - 1000;
+
+/// The extension of WDL table files.
+pub const WDL_EXTENSION: &str = "rtbw";
+
+/// The extension of DTZ table files.
+pub const DTZ_EXTENSION: &str = "rtbz";
+
+/// The largest number of pieces, including both kings, of Syzygy tables.
+pub const MAX_PIECES: usize = 7;
+
+// the magic numbers starting every table file
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// the flags of the compressed data of a table
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// the right symbol of the leaves of the pairing tree
+const LEAF: usize = 0xFFF;
+
+// the number of bytes kept in memory from the start of each table file, which hold its
+// header and, for the smallest tables, all of their values
+const HEAD_SIZE: usize = 1 << 16;
+
+/// The result of a position for the player to move, according to a WDL table.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Wdl {
+    /// The position is lost.
+    Loss = -2,
+
+    /// The position is lost, but drawn by the fifty-move rule.
+    BlessedLoss = -1,
+
+    /// The position is drawn.
+    Draw = 0,
+
+    /// The position is won, but drawn by the fifty-move rule.
+    CursedWin = 1,
+
+    /// The position is won.
+    Win = 2,
+}
+
+impl Wdl {
+    /// Returns the score of the result for the player to move, in centipawns.
+    ///
+    /// Cursed wins and blessed losses score barely off a draw, which they are in play.
+    pub fn score(self) -> i32 {
+        match self {
+            Wdl::Loss => -SYZYGY_WIN,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 1,
+            Wdl::Win => SYZYGY_WIN,
+        }
+    }
+
+    /// Returns the result for the other player.
+    pub fn negate(self) -> Wdl {
+        Wdl::from_value(-(self as i32))
+    }
+
+    // returns the result with the given value, from -2 to 2
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    // returns the DTZ of a position whose best move zeroes with this result
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Loss => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 101,
+            Wdl::Win => 1,
+        }
+    }
+
+    // returns -1, 0 or 1 as the result is a loss, draw or win
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+/// A set of Syzygy table files.
+///
+/// Files are only found when the set is loaded, and opened the first time they are probed.
+#[derive(Debug, Default)]
+pub struct Syzygy {
+    wdl: HashMap<String, LazyTable>,
+    dtz: HashMap<String, LazyTable>,
+    max_pieces: usize,
+}
+
+impl Syzygy {
+    /// Returns an empty set of tables.
+    pub fn new() -> Self {
+        Syzygy::default()
+    }
+
+    /// Finds the table files in the given directories, separated like the `PATH`
+    /// environment variable.
+    pub fn load_dir(path: &str) -> Result<Self, String> {
+        let mut syzygy = Syzygy::new();
+        for dir in env::split_paths(path) {
+            let entries = fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            for entry in entries {
+                let path = entry
+                    .map_err(|e| format!("{}: {}", dir.display(), e))?
+                    .path();
+                let (name, extension) = match (path.file_stem(), path.extension()) {
+                    (Some(name), Some(extension)) => (
+                        name.to_string_lossy().to_string(),
+                        extension.to_string_lossy(),
+                    ),
+                    _ => continue,
+                };
+                let kind = match extension.as_ref() {
+                    WDL_EXTENSION => Kind::Wdl,
+                    DTZ_EXTENSION => Kind::Dtz,
+                    _ => continue,
+                };
+                let material = match MaterialInfo::from_name(&name) {
+                    Ok(material) => material,
+                    Err(_) => continue,
+                };
+                let table = LazyTable {
+                    path,
+                    table: OnceLock::new(),
+                };
+                match kind {
+                    Kind::Wdl => {
+                        syzygy.max_pieces = syzygy.max_pieces.max(material.piece_count);
+                        syzygy.wdl.insert(name, table);
+                    }
+                    Kind::Dtz => {
+                        syzygy.dtz.insert(name, table);
+                    }
+                }
+            }
+        }
+        Ok(syzygy)
+    }
+
+    /// Returns the number of WDL tables.
+    pub fn len(&self) -> usize {
+        self.wdl.len()
+    }
+
+    /// Returns true if there are no WDL tables.
+    pub fn is_empty(&self) -> bool {
+        self.wdl.is_empty()
+    }
+
+    /// Returns the largest number of pieces of the WDL tables, including both kings.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Returns the result of the given state for the player to move, or `None` if the
+    /// tables do not cover it.
+    ///
+    /// Only standard chess positions without castling rights are covered. The result
+    /// assumes the fifty-move counter was just reset.
+    pub fn probe_wdl(&self, state: &GameState) -> Option<Wdl> {
+        if !self.covers(state) {
+            return None;
+        }
+        self.search(&mut state.clone(), false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the distance to zeroing of the given state in plies, or `None` if the
+    /// tables do not cover it.
+    ///
+    /// The distance is positive if the player to move wins and negative if they lose,
+    /// offset by 100 for cursed wins and blessed losses, and 0 for draws. It may be one
+    /// ply more than the exact distance when the table stores moves instead of plies.
+    pub fn probe_dtz(&self, state: &GameState) -> Option<i32> {
+        if !self.covers(state) {
+            return None;
+        }
+        self.dtz(&mut state.clone())
+    }
+
+    /// Returns the moves of the given state which reach the best result the fastest,
+    /// according to the DTZ tables, or `None` if the tables do not cover the state and
+    /// all of its moves.
+    ///
+    /// Wins and losses are ranked by their distance to zeroing, counting the plies
+    /// already played towards the fifty-move rule. Losses which the rule turns into
+    /// draws rank above the others, and the longest ones above shorter ones.
+    pub fn root_moves(&self, state: &GameState) -> Option<Vec<Move>> {
+        if !self.covers(state) {
+            return None;
+        }
+        let mut state = state.clone();
+        let draw_plies = i32::from(state.draw_plies);
+        let mut ranked = Vec::new();
+        for mv in state.legal_moves() {
+            let undo = state.make_move(&mv);
+            let dtz = if state.draw_plies == 0 {
+                self.search(&mut state, false)
+                    .map(|(wdl, _)| wdl.negate().dtz_before_zeroing())
+            } else {
+                self.dtz(&mut state).map(|dtz| -dtz - dtz.signum())
+            };
+            let mates = state.is_check(state.player_turn) && state.legal_moves().is_empty();
+            state.unmake_move(&mv, &undo);
+            let dtz = match dtz? {
+                2 if mates => 1,
+                dtz => dtz,
+            };
+            let rank = if dtz > 0 {
+                1000 - (dtz + draw_plies)
+            } else if dtz < 0 && -dtz * 2 + draw_plies < 100 {
+                -1000
+            } else if dtz < 0 {
+                -1000 + (-dtz + draw_plies)
+            } else {
+                0
+            };
+            ranked.push((mv, rank));
+        }
+        let best = ranked.iter().map(|&(_, rank)| rank).max()?;
+        Some(
+            ranked
+                .into_iter()
+                .filter(|&(_, rank)| rank == best)
+                .map(|(mv, _)| mv)
+                .collect(),
+        )
+    }
+
+    // returns true if the state may be covered by the tables
+    fn covers(&self, state: &GameState) -> bool {
+        let pieces = state.occupied().count() as usize;
+        state.variant == Variant::Standard
+            && state.white_castle_rights == CastleRights::None
+            && state.black_castle_rights == CastleRights::None
+            && state.player_board(Player::White).king.count() == 1
+            && state.player_board(Player::Black).king.count() == 1
+            && pieces <= self.max_pieces
+    }
+
+    // returns the result of the state, searching captures, and pawn moves if asked, as
+    // the tables store arbitrary values where those are best. Also returns true if the
+    // best move is one of those moves, so that the DTZ table cannot be trusted.
+    fn search(&self, state: &mut GameState, zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = state.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for mv in &moves {
+            if !mv.capture && (!zeroing_moves || mv.piece != Piece::Pawn) {
+                continue;
+            }
+            searched += 1;
+            let undo = state.make_move(mv);
+            let result = self.search(state, false);
+            state.unmake_move(mv, &undo);
+            let value = result?.0.negate();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // the stored value is wrong if every move was searched, such as with en passant
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            self.probe_table(state, Kind::Wdl, Wdl::Draw)?
+                .map(Wdl::from_value)?
+        };
+        if best >= value {
+            Some((best, best > Wdl::Draw || all_searched))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    // returns the distance to zeroing of the state
+    fn dtz(&self, state: &mut GameState) -> Option<i32> {
+        let (wdl, zeroing) = self.search(state, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(wdl.dtz_before_zeroing());
+        }
+        if let Some(dtz) = self.probe_table(state, Kind::Dtz, wdl)? {
+            let cursed = wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss;
+            return Some((dtz + if cursed { 100 } else { 0 }) * wdl.signum());
+        }
+
+        // the table only holds the other player's moves, so search one ply
+        let mut min_dtz = i32::MAX;
+        for mv in state.legal_moves() {
+            let zeroing = mv.capture || mv.piece == Piece::Pawn;
+            let undo = state.make_move(&mv);
+            let result = if zeroing {
+                self.search(state, false)
+                    .map(|(wdl, _)| -wdl.dtz_before_zeroing())
+            } else {
+                self.dtz(state).map(|dtz| -dtz)
+            };
+            let mates = state.is_check(state.player_turn) && state.legal_moves().is_empty();
+            state.unmake_move(&mv, &undo);
+            let mut dtz = result?;
+            if dtz == 1 && mates {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    // returns the value stored for the state, or `None` if there is no table for it.
+    // The inner value is `None` if the DTZ table is stored for the other player to move.
+    fn probe_table(&self, state: &GameState, kind: Kind, wdl: Wdl) -> Option<Option<i32>> {
+        let (white, black) = (
+            side_name(state, Player::White),
+            side_name(state, Player::Black),
+        );
+        if white == "K" && black == "K" {
+            return Some(Some(0));
+        }
+        let tables = match kind {
+            Kind::Wdl => &self.wdl,
+            Kind::Dtz => &self.dtz,
+        };
+        let (table, black_stronger) = match tables.get(&format!("{}v{}", white, black)) {
+            Some(table) => (table, false),
+            None => (tables.get(&format!("{}v{}", black, white))?, true),
+        };
+        let table = table.get(kind)?;
+        Some(table.probe(state, black_stronger, wdl))
+    }
+}
+
+// the kinds of table files
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    Wdl,
+    Dtz,
+}
+
+// a table file, opened the first time it is probed
+struct LazyTable {
+    path: PathBuf,
+    table: OnceLock<Option<Table>>,
+}
+
+impl LazyTable {
+    // returns the table, opening it if needed, or `None` if it is unreadable
+    fn get(&self, kind: Kind) -> Option<&Table> {
+        self.table
+            .get_or_init(|| match Table::load(&self.path, kind) {
+                Ok(table) => Some(table),
+                Err(e) => {
+                    warn!("{}", e);
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+impl fmt::Debug for LazyTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LazyTable({})", self.path.display())
+    }
+}
+
+// the properties of a material which decide how its tables are laid out
+#[derive(Clone, Debug)]
+struct MaterialInfo {
+    piece_count: usize,
+    has_pawns: bool,
+    // true if both players have the same pieces
+    symmetric: bool,
+    // true if a player has a single piece of some kind other than the king
+    has_unique_pieces: bool,
+    // the pawns of the player whose pawns lead the index, then of the other player
+    pawn_counts: [usize; 2],
+}
+
+impl MaterialInfo {
+    // parses a name such as "KRPvKR"
+    fn from_name(name: &str) -> Result<Self, String> {
+        let sides: Vec<&str> = name.split('v').collect();
+        let valid = sides.len() == 2
+            && sides.iter().all(|side| {
+                side.chars().filter(|&c| c == 'K').count() == 1
+                    && side.chars().all(|c| "KQRBNP".contains(c))
+            });
+        if !valid || name.len() - 1 > MAX_PIECES {
+            return Err(format!("invalid Syzygy material {:?}", name));
+        }
+        let count = |side: &str, letter: char| side.chars().filter(|&c| c == letter).count();
+        let has_unique_pieces = sides
+            .iter()
+            .any(|side| "QRBNP".chars().any(|letter| count(side, letter) == 1));
+        let (white_pawns, black_pawns) = (count(sides[0], 'P'), count(sides[1], 'P'));
+
+        // the player with fewer pawns leads, as that compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        Ok(MaterialInfo {
+            piece_count: name.len() - 1,
+            has_pawns: white_pawns + black_pawns > 0,
+            symmetric: sides[0] == sides[1],
+            has_unique_pieces,
+            pawn_counts: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+        })
+    }
+}
+
+// the compressed values of one player to move and leading pawn file of a table
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    // the pieces in the order they are indexed, as codes of colour and kind
+    pieces: Vec<u8>,
+    // the number of pieces in each group indexed together, and each group's multiplier
+    group_len: Vec<usize>,
+    group_idx: Vec<u64>,
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    min_sym_len: usize,
+    // the offsets of the lowest symbol of each code length, and of the pairing tree
+    lowest_sym: usize,
+    btree: usize,
+    // the lowest code of each length, left-aligned to 64 bits
+    base64: Vec<u64>,
+    // the number of values less one that each symbol stands for
+    symlen: Vec<u8>,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_length: usize,
+    block_length_size: usize,
+    data: usize,
+    // the offsets in the DTZ value map of each result, less one
+    map_idx: [usize; 4],
+}
+
+// a table read from a file
+struct Table {
+    kind: Kind,
+    file: Mutex<fs::File>,
+    // the length of the file, and the bytes at its start
+    len: usize,
+    head: Vec<u8>,
+    material: MaterialInfo,
+    // the compressed data of each player to move, then each leading pawn file
+    pairs: Vec<Vec<PairsData>>,
+    // the offset of the DTZ value map
+    map: usize,
+}
+
+impl Table {
+    // opens a table file, reading its start
+    fn load(path: &PathBuf, kind: Kind) -> Result<Self, String> {
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let material = MaterialInfo::from_name(&name)?;
+        let read = || -> Result<(fs::File, usize, Vec<u8>), std::io::Error> {
+            let mut file = fs::File::open(path)?;
+            let len = file.metadata()?.len() as usize;
+            let mut head = vec![0; len.min(HEAD_SIZE)];
+            file.read_exact(&mut head)?;
+            Ok((file, len, head))
+        };
+        let (file, len, head) = read().map_err(|e| format!("{}: {}", path.display(), e))?;
+        Table::from_file(kind, material, file, len, head)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // parses the header of a table file, given its length and the bytes at its start
+    fn from_file(
+        kind: Kind,
+        material: MaterialInfo,
+        file: fs::File,
+        len: usize,
+        head: Vec<u8>,
+    ) -> Result<Self, String> {
+        let magic = match kind {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        };
+        if head.len() < 5 || head[..4] != magic {
+            return Err("not a Syzygy table".to_string());
+        }
+        let mut table = Table {
+            kind,
+            file: Mutex::new(file),
+            len,
+            head,
+            material,
+            pairs: vec![],
+            map: 0,
+        };
+        let sides = if kind == Kind::Wdl && !table.material.symmetric {
+            2
+        } else {
+            1
+        };
+        let files = if table.material.has_pawns { 4 } else { 1 };
+        let both_pawns = table.material.has_pawns && table.material.pawn_counts[1] > 0;
+        table.pairs = vec![vec![PairsData::default(); files]; sides];
+
+        // the first byte holds flags which the material already tells
+        let mut offset = 5;
+        for file in 0..files {
+            let order = [
+                [
+                    table.byte(offset)? & 0xF,
+                    if both_pawns {
+                        table.byte(offset + 1)? & 0xF
+                    } else {
+                        0xF
+                    },
+                ],
+                [
+                    table.byte(offset)? >> 4,
+                    if both_pawns {
+                        table.byte(offset + 1)? >> 4
+                    } else {
+                        0xF
+                    },
+                ],
+            ];
+            offset += if both_pawns { 2 } else { 1 };
+            for _ in 0..table.material.piece_count {
+                let byte = table.byte(offset)?;
+                for (side, pairs) in table.pairs.iter_mut().enumerate() {
+                    pairs[file]
+                        .pieces
+                        .push(if side == 0 { byte & 0xF } else { byte >> 4 });
+                }
+                offset += 1;
+            }
+            for side in 0..sides {
+                let order = [usize::from(order[side][0]), usize::from(order[side][1])];
+                table.set_groups(side, file, order)?;
+            }
+        }
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = table.set_sizes(side, file, offset)?;
+            }
+        }
+        if kind == Kind::Dtz {
+            offset = table.set_dtz_map(files, offset)?;
+        }
+        for file in 0..files {
+            for pairs in &mut table.pairs {
+                pairs[file].sparse_index = offset;
+                offset += pairs[file].sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for pairs in &mut table.pairs {
+                pairs[file].block_length = offset;
+                offset += pairs[file].block_length_size * 2;
+            }
+        }
+        for file in 0..files {
+            for pairs in &mut table.pairs {
+                offset = (offset + 0x3F) & !0x3F;
+                pairs[file].data = offset;
+                offset += pairs[file].num_blocks * pairs[file].block_size;
+                if pairs[file].num_blocks > 0 && offset > table.len {
+                    return Err("truncated table".to_string());
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    // splits the pieces into the groups indexed together and sets their multipliers,
+    // in the order of the groups given by the file
+    fn set_groups(&mut self, side: usize, file: usize, order: [usize; 2]) -> Result<(), String> {
+        let material = &self.material;
+        let indices = indices();
+        let d = &mut self.pairs[side][file];
+        let mut first_len: i32 = if material.has_pawns {
+            0
+        } else if material.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        d.group_len = vec![1];
+        for i in 1..material.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                *d.group_len.last_mut().expect("No groups") += 1;
+            } else {
+                d.group_len.push(1);
+            }
+        }
+        if d.group_len.iter().any(|&len| len > 5) {
+            return Err("too many pieces in a group".to_string());
+        }
+
+        let groups = d.group_len.len();
+        let both_pawns = material.has_pawns && material.pawn_counts[1] > 0;
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        d.group_idx = vec![0; groups + 1];
+        let mut k = 0;
+        while next < groups || k == order[0] || k == order[1] {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= if material.has_pawns {
+                    indices.lead_pawns_size[d.group_len[0]][file]
+                } else if material.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= indices.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= indices.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+            if k > 16 {
+                return Err("invalid group order".to_string());
+            }
+        }
+        d.group_idx[groups] = idx;
+        Ok(())
+    }
+
+    // reads the sizes and the Huffman code of the compressed data, returning the offset
+    // after them
+    fn set_sizes(&mut self, side: usize, file: usize, mut offset: usize) -> Result<usize, String> {
+        let flags = self.byte(offset)?;
+        offset += 1;
+        if flags & FLAG_SINGLE_VALUE != 0 {
+            let value = usize::from(self.byte(offset)?);
+            let d = &mut self.pairs[side][file];
+            d.flags = flags;
+            d.min_sym_len = value;
+            return Ok(offset + 1);
+        }
+
+        let block_bits = self.byte(offset)?;
+        let span_bits = self.byte(offset + 1)?;
+        let padding = usize::from(self.byte(offset + 2)?);
+        let num_blocks = self.u32_le(offset + 3)? as usize;
+        let max_sym_len = usize::from(self.byte(offset + 7)?);
+        let min_sym_len = usize::from(self.byte(offset + 8)?);
+        offset += 9;
+        if block_bits >= 32
+            || span_bits >= 32
+            || min_sym_len == 0
+            || max_sym_len < min_sym_len
+            || max_sym_len > 32
+        {
+            return Err("invalid compression parameters".to_string());
+        }
+
+        // the lowest symbol of each length gives the lowest code of each length, in the
+        // canonical code where longer codes have lower values
+        let lowest_sym = offset;
+        let lengths = max_sym_len - min_sym_len + 1;
+        let mut base64 = vec![0u64; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = u64::from(self.u16_le(lowest_sym + 2 * i)?);
+            let next_lowest = u64::from(self.u16_le(lowest_sym + 2 * (i + 1))?);
+            base64[i] = (base64[i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in base64.iter_mut().enumerate() {
+            *base = base.checked_shl((64 - i - min_sym_len) as u32).unwrap_or(0);
+        }
+        offset += lengths * 2;
+        let symbols = usize::from(self.u16_le(offset)?);
+        offset += 2;
+        let btree = offset;
+        if btree + symbols * 3 > self.len {
+            return Err("truncated table".to_string());
+        }
+
+        // symbols stand for pairs of symbols, down to single values
+        let mut symlen = vec![0u8; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                self.set_symlen(btree, symbol, &mut symlen, &mut visited)?;
+            }
+        }
+
+        let tb_size = *self.pairs[side][file].group_idx.last().expect("No groups");
+        let d = &mut self.pairs[side][file];
+        d.flags = flags;
+        d.block_size = 1 << block_bits;
+        d.span = 1 << span_bits;
+        d.sparse_index_size = tb_size.div_ceil(d.span) as usize;
+        d.num_blocks = num_blocks;
+        d.block_length_size = num_blocks + padding;
+        d.min_sym_len = min_sym_len;
+        d.lowest_sym = lowest_sym;
+        d.base64 = base64;
+        d.symlen = symlen;
+        d.btree = btree;
+        Ok(btree + symbols * 3 + (symbols & 1))
+    }
+
+    // sets the length of a symbol and of the symbols it pairs
+    fn set_symlen(
+        &self,
+        btree: usize,
+        symbol: usize,
+        symlen: &mut [u8],
+        visited: &mut [bool],
+    ) -> Result<(), String> {
+        visited[symbol] = true;
+        let (left, right) = self.pair(btree, symbol);
+        if right == LEAF {
+            return Ok(());
+        }
+        if left >= symlen.len() || right >= symlen.len() {
+            return Err("invalid symbol".to_string());
+        }
+        for &child in &[left, right] {
+            if !visited[child] {
+                self.set_symlen(btree, child, symlen, visited)?;
+            }
+        }
+        symlen[symbol] = (i32::from(symlen[left]) + i32::from(symlen[right]) + 1) as u8;
+        Ok(())
+    }
+
+    // reads the offsets of the maps from stored DTZ values to distances, returning the
+    // offset after them
+    fn set_dtz_map(&mut self, files: usize, mut offset: usize) -> Result<usize, String> {
+        self.map = offset;
+        for file in 0..files {
+            let flags = self.pairs[0][file].flags;
+            if flags & FLAG_MAPPED == 0 {
+                continue;
+            }
+            if flags & FLAG_WIDE != 0 {
+                offset += offset & 1;
+                for i in 0..4 {
+                    self.pairs[0][file].map_idx[i] = (offset - self.map) / 2 + 1;
+                    offset += 2 * usize::from(self.u16_le(offset)?) + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    self.pairs[0][file].map_idx[i] = offset - self.map + 1;
+                    offset += usize::from(self.byte(offset)?) + 1;
+                }
+            }
+        }
+        Ok(offset + (offset & 1))
+    }
+
+    // returns the value stored for the state, or `None` if the DTZ table is stored for
+    // the other player to move. Black's pieces are looked up as white's if the table is
+    // stored with them on white's side, or if the material is symmetric and black is to
+    // move, as only white to move is stored then.
+    fn probe(&self, state: &GameState, black_stronger: bool, wdl: Wdl) -> Option<i32> {
+        let indices = indices();
+        let black_to_move = state.player_turn == Player::Black;
+        let flip = black_stronger || (self.material.symmetric && black_to_move);
+        let flip_colour = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side = usize::from(flip != black_to_move);
+
+        let mut squares = Vec::with_capacity(self.material.piece_count);
+        let mut pieces = Vec::with_capacity(self.material.piece_count);
+        let mut lead_pawns = 0;
+        let mut file = 0;
+
+        // pawn tables are split by the file of the leading pawn, the one nearest the
+        // edge and then the lowest rank
+        if self.material.has_pawns {
+            let piece = self.pairs[0][0].pieces[0] ^ flip_colour;
+            let player = if piece & 8 == 0 {
+                Player::White
+            } else {
+                Player::Black
+            };
+            lead_pawns = state.player_board(player).pawns.to_u64();
+            for square in state.player_board(player).pawns.iter() {
+                squares.push(square.to_index() ^ flip_squares);
+                pieces.push(piece ^ flip_colour);
+            }
+            let lead = (0..squares.len())
+                .max_by_key(|&i| indices.map_pawns[usize::from(squares[i])])
+                .expect("No leading pawns");
+            squares.swap(0, lead);
+            file = usize::from(squares[0] % 8);
+            if file > 3 {
+                file = 7 - file;
+            }
+        }
+        let lead_count = squares.len();
+
+        if self.kind == Kind::Dtz {
+            let stored = usize::from(self.pairs[0][file].flags & FLAG_STM);
+            let both_stored = self.material.symmetric && !self.material.has_pawns;
+            if stored != side && !both_stored {
+                return None;
+            }
+        }
+
+        for index in 0..64 {
+            if lead_pawns & (1 << index) != 0 {
+                continue;
+            }
+            if let Some((piece, player)) = state.piece_at(Square::new(index)) {
+                squares.push(index ^ flip_squares);
+                pieces.push(code(piece, player) ^ flip_colour);
+            }
+        }
+        let d = &self.pairs[side % self.pairs.len()][file];
+
+        // order the pieces as the table indexes them
+        let size = squares.len();
+        for i in lead_count..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // mirror the board so that the leading piece is on files A to D
+        if squares[0] % 8 > 3 {
+            for square in &mut squares {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx = if self.material.has_pawns {
+            let mut idx = indices.lead_pawn_idx[lead_count][usize::from(squares[0])];
+            squares[1..lead_count].sort_by_key(|&square| indices.map_pawns[usize::from(square)]);
+            for (i, &square) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += indices.binomial[i][indices.map_pawns[usize::from(square)]];
+            }
+            idx
+        } else {
+            // mirror the board so that the leading piece is on ranks 1 to 4, then so
+            // that the first leading piece off the long diagonal is below it
+            if squares[0] / 8 > 3 {
+                for square in &mut squares {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let diagonal = off_diagonal(squares[i]);
+                if diagonal == 0 {
+                    continue;
+                }
+                if diagonal > 0 {
+                    for square in &mut squares[i..] {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            if self.material.has_unique_pieces {
+                unique_pieces_index(&squares)
+            } else {
+                indices.map_kk[indices.map_a1d1d4[usize::from(squares[0])]][usize::from(squares[1])]
+            }
+        };
+
+        // the remaining groups each choose their squares among those left, in order
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.material.has_pawns && self.material.pawn_counts[1] > 0;
+        for next in 1..d.group_len.len() {
+            let len = d.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let square = usize::from(squares[start + i]);
+                let adjust = squares[..start]
+                    .iter()
+                    .filter(|&&s| usize::from(s) < square)
+                    .count();
+                let pawn_adjust = if remaining_pawns { 8 } else { 0 };
+                n += indices.binomial[i + 1][square - adjust - pawn_adjust];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+        }
+
+        let value = i32::from(self.decompress(d, idx));
+        Some(match self.kind {
+            Kind::Wdl => value - 2,
+            Kind::Dtz => self.map_score(d, value, wdl),
+        })
+    }
+
+    // returns the value at the given index of the compressed data
+    fn decompress(&self, d: &PairsData, idx: u64) -> u16 {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return d.min_sym_len as u16;
+        }
+
+        // the sparse index gives the block and offset of the value in the middle of
+        // every span, from which the blocks are walked to the value
+        let k = (idx / d.span) as usize;
+        let mut block = self.u32_le(d.sparse_index + 6 * k).unwrap_or(0) as usize;
+        let mut offset = i64::from(self.u16_le(d.sparse_index + 6 * k + 4).unwrap_or(0));
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+        let block_length =
+            |block: usize| i64::from(self.u16_le(d.block_length + 2 * block).unwrap_or(0));
+        while offset < 0 && block > 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) && block + 1 < d.block_length_size {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        // decode the symbols of the block until the one holding the value, reading past its
+        // end as zeroes
+        let start = d.data + block * d.block_size;
+        let mut data = vec![0; d.block_size + 8];
+        let available = self.len.saturating_sub(start).min(data.len());
+        if self.read(start, &mut data[..available]).is_err() {
+            return 0;
+        }
+        let u32_be = |ptr: usize| {
+            let bytes = data.get(ptr..ptr + 4).unwrap_or(&[0; 4]);
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+        let mut buf64 = u64::from(u32_be(0)) << 32 | u64::from(u32_be(4));
+        let mut ptr = 8;
+        let mut buf64_size = 64;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while len + 1 < d.base64.len() && buf64 < d.base64[len] {
+                len += 1;
+            }
+            symbol = (buf64.wrapping_sub(d.base64[len]) >> (64 - len - d.min_sym_len)) as usize;
+            symbol += usize::from(self.u16_le(d.lowest_sym + 2 * len).unwrap_or(0));
+            if symbol >= d.symlen.len() {
+                return 0;
+            }
+            let values = i64::from(d.symlen[symbol]) + 1;
+            if offset < values {
+                break;
+            }
+            offset -= values;
+            len += d.min_sym_len;
+            buf64 = buf64.checked_shl(len as u32).unwrap_or(0);
+            buf64_size -= len as i32;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= u64::from(u32_be(ptr)) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // descend the pairs of the symbol to the value
+        while d.symlen[symbol] != 0 {
+            let (left, right) = self.pair(d.btree, symbol);
+            let values = i64::from(d.symlen[left]) + 1;
+            if offset < values {
+                symbol = left;
+            } else {
+                offset -= values;
+                symbol = right;
+            }
+        }
+        self.pair(d.btree, symbol).0 as u16
+    }
+
+    // returns the distance to zeroing in plies of a stored DTZ value
+    fn map_score(&self, d: &PairsData, mut value: i32, wdl: Wdl) -> i32 {
+        if d.flags & FLAG_MAPPED != 0 {
+            let map = d.map_idx[match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::CursedWin => 2,
+                Wdl::Draw | Wdl::Win => 0,
+            }];
+            value = if d.flags & FLAG_WIDE != 0 {
+                i32::from(
+                    self.u16_le(self.map + 2 * (map + value as usize))
+                        .unwrap_or(0),
+                )
+            } else {
+                i32::from(self.byte(self.map + map + value as usize).unwrap_or(0))
+            };
+        }
+        let plies = match wdl {
+            Wdl::Win => d.flags & FLAG_WIN_PLIES != 0,
+            Wdl::Loss => d.flags & FLAG_LOSS_PLIES != 0,
+            _ => false,
+        };
+        if !plies {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    // returns the left and right symbols of a symbol's pair, the left holding the value
+    // of a leaf
+    fn pair(&self, btree: usize, symbol: usize) -> (usize, usize) {
+        let mut bytes = [0xFF; 3];
+        if self.read(btree + 3 * symbol, &mut bytes).is_err() {
+            bytes = [0xFF; 3];
+        }
+        let lr = |i: usize| usize::from(bytes[i]);
+        ((lr(1) & 0xF) << 8 | lr(0), lr(2) << 4 | lr(1) >> 4)
+    }
+
+    // fills the buffer with the bytes at the given offset, from the start of the file if
+    // it is in memory, or else from the file itself
+    fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), String> {
+        if offset + buffer.len() > self.len {
+            return Err("truncated table".to_string());
+        }
+        if let Some(bytes) = self.head.get(offset..offset + buffer.len()) {
+            buffer.copy_from_slice(bytes);
+            return Ok(());
+        }
+        let mut file = self.file.lock().expect("Poisoned table file");
+        file.seek(SeekFrom::Start(offset as u64))
+            .and_then(|_| file.read_exact(buffer))
+            .map_err(|e| e.to_string())
+    }
+
+    fn byte(&self, offset: usize) -> Result<u8, String> {
+        let mut bytes = [0; 1];
+        self.read(offset, &mut bytes)?;
+        Ok(bytes[0])
+    }
+
+    fn u16_le(&self, offset: usize) -> Result<u16, String> {
+        let mut bytes = [0; 2];
+        self.read(offset, &mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32_le(&self, offset: usize) -> Result<u32, String> {
+        let mut bytes = [0; 4];
+        self.read(offset, &mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+}
+
+// the tables used to index positions
+struct Indices {
+    // the number of ways to choose k of n squares
+    binomial: [[u64; 64]; 6],
+    // the squares A2 to H7, numbered so that the leading pawn has the highest number
+    map_pawns: [usize; 64],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+    // the squares below the long diagonal, numbered from 0 to 27
+    map_b1h1h7: [u64; 64],
+    // the squares of the A1-D1-D4 triangle, numbered from 0 to 9 with the diagonal last
+    map_a1d1d4: [usize; 64],
+    // the legal placements of two kings, the first in the triangle
+    map_kk: [[u64; 64]; 10],
+}
+
+// returns the tables used to index positions, computing them the first time
+fn indices() -> &'static Indices {
+    static INDICES: OnceLock<Indices> = OnceLock::new();
+    INDICES.get_or_init(|| {
+        let mut indices = Indices {
+            binomial: [[0; 64]; 6],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                indices.map_b1h1h7[usize::from(square)] = code;
+                code += 1;
+            }
+        }
+
+        let mut code = 0;
+        let mut diagonal = vec![];
+        for square in 0..28 {
+            if square % 8 > 3 {
+                continue;
+            }
+            if off_diagonal(square) < 0 {
+                indices.map_a1d1d4[usize::from(square)] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            indices.map_a1d1d4[usize::from(square)] = code;
+            code += 1;
+        }
+
+        // both kings on the diagonal come last
+        let mut code = 0;
+        let mut both_on_diagonal = vec![];
+        for idx in 0..10 {
+            for first in 0..28u8 {
+                if indices.map_a1d1d4[usize::from(first)] != idx || (idx == 0 && first != 1) {
+                    continue;
+                }
+                for second in 0..64u8 {
+                    let rank_distance = (i32::from(first / 8) - i32::from(second / 8)).abs();
+                    let file_distance = (i32::from(first % 8) - i32::from(second % 8)).abs();
+                    if rank_distance <= 1 && file_distance <= 1 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        indices.map_kk[idx][usize::from(second)] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            indices.map_kk[idx][usize::from(second)] = code;
+            code += 1;
+        }
+
+        indices.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                indices.binomial[k][n] = if k > 0 {
+                    indices.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { indices.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // the leading pawn leaves fewer squares for the others the further it is from
+        // the edge and from rank 2
+        let mut available = 47;
+        for lead_count in 1..6 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_count == 1 {
+                        indices.map_pawns[square] = available;
+                        indices.map_pawns[square ^ 7] = available.saturating_sub(1);
+                        available = available.saturating_sub(2);
+                    }
+                    indices.lead_pawn_idx[lead_count][square] = idx;
+                    idx += indices.binomial[lead_count - 1][indices.map_pawns[square]];
+                }
+                indices.lead_pawns_size[lead_count][file] = idx;
+            }
+        }
+        indices
+    })
+}
+
+// returns the index of a pawnless position with unique pieces, which places the first
+// three pieces together
+fn unique_pieces_index(squares: &[u8]) -> u64 {
+    let indices = indices();
+    let square = |i: usize| u64::from(squares[i]);
+    let rank = |i: usize| u64::from(squares[i] / 8);
+    let adjust1 = u64::from(squares[1] > squares[0]);
+    let adjust2 = u64::from(squares[2] > squares[0]) + u64::from(squares[2] > squares[1]);
+    if off_diagonal(squares[0]) != 0 {
+        (indices.map_a1d1d4[usize::from(squares[0])] as u64 * 63 + square(1) - adjust1) * 62
+            + square(2)
+            - adjust2
+    } else if off_diagonal(squares[1]) != 0 {
+        (6 * 63 + rank(0) * 28 + indices.map_b1h1h7[usize::from(squares[1])]) * 62 + square(2)
+            - adjust2
+    } else if off_diagonal(squares[2]) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(0) * 7 * 28
+            + (rank(1) - adjust1) * 28
+            + indices.map_b1h1h7[usize::from(squares[2])]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(0) * 7 * 6
+            + (rank(1) - adjust1) * 6
+            + (rank(2) - adjust2)
+    }
+}
+
+// returns the rank less the file of a square, which is 0 on the long diagonal
+fn off_diagonal(square: u8) -> i32 {
+    i32::from(square / 8) - i32::from(square % 8)
+}
+
+// returns the code of a piece in table files
+fn code(piece: Piece, player: Player) -> u8 {
+    let kind = match piece {
+        Piece::Pawn => 1,
+        Piece::Knight => 2,
+        Piece::Bishop => 3,
+        Piece::Rook => 4,
+        Piece::Queen => 5,
+        Piece::King => 6,
+    };
+    match player {
+        Player::White => kind,
+        Player::Black => kind | 8,
+    }
+}
+
+// returns the letters of a player's pieces, strongest first, as in table names
+fn side_name(state: &GameState, player: Player) -> String {
+    let board = state.player_board(player);
+    let mut name = String::new();
+    for &(piece, letter) in &[
+        (Piece::King, 'K'),
+        (Piece::Queen, 'Q'),
+        (Piece::Rook, 'R'),
+        (Piece::Bishop, 'B'),
+        (Piece::Knight, 'N'),
+        (Piece::Pawn, 'P'),
+    ] {
+        for _ in 0..board.piece(piece).count() {
+            name.push(letter);
+        }
+    }
+    name
+}
@@ -0,0 +1,976 @@
+//! Endgame tablebases generated by retrograde analysis.
+//!
+//! A table holds, for every position with a given material, whether the player to move
+//! wins, draws or loses, and how many plies a win or loss takes to checkmate with best
+//! play. Tables are generated from the engine's own move rules, so no external files are
+//! needed, for material of up to [MAX_PIECES] pieces including both kings.
+//!
+//! Generation starts from the positions whose results are known without searching:
+//! checkmates, stalemates, and positions whose captures and promotions lead into smaller
+//! tables. It then works backwards one ply at a time, undoing moves from the positions
+//! classified at the previous ply. A position is a win as soon as one of its moves leads
+//! to a loss, and a loss once every one of its moves leads to a win. Positions never
+//! classified are draws. Castling and the fifty-move rule are ignored, and en-passant
+//! captures are not considered within a table.
+//!
+//! Material is named with white's pieces, then "v", then black's, such as "KQvKR". Only
+//! the orientation with the stronger pieces on white's side is stored, and positions are
+//! mirrored onto it when probed. Within a table, positions are indexed by the side to
+//! move and the squares of each piece, with the board mirrored so that white's king is
+//! on files A to D, and without pawns also on ranks 1 to 4 below the long diagonal. The
+//! side to move is the most significant part of the index, and the last piece the
+//! least, so that neighbouring positions tend to share results and compress well.
+//!
+//! Tables are stored in little-endian binary files named after their material with a
+//! `.gtb` extension, holding:
+//!
+//! ```text
+//! 4 bytes       the magic number "GTB1"
+//! u8            the length of the material name, followed by the name
+//! u32           the number of positions N
+//! u32           the number of blocks B, each of BLOCK_SIZE positions
+//! u32 × B       the offset of each block in the runs
+//! u32           the length of the runs in bytes
+//! (u8, u8) × …  the runs, each a value and a count from 1 to 255
+//! ```
+//!
+//! A value of 0 is a draw, 255 is an illegal position, and any other value `v` is a
+//! result `v - 1` plies from mate, won by the player to move if odd and lost if even.
+//! Illegal positions are merged into the runs of the positions around them, and are only
+//! stored as such when nothing else is in their block.
+
+use board::bitboard::BitBoard;
+use board::piece::Piece;
+use board::player::Player;
+use board::square::Square;
+use rules::castle_rights::CastleRights;
+use rules::game_state::GameState;
+use rules::move_application::Move;
+use rules::player_board::PlayerBoard;
+use rules::variant::Variant;
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The largest number of pieces, including both kings, of the generated tables.
+pub const MAX_PIECES: usize = 4;
+
+/// The score of a position won according to a table, less the plies to mate.
+pub const TABLEBASE_WIN: i32 = 100000;
+
+/// The number of positions in each independently compressed block.
+pub const BLOCK_SIZE: usize = 1024;
+
+/// The extension of table files.
+pub const EXTENSION: &str = "gtb";
+
+/// The magic number starting every table file.
+const MAGIC: &[u8; 4] = b"GTB1";
+
+// the stored values of drawn and illegal positions
+const DRAW: u8 = 0;
+const INVALID: u8 = 255;
+
+// the value of positions not classified yet while a table is generated
+const UNKNOWN: u8 = 254;
+
+// the most plies to mate which can be stored
+const MAX_PLIES: usize = 252;
+
+// the pieces in the order they are named and indexed
+const ORDER: [Piece; 6] = [
+    Piece::King,
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+];
+
+// the squares of white's king in tables without pawns: A1 to D1, B2 to D2, C3, D3 and D4
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+/// The result of a position for the player to move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableResult {
+    /// The player to move mates in the given number of plies.
+    Win(u8),
+
+    /// The position is drawn.
+    Draw,
+
+    /// The player to move is mated in the given number of plies.
+    Loss(u8),
+}
+
+impl TableResult {
+    /// Returns the score of the result in centipawns for the player to move, preferring
+    /// quicker wins and slower losses.
+    pub fn score(self) -> i32 {
+        match self {
+            TableResult::Win(plies) => TABLEBASE_WIN - plies as i32,
+            TableResult::Draw => 0,
+            TableResult::Loss(plies) => plies as i32 - TABLEBASE_WIN,
+        }
+    }
+
+    /// Returns the result for the player who made the move leading to a position with
+    /// this result.
+    pub fn before_move(self) -> TableResult {
+        match self {
+            TableResult::Win(plies) => TableResult::Loss(plies + 1),
+            TableResult::Draw => TableResult::Draw,
+            TableResult::Loss(plies) => TableResult::Win(plies + 1),
+        }
+    }
+
+    // decodes a stored value, which must not be illegal
+    fn from_value(value: u8) -> TableResult {
+        match value {
+            DRAW => TableResult::Draw,
+            value if value % 2 == 0 => TableResult::Win(value - 1),
+            value => TableResult::Loss(value - 1),
+        }
+    }
+}
+
+/// The pieces of a table: white's then black's, each starting with the king and
+/// followed by queens, rooks, bishops, knights and pawns.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Material {
+    pieces: Vec<(Piece, Player)>,
+}
+
+impl Material {
+    /// Parses material from its name, such as "KQvKR".
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let sides: Vec<&str> = name.split('v').collect();
+        if sides.len() != 2 {
+            return Err(format!("Invalid material: {}", name));
+        }
+        let mut pieces = Vec::new();
+        for (side, &player) in sides.iter().zip([Player::White, Player::Black].iter()) {
+            let mut side_pieces = side
+                .chars()
+                .map(|letter| match letter {
+                    'K' => Ok(Piece::King),
+                    'Q' => Ok(Piece::Queen),
+                    'R' => Ok(Piece::Rook),
+                    'B' => Ok(Piece::Bishop),
+                    'N' => Ok(Piece::Knight),
+                    'P' => Ok(Piece::Pawn),
+                    _ => Err(format!("Invalid piece '{}' in material: {}", letter, name)),
+                })
+                .collect::<Result<Vec<Piece>, String>>()?;
+            if side_pieces.iter().filter(|&&piece| piece == Piece::King).count() != 1 {
+                return Err(format!("Each side needs one king: {}", name));
+            }
+            side_pieces.sort_by_key(|&piece| order(piece));
+            pieces.extend(side_pieces.into_iter().map(|piece| (piece, player)));
+        }
+        Ok(Material { pieces })
+    }
+
+    /// Returns the material of the given state, which may lack kings.
+    pub fn of(state: &GameState) -> Self {
+        let mut pieces = Vec::new();
+        for &player in [Player::White, Player::Black].iter() {
+            let board = state.player_board(player);
+            for &piece in ORDER.iter() {
+                let count = board.piece(piece).count() as usize;
+                pieces.extend(vec![(piece, player); count]);
+            }
+        }
+        Material { pieces }
+    }
+
+    /// Returns the name of the material, such as "KQvKR".
+    pub fn name(&self) -> String {
+        let side = |player: Player| -> String {
+            self.pieces
+                .iter()
+                .filter(|&&(_, owner)| owner == player)
+                .map(|&(piece, _)| letter(piece))
+                .collect()
+        };
+        format!("{}v{}", side(Player::White), side(Player::Black))
+    }
+
+    /// Returns the number of pieces, including the kings.
+    pub fn count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Returns true if either side has pawns.
+    pub fn has_pawns(&self) -> bool {
+        self.pieces.iter().any(|&(piece, _)| piece == Piece::Pawn)
+    }
+
+    /// Returns the material with the players' pieces swapped.
+    pub fn flipped(&self) -> Self {
+        let mut pieces: Vec<(Piece, Player)> = self.pieces
+            .iter()
+            .map(|&(piece, player)| (piece, player.other()))
+            .collect();
+        pieces.sort_by_key(|&(piece, player)| (player != Player::White, order(piece)));
+        Material { pieces }
+    }
+
+    /// Returns true if this is the orientation of the material which is stored: the one
+    /// where white has more pieces, or as many pieces with the strongest difference.
+    pub fn is_canonical(&self) -> bool {
+        let side = |player: Player| {
+            let pieces: Vec<usize> = self.pieces
+                .iter()
+                .filter(|&&(_, owner)| owner == player)
+                .map(|&(piece, _)| ORDER.len() - order(piece))
+                .collect();
+            (pieces.len(), pieces)
+        };
+        side(Player::White) >= side(Player::Black)
+    }
+
+    /// Returns the orientation of the material which is stored.
+    pub fn canonical(&self) -> Self {
+        if self.is_canonical() {
+            self.clone()
+        } else {
+            self.flipped()
+        }
+    }
+
+    /// Returns the number of positions indexed in the material's table, including
+    /// illegal ones.
+    pub fn size(&self) -> usize {
+        2 * king_squares(self.has_pawns()).len() * 64usize.pow(self.count() as u32 - 1)
+    }
+
+    /// Returns the index of a state with this material in its table.
+    ///
+    /// Positions which are reflections of each other share an index.
+    pub fn index(&self, state: &GameState) -> usize {
+        let pawns = self.has_pawns();
+        let king = state.player_board(Player::White).king.to_square().to_index();
+        let symmetry = Symmetry::normalising(king, pawns);
+        let index = self.symmetric_index(state, symmetry);
+
+        // a king on the diagonal is left there by its reflection, so the smaller index
+        // of the two is used
+        let king = symmetry.apply(king);
+        if pawns || king / 8 != king % 8 {
+            return index;
+        }
+        let reflected = Symmetry {
+            diagonal: !symmetry.diagonal,
+            ..symmetry
+        };
+        cmp::min(index, self.symmetric_index(state, reflected))
+    }
+
+    /// Returns the position with the given index, or `None` if it is illegal or is
+    /// never produced by [Material::index].
+    pub fn position(&self, index: usize) -> Option<GameState> {
+        let mut rest = index;
+        let mut squares = vec![0u8; self.count()];
+        for square in squares.iter_mut().skip(1).rev() {
+            *square = (rest % 64) as u8;
+            rest /= 64;
+        }
+        let kings = king_squares(self.has_pawns());
+        let player_turn = if rest < kings.len() { Player::White } else { Player::Black };
+        squares[0] = *kings.get(rest % kings.len())?;
+        if rest >= 2 * kings.len() {
+            return None;
+        }
+
+        let mut boards = [PlayerBoard::new(), PlayerBoard::new()];
+        let mut occupied = BitBoard::empty();
+        for (start, end) in self.groups() {
+            let (piece, player) = self.pieces[start];
+            for &square in squares[start..end].iter() {
+                let bit = Square::new(square).to_bitboard();
+                let pawn_on_last_ranks = piece == Piece::Pawn && !(8..56).contains(&square);
+                if !(occupied & bit).is_empty() || pawn_on_last_ranks {
+                    return None;
+                }
+                occupied |= bit;
+                let board = &mut boards[player as usize];
+                *board = board.with_piece(piece, board.piece(piece) | bit);
+            }
+        }
+        let state = position(boards[0], boards[1], player_turn);
+        if state.is_check(player_turn.other()) || self.index(&state) != index {
+            return None;
+        }
+        Some(state)
+    }
+
+    // returns the index of a state after applying a symmetry which brings white's king
+    // to its indexed squares
+    fn symmetric_index(&self, state: &GameState, symmetry: Symmetry) -> usize {
+        let king = symmetry.apply(state.player_board(Player::White).king.to_square().to_index());
+        let mut index = king_squares(self.has_pawns())
+            .iter()
+            .position(|&square| square == king)
+            .expect("King outside of the indexed squares");
+        if state.player_turn == Player::Black {
+            index += king_squares(self.has_pawns()).len();
+        }
+
+        // each group of identical pieces is indexed in increasing order of squares
+        for (start, end) in self.groups().into_iter().skip(1) {
+            let (piece, player) = self.pieces[start];
+            let mut squares: Vec<u8> = state
+                .player_board(player)
+                .piece(piece)
+                .iter()
+                .map(|square| symmetry.apply(square.to_index()))
+                .collect();
+            debug_assert!(squares.len() == end - start);
+            squares.sort_unstable();
+            for square in squares {
+                index = index * 64 + square as usize;
+            }
+        }
+        index
+    }
+
+    // returns the ranges of identical pieces
+    fn groups(&self) -> Vec<(usize, usize)> {
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        for (index, piece) in self.pieces.iter().enumerate() {
+            match groups.last_mut() {
+                Some(group) if self.pieces[group.0] == *piece => group.1 = index + 1,
+                _ => groups.push((index, index + 1)),
+            }
+        }
+        groups
+    }
+
+    // returns the canonical materials reached by a capture or promotion, other than
+    // kings alone
+    fn dependencies(&self) -> Vec<Material> {
+        let mut dependencies: Vec<Material> = Vec::new();
+        for (index, &(piece, player)) in self.pieces.iter().enumerate() {
+            if piece == Piece::King {
+                continue;
+            }
+            let mut replacements = vec![None];
+            if piece == Piece::Pawn {
+                replacements.extend(Piece::iter_pieces().map(Some));
+            }
+            for replacement in replacements {
+                let mut pieces = self.pieces.clone();
+                match replacement {
+                    Some(promotion) => pieces[index] = (promotion, player),
+                    None => {
+                        pieces.remove(index);
+                    }
+                }
+                pieces.sort_by_key(|&(piece, player)| (player != Player::White, order(piece)));
+                let material = Material { pieces }.canonical();
+                if material.count() > 2 && !dependencies.contains(&material) {
+                    dependencies.push(material);
+                }
+            }
+        }
+        dependencies
+    }
+}
+
+/// Returns every canonical material with three to the given number of pieces, smaller
+/// materials first.
+pub fn all_materials(max_pieces: usize) -> Vec<Material> {
+    let mut materials: Vec<Material> = Vec::new();
+    for extra in 1..max_pieces.saturating_sub(1) {
+        for white in 0..extra + 1 {
+            for white_pieces in combinations(white, 0) {
+                for black_pieces in combinations(extra - white, 0) {
+                    let name = format!("K{}vK{}", white_pieces, black_pieces);
+                    let material = Material::from_name(&name)
+                        .expect("Invalid generated material")
+                        .canonical();
+                    if !materials.contains(&material) {
+                        materials.push(material);
+                    }
+                }
+            }
+        }
+    }
+    materials
+}
+
+// returns the names of the multisets of the given size of pieces other than kings, using
+// pieces from the given position in the naming order on
+fn combinations(size: usize, first: usize) -> Vec<String> {
+    if size == 0 {
+        return vec![String::new()];
+    }
+    let mut names = Vec::new();
+    for (index, &piece) in ORDER.iter().enumerate().skip(first.max(1)) {
+        for rest in combinations(size - 1, index) {
+            names.push(format!("{}{}", letter(piece), rest));
+        }
+    }
+    names
+}
+
+/// The results of every position with a given material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    material: Material,
+    positions: usize,
+    offsets: Vec<u32>,
+    runs: Vec<u8>,
+}
+
+impl Table {
+    /// Generates the table of the given canonical material, whose captures and
+    /// promotions lead to positions found in the given tablebase.
+    pub fn generate(material: &Material, tablebase: &Tablebase) -> Result<Self, String> {
+        if !material.is_canonical() {
+            return Err(format!("Material is not canonical: {}", material.name()));
+        }
+        if material.count() > MAX_PIECES {
+            return Err(format!("Too many pieces: {}", material.name()));
+        }
+        let values = retrograde(material, tablebase)?;
+        Ok(Table::from_values(material.clone(), &values))
+    }
+
+    /// Reads a table from the binary format described in the module documentation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 5 || &bytes[..4] != MAGIC {
+            return Err("Not a tablebase file".to_string());
+        }
+        let name_length = bytes[4] as usize;
+        let name = bytes
+            .get(5..5 + name_length)
+            .and_then(|name| String::from_utf8(name.to_vec()).ok())
+            .ok_or("Truncated tablebase file")?;
+        let material = Material::from_name(&name)?;
+
+        let mut offset = 5 + name_length;
+        let mut read_u32 = || -> Result<u32, String> {
+            let value = bytes.get(offset..offset + 4).ok_or("Truncated tablebase file")?;
+            offset += 4;
+            Ok(u32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+        };
+        let positions = read_u32()? as usize;
+        if positions != material.size() {
+            return Err(format!(
+                "Expected {} positions for {}, found {}",
+                material.size(),
+                name,
+                positions
+            ));
+        }
+        let blocks = read_u32()? as usize;
+        if blocks != positions.div_ceil(BLOCK_SIZE) {
+            return Err(format!("Invalid number of blocks: {}", blocks));
+        }
+        let offsets = (0..blocks).map(|_| read_u32()).collect::<Result<Vec<u32>, String>>()?;
+        let length = read_u32()? as usize;
+        let runs = bytes.get(offset..).ok_or("Truncated tablebase file")?;
+        if runs.len() != length || !length.is_multiple_of(2) {
+            return Err(format!("Expected {} bytes of runs, found {}", length, runs.len()));
+        }
+        if offsets.iter().any(|&start| start as usize > length) {
+            return Err("Invalid block offset".to_string());
+        }
+        Ok(Table {
+            material,
+            positions,
+            offsets,
+            runs: runs.to_vec(),
+        })
+    }
+
+    /// Writes the table in the binary format described in the module documentation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let name = self.material.name();
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&(self.positions as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.offsets.len() as u32).to_le_bytes());
+        for offset in self.offsets.iter() {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.runs.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.runs);
+        bytes
+    }
+
+    /// Loads a table from the file at the given path.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Table::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Returns the material of the table.
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Returns the result of the position with the given index.
+    ///
+    /// Illegal positions are not stored separately, and share the results of the
+    /// positions around them, so only give `None` when a whole block is illegal.
+    pub fn result(&self, index: usize) -> Option<TableResult> {
+        match self.value(index) {
+            INVALID => None,
+            value => Some(TableResult::from_value(value)),
+        }
+    }
+
+    /// Returns the result of the given state, which must have the table's material,
+    /// for the player to move.
+    pub fn probe(&self, state: &GameState) -> Option<TableResult> {
+        self.result(self.material.index(state))
+    }
+
+    // compresses the values of every position, letting illegal positions join the runs
+    // around them
+    fn from_values(material: Material, values: &[u8]) -> Self {
+        let mut offsets = Vec::new();
+        let mut runs: Vec<u8> = Vec::new();
+        for block in values.chunks(BLOCK_SIZE) {
+            offsets.push(runs.len() as u32);
+            let mut start = 0;
+            while start < block.len() {
+                let value = block[start..]
+                    .iter()
+                    .cloned()
+                    .find(|&value| value != INVALID)
+                    .unwrap_or(INVALID);
+                let length = block[start..]
+                    .iter()
+                    .take(255)
+                    .take_while(|&&other| other == value || other == INVALID)
+                    .count();
+                runs.push(value);
+                runs.push(length as u8);
+                start += length;
+            }
+        }
+        Table {
+            material,
+            positions: values.len(),
+            offsets,
+            runs,
+        }
+    }
+
+    // returns the stored value of the position with the given index
+    fn value(&self, index: usize) -> u8 {
+        if index >= self.positions {
+            return INVALID;
+        }
+        let mut remaining = index % BLOCK_SIZE;
+        let start = self.offsets[index / BLOCK_SIZE] as usize;
+        for run in self.runs[start..].chunks(2) {
+            if remaining < run[1] as usize {
+                return run[0];
+            }
+            remaining -= run[1] as usize;
+        }
+        INVALID
+    }
+}
+
+/// A set of tables, probed by material.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tablebase {
+    tables: HashMap<String, Table>,
+}
+
+impl Tablebase {
+    /// Returns an empty tablebase.
+    pub fn new() -> Self {
+        Tablebase::default()
+    }
+
+    /// Loads every table file in the given directory.
+    pub fn load_dir(path: &str) -> Result<Self, String> {
+        let mut tablebase = Tablebase::new();
+        let entries = fs::read_dir(path).map_err(|e| format!("{}: {}", path, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("{}: {}", path, e))?.path();
+            if path.extension().is_some_and(|extension| extension == EXTENSION) {
+                tablebase.insert(Table::load(&path)?);
+            }
+        }
+        Ok(tablebase)
+    }
+
+    /// Writes every table to a file in the given directory, returning the paths written.
+    pub fn save_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        fs::create_dir_all(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut names: Vec<&String> = self.tables.keys().collect();
+        names.sort();
+        let mut written = Vec::new();
+        for name in names {
+            let file = Path::new(path).join(format!("{}.{}", name, EXTENSION));
+            fs::write(&file, self.tables[name].to_bytes())
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            written.push(file.display().to_string());
+        }
+        Ok(written)
+    }
+
+    /// Adds a table, replacing any with the same material.
+    pub fn insert(&mut self, table: Table) {
+        self.tables.insert(table.material.name(), table);
+    }
+
+    /// Returns the table with the given canonical material, if present.
+    pub fn table(&self, material: &Material) -> Option<&Table> {
+        self.tables.get(&material.name())
+    }
+
+    /// Returns the number of tables.
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Returns true if there are no tables.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Generates the table of the given material, in either orientation, along with the
+    /// smaller tables it depends on which are missing. Returns the materials generated,
+    /// in the order they were generated.
+    pub fn generate(&mut self, material: &Material) -> Result<Vec<Material>, String> {
+        let material = material.canonical();
+        if material.count() > MAX_PIECES {
+            return Err(format!("Too many pieces: {}", material.name()));
+        }
+        if self.table(&material).is_some() || material.count() <= 2 {
+            return Ok(Vec::new());
+        }
+        let mut generated = Vec::new();
+        for dependency in material.dependencies() {
+            generated.extend(self.generate(&dependency)?);
+        }
+        let table = Table::generate(&material, self)?;
+        info!("Generated tablebase {}", material.name());
+        self.insert(table);
+        generated.push(material);
+        Ok(generated)
+    }
+
+    /// Returns the result of the given state for the player to move, or `None` if no
+    /// table covers it.
+    ///
+    /// Only standard chess positions without castling rights are covered, and not those
+    /// where an en-passant capture is possible. Kings alone are always drawn.
+    pub fn probe(&self, state: &GameState) -> Option<TableResult> {
+        if state.variant != Variant::Standard
+            || state.white_castle_rights != CastleRights::None
+            || state.black_castle_rights != CastleRights::None
+            || can_capture_en_passant(state)
+        {
+            return None;
+        }
+        let material = Material::of(state);
+        if material.count() > MAX_PIECES
+            || state.player_board(Player::White).king.count() != 1
+            || state.player_board(Player::Black).king.count() != 1
+        {
+            return None;
+        }
+        if material.count() == 2 {
+            return Some(TableResult::Draw);
+        }
+        if material.is_canonical() {
+            self.table(&material)?.probe(state)
+        } else {
+            self.table(&material.flipped())?.probe(&flip(state))
+        }
+    }
+
+    /// Returns the move with the best result for the player to move and its result, or
+    /// `None` if the state or any position its moves lead to is not covered.
+    ///
+    /// Wins are chosen by the fewest plies to mate and losses by the most.
+    pub fn best_move(&self, state: &GameState) -> Option<(Move, TableResult)> {
+        self.probe(state)?;
+        let mut state = state.clone();
+        let mut best: Option<(Move, TableResult)> = None;
+        for mv in state.legal_moves() {
+            let undo = state.make_move(&mv);
+            let result = self.probe(&state);
+            state.unmake_move(&mv, &undo);
+            let result = result?.before_move();
+            if best.as_ref().is_none_or(|(_, best)| result.score() > best.score()) {
+                best = Some((mv, result));
+            }
+        }
+        best
+    }
+}
+
+// classifies every position of a material, returning the stored values
+fn retrograde(material: &Material, tablebase: &Tablebase) -> Result<Vec<u8>, String> {
+    let size = material.size();
+    let mut values = vec![INVALID; size];
+    // the distinct positions in the table reached by moves not yet known to lose
+    let mut counters = vec![0u8; size];
+    // the best result of leaving the table through a capture or promotion
+    let mut exits: Vec<Option<TableResult>> = vec![None; size];
+    // the positions to classify at each ply, by the ply they are decided at
+    let mut levels: Vec<Vec<u32>> = Vec::new();
+
+    for index in 0..size {
+        let mut state = match material.position(index) {
+            Some(state) => state,
+            None => continue,
+        };
+        values[index] = UNKNOWN;
+        let moves = state.legal_moves();
+        if moves.is_empty() {
+            if state.is_check(state.player_turn) {
+                schedule(&mut levels, 0, index)?;
+            } else {
+                values[index] = DRAW;
+            }
+            continue;
+        }
+
+        let mut children = Vec::new();
+        for mv in moves {
+            let undo = state.make_move(&mv);
+            if mv.capture || mv.promotion.is_some() {
+                let result = tablebase.probe(&state).ok_or_else(|| {
+                    format!("Missing tablebase {}", Material::of(&state).canonical().name())
+                })?;
+                let result = result.before_move();
+                if exits[index].is_none_or(|best| result.score() > best.score()) {
+                    exits[index] = Some(result);
+                }
+            } else {
+                children.push(material.index(&state));
+            }
+            state.unmake_move(&mv, &undo);
+        }
+        children.sort_unstable();
+        children.dedup();
+        counters[index] = children.len() as u8;
+
+        match exits[index] {
+            Some(TableResult::Win(plies)) => schedule(&mut levels, plies as usize, index)?,
+            Some(TableResult::Draw) if children.is_empty() => values[index] = DRAW,
+            Some(TableResult::Loss(plies)) if children.is_empty() => {
+                schedule(&mut levels, plies as usize, index)?
+            }
+            _ => {}
+        }
+    }
+
+    let mut plies = 0;
+    while plies < levels.len() {
+        for index in levels[plies].split_off(0) {
+            let index = index as usize;
+            if values[index] != UNKNOWN {
+                continue;
+            }
+            values[index] = plies as u8 + 1;
+            let state = material.position(index).expect("Scheduled an illegal position");
+            for previous in predecessors(material, &state) {
+                if values[previous] != UNKNOWN {
+                    continue;
+                }
+                if plies % 2 == 0 {
+                    // a move to a lost position wins
+                    schedule(&mut levels, plies + 1, previous)?;
+                    continue;
+                }
+                // a move to a won position loses, and a position is lost once all are
+                counters[previous] -= 1;
+                if counters[previous] > 0 {
+                    continue;
+                }
+                match exits[previous] {
+                    Some(TableResult::Win(_)) => {}
+                    Some(TableResult::Draw) => values[previous] = DRAW,
+                    Some(TableResult::Loss(exit)) => {
+                        schedule(&mut levels, cmp::max(plies + 1, exit as usize), previous)?
+                    }
+                    None => schedule(&mut levels, plies + 1, previous)?,
+                }
+            }
+        }
+        plies += 1;
+    }
+
+    for value in values.iter_mut().filter(|value| **value == UNKNOWN) {
+        *value = DRAW;
+    }
+    Ok(values)
+}
+
+// adds a position to be classified at the given ply
+fn schedule(levels: &mut Vec<Vec<u32>>, plies: usize, index: usize) -> Result<(), String> {
+    if plies > MAX_PLIES {
+        return Err(format!("Mate in more than {} plies", MAX_PLIES));
+    }
+    if levels.len() <= plies {
+        levels.resize(plies + 1, Vec::new());
+    }
+    levels[plies].push(index as u32);
+    Ok(())
+}
+
+// returns the distinct indices of the positions in the same table from which a move
+// leads to the given state
+fn predecessors(material: &Material, state: &GameState) -> Vec<usize> {
+    let mover = state.player_turn.other();
+    let board = state.player_board(mover);
+    let occupied = state.occupied();
+    let mut indices = Vec::new();
+    for piece in Piece::iter() {
+        for target in board.piece(piece).iter() {
+            let origins = if piece == Piece::Pawn {
+                pawn_origins(target, mover, occupied)
+            } else {
+                piece.attacked_squares(target, mover, occupied) & !occupied
+            };
+            for origin in origins.iter() {
+                let pieces = board.piece(piece).unset_square(target).set_square(origin);
+                let moved = board.with_piece(piece, pieces);
+                let previous = match mover {
+                    Player::White => position(moved, state.player_board(Player::Black), mover),
+                    Player::Black => position(state.player_board(Player::White), moved, mover),
+                };
+                if !previous.is_check(state.player_turn) {
+                    indices.push(material.index(&previous));
+                }
+            }
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+// returns the squares a pawn on the given square could have been pushed from
+fn pawn_origins(target: Square, player: Player, occupied: BitBoard) -> BitBoard {
+    let (target, step, double_rank) = match player {
+        Player::White => (target.to_index() as i32, -8, 3),
+        Player::Black => (target.to_index() as i32, 8, 4),
+    };
+    let mut origins = BitBoard::empty();
+    let single = target + step;
+    if !(8..56).contains(&single) || occupied.is_square_set(Square::new(single as u8)) {
+        return origins;
+    }
+    origins = origins.set_square(Square::new(single as u8));
+    let double = single + step;
+    if target / 8 == double_rank && !occupied.is_square_set(Square::new(double as u8)) {
+        origins = origins.set_square(Square::new(double as u8));
+    }
+    origins
+}
+
+// returns a state with the given boards and player to move, and no other rights
+fn position(white_board: PlayerBoard, black_board: PlayerBoard, player_turn: Player) -> GameState {
+    let mut state = GameState::default().with_player_turn(player_turn);
+    state.set_boards(white_board, black_board);
+    state
+}
+
+// returns the state with the players swapped and the board mirrored between them
+fn flip(state: &GameState) -> GameState {
+    let mirror = |board: PlayerBoard| {
+        Piece::iter().fold(PlayerBoard::new(), |mirrored, piece| {
+            mirrored.with_piece(piece, board.piece(piece).mirror_horizontal())
+        })
+    };
+    position(
+        mirror(state.player_board(Player::Black)),
+        mirror(state.player_board(Player::White)),
+        state.player_turn.other(),
+    )
+}
+
+// returns true if the player to move has a pawn able to capture en-passant
+fn can_capture_en_passant(state: &GameState) -> bool {
+    state.en_passant.is_some_and(|square| {
+        let attackers = Piece::Pawn.attacked_squares(
+            square,
+            state.player_turn.other(),
+            BitBoard::empty(),
+        );
+        !(attackers & state.player_board(state.player_turn).pawns).is_empty()
+    })
+}
+
+// returns the squares white's king is indexed on
+fn king_squares(pawns: bool) -> Vec<u8> {
+    if pawns {
+        (0..64).filter(|square| square % 8 < 4).collect()
+    } else {
+        TRIANGLE.to_vec()
+    }
+}
+
+// returns the position of a piece in the naming order
+fn order(piece: Piece) -> usize {
+    ORDER.iter().position(|&other| other == piece).expect("Piece missing from order")
+}
+
+// returns the letter naming a piece
+fn letter(piece: Piece) -> char {
+    match piece {
+        Piece::King => 'K',
+        Piece::Queen => 'Q',
+        Piece::Rook => 'R',
+        Piece::Bishop => 'B',
+        Piece::Knight => 'N',
+        Piece::Pawn => 'P',
+    }
+}
+
+// a reflection of the board which brings white's king to its indexed squares
+#[derive(Clone, Copy, Debug)]
+struct Symmetry {
+    files: bool,
+    ranks: bool,
+    diagonal: bool,
+}
+
+impl Symmetry {
+    // returns the symmetry for the given square of white's king
+    fn normalising(king: u8, pawns: bool) -> Self {
+        let files = king % 8 > 3;
+        let ranks = !pawns && king / 8 > 3;
+        let mirrored = Symmetry {
+            files,
+            ranks,
+            diagonal: false,
+        }
+        .apply(king);
+        Symmetry {
+            files,
+            ranks,
+            diagonal: !pawns && mirrored / 8 > mirrored % 8,
+        }
+    }
+
+    // applies the symmetry to a square
+    fn apply(self, mut square: u8) -> u8 {
+        if self.files {
+            square ^= 7;
+        }
+        if self.ranks {
+            square ^= 56;
+        }
+        if self.diagonal {
+            square = (square % 8) * 8 + square / 8;
+        }
+        square
+    }
+}
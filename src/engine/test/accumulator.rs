@@ -0,0 +1,79 @@
+use board::piece::Piece;
+use board::player::Player;
+use engine::accumulator::Accumulator;
+use engine::heuristic;
+use engine::heuristic::Score;
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::variant::Variant;
+
+#[test]
+fn accumulator_start_position() {
+    let state = GameState::start_position();
+    let accumulator = Accumulator::new(&EvalParams::default(), &state);
+    assert_eq!(accumulator.material(Player::White), Score::new(3900, 4100));
+    assert_eq!(accumulator.material(Player::Black), Score::new(3900, 4100));
+    for piece in Piece::iter() {
+        assert_eq!(
+            accumulator.squares(Player::White, piece),
+            accumulator.squares(Player::Black, piece)
+        );
+    }
+    assert_eq!(accumulator.total(), Score::new(0, 0));
+}
+
+#[test]
+fn accumulator_move_application() {
+    // captures, promotions, castling, en passant, drops and explosions
+    let params = EvalParams::default();
+    let states = [
+        parse_fen("r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/P1PBBPPP/R3K2R w KQkq - 0 1").unwrap(),
+        parse_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap(),
+        parse_fen("4k3/8/8/8/8/8/8/4K3[QNp] w - - 0 1")
+            .unwrap()
+            .with_variant(Variant::Crazyhouse),
+        parse_fen("r3k2r/p1pp1pb1/bn2Qnp1/2qPN3/1p2P3/2N5/PPPBBPPP/R3K2R b KQkq - 3 2")
+            .unwrap()
+            .with_variant(Variant::Atomic),
+    ];
+
+    for original in states.iter() {
+        let accumulator = Accumulator::new(&params, original);
+        let mut state = original.clone();
+        for mv in original.legal_moves() {
+            let undo = state.make_move(&mv);
+            let mut updated = accumulator;
+            updated.update(&params, &undo.changes);
+            assert_eq!(updated, Accumulator::new(&params, &state), "{} desynced", mv);
+            state.unmake_move(&mv, &undo);
+        }
+    }
+}
+
+#[test]
+fn accumulator_custom_params() {
+    // the accumulator is weighted by whatever parameters the evaluation uses
+    let state = parse_fen("4k3/pp6/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap();
+    let default = EvalParams::default();
+    let mut params = default.clone();
+    let mut weights = params.weights();
+    weights[0] += 1;
+    params.set_weights(&weights);
+    assert_eq!(
+        Accumulator::new(&params, &state).total() - Accumulator::new(&default, &state).total(),
+        Score::new(2, 0)
+    );
+
+    for params in [
+        params,
+        EvalParams::from_toml("[material]\nrook = [510, 520]").unwrap(),
+    ].iter()
+    {
+        let accumulator = Accumulator::new(params, &state);
+        assert_eq!(
+            heuristic::accumulated_score(&state, params, &accumulator),
+            heuristic::score(&state, params)
+        );
+    }
+}
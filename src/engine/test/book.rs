@@ -0,0 +1,172 @@
+use board::player::Player;
+use engine::book::{encode_move, Book, BookBuilder, BookEntry, BookSelection};
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::move_application::Move;
+use rules::variant::Outcome;
+
+// the legal move with the given origin and target, in lower case such as "e2e4"
+fn find_move(state: &GameState, text: &str) -> Move {
+    state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| format!("{}{}", mv.origin, mv.target).eq_ignore_ascii_case(&text[..4]))
+        .unwrap()
+}
+
+// the moves a book gives for a position, written out, with their weights
+fn book_moves(book: &Book, state: &GameState) -> Vec<(String, u16)> {
+    book.moves(state)
+        .iter()
+        .map(|&(ref mv, weight)| (mv.to_string(), weight))
+        .collect()
+}
+
+// an entry for the given move from a position
+fn entry(state: &GameState, text: &str, weight: u16) -> BookEntry {
+    BookEntry {
+        key: state.polyglot_key(),
+        mv: encode_move(state, &find_move(state, text)),
+        weight,
+        learn: 0,
+    }
+}
+
+#[test]
+fn book_entry_bytes() {
+    let entry = BookEntry {
+        key: 0x463b96181691fc9c,
+        mv: 0x031c,
+        weight: 7,
+        learn: 0x01020304,
+    };
+    let bytes = entry.to_bytes();
+    assert_eq!(
+        bytes,
+        [0x46, 0x3b, 0x96, 0x18, 0x16, 0x91, 0xfc, 0x9c, 0x03, 0x1c, 0x00, 0x07, 1, 2, 3, 4]
+    );
+    assert_eq!(BookEntry::from_bytes(&bytes), entry);
+
+    let book = Book::from_entries(vec![entry, BookEntry { key: 1, ..entry }]);
+    assert_eq!(book.entries()[0].key, 1);
+    assert_eq!(Book::from_bytes(&book.to_bytes()).unwrap(), book);
+    assert!(Book::from_bytes(&[0; 15]).is_err());
+    assert!(Book::from_bytes(&[]).unwrap().is_empty());
+    assert!(Book::load("/nonexistent/gladius.bin").is_err());
+}
+
+#[test]
+fn book_encode_move() {
+    let state = GameState::start_position();
+    // e2e4 is file 4, rank 1 to file 4, rank 3
+    assert_eq!(encode_move(&state, &find_move(&state, "e2e4")), 0x031c);
+
+    // castling is written as the king taking its rook
+    let state = parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let castles: Vec<u16> = state
+        .legal_moves()
+        .iter()
+        .filter(|mv| mv.castle.is_some())
+        .map(|mv| encode_move(&state, mv))
+        .collect();
+    assert_eq!(castles.len(), 2);
+    assert!(castles.contains(&(4 << 6 | 7)));
+    assert!(castles.contains(&(4 << 6)));
+
+    // promotions are numbered from the knight up
+    let state = parse_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let promotions: Vec<u16> = state
+        .legal_moves()
+        .iter()
+        .filter(|mv| mv.promotion.is_some())
+        .map(|mv| encode_move(&state, mv) >> 12)
+        .collect();
+    assert_eq!(promotions.len(), 4);
+    for promotion in 1..5 {
+        assert!(promotions.contains(&promotion));
+    }
+}
+
+#[test]
+fn book_choose_move() {
+    let state = GameState::start_position();
+    let other = parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let book = Book::from_entries(vec![
+        entry(&other, "e1e2", 50),
+        entry(&state, "d2d4", 1),
+        entry(&state, "e2e4", 3),
+        BookEntry {
+            mv: 0x0fff,
+            ..entry(&state, "g1f3", 100)
+        },
+    ]);
+    assert_eq!(book.probe(state.polyglot_key()).len(), 3);
+    assert!(book.probe(0).is_empty());
+
+    // illegal moves are left out
+    assert_eq!(
+        book_moves(&book, &state),
+        vec![(": D2 -> D4".to_string(), 1), (": E2 -> E4".to_string(), 3)]
+    );
+
+    let best = book.choose_move(&state, BookSelection::Best, 0);
+    assert_eq!(best.unwrap().to_string(), ": E2 -> E4");
+
+    // weighted choices follow the weights
+    let chosen: Vec<String> = (0..4)
+        .map(|random| {
+            book.choose_move(&state, BookSelection::Weighted, random)
+                .unwrap()
+        })
+        .map(|mv| mv.to_string())
+        .collect();
+    assert_eq!(chosen.iter().filter(|&mv| mv == ": E2 -> E4").count(), 3);
+
+    let empty = parse_fen("4k3/8/8/8/8/8/8/3K4 w - - 0 1").unwrap();
+    assert!(book
+        .choose_move(&empty, BookSelection::Weighted, 0)
+        .is_none());
+}
+
+#[test]
+fn book_builder() {
+    let start = GameState::start_position();
+    let e4 = find_move(&start, "e2e4");
+    let after_e4 = start.apply_move(&e4);
+    let e5 = find_move(&after_e4, "e7e5");
+    let c5 = find_move(&after_e4, "c7c5");
+    let d4 = find_move(&start, "d2d4");
+
+    let mut builder = BookBuilder::new();
+    builder.add_game(
+        &start,
+        &[e4.clone(), e5.clone()],
+        Some(Outcome::Win(Player::White)),
+        10,
+    );
+    builder.add_game(&start, &[e4.clone(), e5.clone()], Some(Outcome::Draw), 10);
+    builder.add_game(
+        &start,
+        &[e4.clone(), c5.clone()],
+        Some(Outcome::Win(Player::Black)),
+        10,
+    );
+    builder.add_game(&start, &[d4], Some(Outcome::Win(Player::Black)), 10);
+    builder.add_game(&start, &[e4.clone(), c5.clone()], None, 1);
+    assert_eq!(builder.len(), 4);
+
+    // e4 scored 2 + 1 + 0 + 0, e5 scored 0 + 1 and c5 scored 2, while d4 lost
+    let book = builder.build(1);
+    assert_eq!(book_moves(&book, &start), vec![(e4.to_string(), 3)]);
+    assert_eq!(
+        book_moves(&book, &after_e4),
+        vec![(c5.to_string(), 2), (e5.to_string(), 1)]
+    );
+
+    // the unfinished game stopped after its first ply, so c5 was only played once
+    let book = builder.build(2);
+    assert_eq!(book_moves(&book, &start), vec![(e4.to_string(), 3)]);
+    assert_eq!(book_moves(&book, &after_e4), vec![(e5.to_string(), 1)]);
+    assert_eq!(builder.build(4).len(), 1);
+    assert!(builder.build(5).is_empty());
+}
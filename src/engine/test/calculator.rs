@@ -1,8 +1,15 @@
+use board::player::Player;
 use engine::calculator;
-use engine::calculator::ScoredSequence;
+use engine::calculator::{ScoredSequence, SearchLimit, SearchSignals};
+use engine::evaluator::Evaluator;
+use engine::params::EvalParams;
+use engine::time_manager::{TimeControl, TimeManager};
 use logger;
 use rules::fen_parser::parse_fen;
 use rules::game_state::GameState;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 // http://wtharvey.com/m8n2.txt
 const WHITE_MATES_IN_TWO: [&str; 166] = [
@@ -236,15 +243,137 @@ const BLACK_MATES_IN_TWO: [&str; 55] = [
 fn tactics_mate_in_2() {
     for fen in WHITE_MATES_IN_TWO.iter() {
         let state = parse_fen(fen).unwrap();
-        let result = calculator::alpha_beta(&state, 3);
+        let result = calculator::alpha_beta(&state, &EvalParams::default(), 3);
         println!("FEN: {}. Result: {}", fen, result);
         assert_eq!(result, 200000);
     }
 
     for fen in BLACK_MATES_IN_TWO.iter() {
         let state = parse_fen(fen).unwrap();
-        let result = calculator::alpha_beta(&state, 3);
+        let result = calculator::alpha_beta(&state, &EvalParams::default(), 3);
         println!("FEN: {}. Result: {}", fen, result);
         assert_eq!(result, -200000);
     }
 }
+
+// searches the start position on another thread, under the given signals
+fn search_thread(
+    limit: SearchLimit,
+    signals: &Arc<SearchSignals>,
+) -> thread::JoinHandle<Option<ScoredSequence>> {
+    let signals = Arc::clone(signals);
+    thread::spawn(move || {
+        let params = EvalParams::default();
+        let state = GameState::start_position();
+        let mut evaluator = Evaluator::handcrafted(&params, &state);
+        calculator::search_signalled(&state, &mut evaluator, limit, &signals)
+    })
+}
+
+#[test]
+fn principal_variation() {
+    let params = EvalParams::default();
+    let state = parse_fen(WHITE_MATES_IN_TWO[0]).unwrap();
+    let signals = SearchSignals::new();
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let line =
+        calculator::search_signalled(&state, &mut evaluator, SearchLimit::Depth(3), &signals)
+            .unwrap();
+    assert_eq!(line.score(), 200000);
+    let mate = line.mate().unwrap();
+    assert_eq!((mate.player(), mate.turns()), (Player::White, 2));
+
+    // the line ends in mate
+    assert_eq!(line.moves().len(), 3);
+    let mut end = state.clone();
+    for mv in line.moves() {
+        end.make_move(mv);
+    }
+    assert!(end.legal_moves().is_empty());
+}
+
+#[test]
+fn search_signals() {
+    // a stopped search returns its best move so far
+    let signals = Arc::new(SearchSignals::new());
+    let search = search_thread(SearchLimit::Infinite, &signals);
+    thread::sleep(Duration::from_millis(100));
+    assert!(!search.is_finished());
+    signals.stop();
+    assert!(!search.join().unwrap().unwrap().moves().is_empty());
+
+    // a pondering search ignores its time limit until the pondered move is played
+    let signals = Arc::new(SearchSignals::pondering());
+    let control = TimeControl::MoveTime(Duration::from_millis(100));
+    let manager = TimeManager::new(control, Duration::from_millis(0));
+    let search = search_thread(SearchLimit::Time(manager), &signals);
+    thread::sleep(Duration::from_millis(300));
+    assert!(!search.is_finished());
+    let start = Instant::now();
+    signals.ponderhit();
+    assert!(!signals.is_pondering());
+    assert!(search.join().unwrap().is_some());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn node_limit() {
+    let state = GameState::start_position();
+    let params = EvalParams::default();
+    let signals = SearchSignals::new();
+
+    // the first iteration is always completed
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let line =
+        calculator::search_signalled(&state, &mut evaluator, SearchLimit::Nodes(1), &signals)
+            .unwrap();
+    assert_eq!(line.depth(), 1);
+    assert_eq!(line.nodes(), 20);
+
+    // later iterations stop as soon as the limit is reached
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let limit = SearchLimit::Nodes(10_000);
+    let line = calculator::search_signalled(&state, &mut evaluator, limit, &signals).unwrap();
+    assert_eq!(line.nodes(), 10_000);
+}
+
+#[test]
+fn multi_pv() {
+    let params = EvalParams::default();
+    let signals = SearchSignals::new();
+    let state = parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let lines =
+        calculator::search_multi_pv(&state, &mut evaluator, SearchLimit::Depth(2), &signals, 3);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].moves()[0].to_string(), "R: A1 -> A8");
+    assert_eq!(lines[0].score(), 200000);
+    for (index, line) in lines.iter().enumerate() {
+        assert_eq!(line.rank(), index + 1);
+        assert_eq!(line.depth(), 2);
+        assert!(index == 0 || line.score() <= lines[index - 1].score());
+        assert!(lines[..index]
+            .iter()
+            .all(|other| other.moves()[0] != line.moves()[0]));
+    }
+
+    // each variation has the exact score of its first move
+    for line in lines.iter() {
+        let after = state.apply_move(&line.moves()[0]);
+        assert_eq!(line.score(), calculator::alpha_beta(&after, &params, 1));
+    }
+
+    // the first variation is the single best line
+    let best =
+        calculator::search_signalled(&state, &mut evaluator, SearchLimit::Depth(2), &signals)
+            .unwrap();
+    assert_eq!(best.moves(), lines[0].moves());
+    assert_eq!(best.score(), calculator::alpha_beta(&state, &params, 2));
+
+    // there are no more variations than legal moves
+    let state = parse_fen("k7/8/8/8/8/8/8/KR6 b - - 0 1").unwrap();
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let lines =
+        calculator::search_multi_pv(&state, &mut evaluator, SearchLimit::Depth(2), &signals, 3);
+    assert_eq!(lines.len(), 1);
+}
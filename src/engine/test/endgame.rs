@@ -0,0 +1,90 @@
+use board::player::Player;
+use board::square::Square;
+use engine::endgame;
+use engine::endgame::{KNOWN_WIN, SCALE_NORMAL};
+use engine::heuristic;
+use engine::kpk;
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use rules::variant::Variant;
+use std::str::FromStr;
+
+fn evaluate(fen: &str) -> Option<i32> {
+    endgame::evaluate(&parse_fen(fen).unwrap(), &EvalParams::default())
+}
+
+#[test]
+fn endgame_insufficient_material() {
+    let insufficient = |fen: &str| endgame::is_insufficient_material(&parse_fen(fen).unwrap());
+    assert!(insufficient("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+    assert!(insufficient("8/8/4k3/8/8/3K4/8/5B2 w - - 0 1"));
+    assert!(insufficient("8/8/4k3/8/2b5/3K4/8/5N2 w - - 0 1"));
+    assert!(insufficient("8/8/4k3/8/8/4B3/8/2B1K3 w - - 0 1"));
+    assert!(!insufficient("8/8/4k3/8/8/4B3/8/3BK3 w - - 0 1"));
+    assert!(!insufficient("8/8/4k3/8/8/3K4/8/5NN1 w - - 0 1"));
+    assert!(!insufficient("8/8/4k3/8/8/3K4/8/5R2 w - - 0 1"));
+    assert!(!insufficient("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1"));
+    assert_eq!(evaluate("8/8/4k3/8/8/3K4/8/5B2 w - - 0 1"), Some(0));
+}
+
+#[test]
+fn endgame_mating() {
+    // the lone king is driven to the edge
+    let edge = evaluate("4k3/8/4K3/8/8/8/8/7Q w - - 0 1").unwrap();
+    let centre = evaluate("8/8/4K3/8/4k3/8/8/7Q w - - 0 1").unwrap();
+    assert!(edge > centre);
+    assert!(centre > KNOWN_WIN);
+    assert!(evaluate("8/8/4k3/8/8/3K4/8/r7 b - - 0 1").unwrap() < -KNOWN_WIN);
+
+    // and to a corner of the bishop's colour
+    let right = evaluate("7k/8/5K2/8/8/8/8/2BN4 w - - 0 1").unwrap();
+    let wrong = evaluate("k7/8/2K5/8/8/8/8/2BN4 w - - 0 1").unwrap();
+    assert!(right > wrong);
+    assert!(wrong > KNOWN_WIN);
+
+    assert_eq!(evaluate("8/8/4k3/8/8/3K4/8/5NN1 w - - 0 1"), Some(0));
+    assert_eq!(evaluate("8/8/4k3/8/8/3K4/8/4RBB1 w - - 0 1").map(|score| score > 0), Some(true));
+    assert_eq!(evaluate("8/8/4k3/8/8/3K4/p7/5NN1 w - - 0 1"), None);
+
+    let atomic = parse_fen("4k3/8/4K3/8/8/8/8/7Q w - - 0 1").unwrap().with_variant(Variant::Atomic);
+    assert_eq!(endgame::evaluate(&atomic, &EvalParams::default()), None);
+}
+
+#[test]
+fn endgame_kpk() {
+    // a king in front of its pawn on the sixth rank wins with either side to move
+    assert!(evaluate("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").unwrap() > KNOWN_WIN);
+    assert!(evaluate("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap() > KNOWN_WIN);
+
+    // the pawn outruns the king, on either wing and for either player
+    assert!(evaluate("7k/8/8/8/P7/8/8/K7 w - - 0 1").unwrap() > KNOWN_WIN);
+    assert!(evaluate("k7/8/8/8/7P/8/8/7K w - - 0 1").unwrap() > KNOWN_WIN);
+    assert!(evaluate("k7/8/8/p7/8/8/8/7K b - - 0 1").unwrap() < -KNOWN_WIN);
+
+    // a king in front of a rook pawn draws, as does capturing the pawn
+    assert_eq!(evaluate("k7/8/8/8/8/8/P7/7K w - - 0 1"), Some(0));
+    assert_eq!(evaluate("8/8/8/8/8/8/kP6/7K b - - 0 1"), Some(0));
+
+    let square = |name: &str| Square::from_str(name).unwrap();
+    assert!(kpk::is_win(Player::White, square("e6"), square("e8"), square("e5"), Player::Black));
+    assert!(!kpk::is_win(Player::White, square("h1"), square("a8"), square("a2"), Player::White));
+}
+
+#[test]
+fn endgame_scale() {
+    let params = EvalParams::default();
+    let scale = |fen: &str, strong: Player| {
+        endgame::scale_factor(&parse_fen(fen).unwrap(), &params, strong)
+    };
+    assert_eq!(scale("4k3/4b3/8/3p4/3P4/8/4B3/4K3 w - - 0 1", Player::White), 32);
+    assert_eq!(scale("4k3/5b2/8/3p4/3P4/8/4B3/4K3 w - - 0 1", Player::White), 64);
+    assert_eq!(scale("4k3/8/8/8/8/8/3R4/2b1K3 w - - 0 1", Player::White), 4);
+    assert_eq!(scale("4k3/4r3/8/8/8/8/3R4/2B1K3 w - - 0 1", Player::White), 14);
+    assert_eq!(scale("4k3/4p3/8/8/8/8/8/2B1K3 w - - 0 1", Player::White), 0);
+    assert_eq!(scale("4k3/8/8/8/8/8/3R4/2bPK3 w - - 0 1", Player::White), SCALE_NORMAL);
+
+    // the drawish endgame scores close to equal
+    let state = parse_fen("4k3/8/8/8/8/8/3R4/2b1K3 w - - 0 1").unwrap();
+    let unscaled = heuristic::tapered_score(&state, &params).taper(heuristic::phase(&state));
+    assert!(heuristic::score(&state, &params) < unscaled / 4);
+}
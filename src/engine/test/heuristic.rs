@@ -1,16 +1,28 @@
 use board::bitboard;
 use board::bitboard::BitBoard;
 use board::file::File;
+use board::player::Player;
 use board::rank::Rank;
 use engine::heuristic;
+use engine::heuristic::{Position, Score, Term};
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
 use rules::game_state::GameState;
 use rules::player_board::PlayerBoard;
 use std::vec::Vec;
 
+// returns the difference between white's and black's scores for the given term
+fn diff(term: Term, state: &GameState) -> Score {
+    let params = EvalParams::default();
+    let position = Position::new(state);
+    term(&position, &params, Player::White) - term(&position, &params, Player::Black)
+}
+
 #[test]
 pub fn heuristic_start() {
+    let params = EvalParams::default();
     let state = GameState::start_position();
-    assert_eq!(heuristic::score(&state), 0);
+    assert_eq!(heuristic::score(&state, &params), 0);
 
     let state2 = GameState::default()
         .with_white_board(
@@ -25,7 +37,167 @@ pub fn heuristic_start() {
                 .with_bishops(BitBoard::empty().set_coordinate(File::A, Rank::Three)),
         );
 
-    assert!(heuristic::score(&state2) < 0);
+    assert!(heuristic::score(&state2, &params) < 0);
+}
+
+#[test]
+pub fn heuristic_phase() {
+    assert_eq!(heuristic::phase(&GameState::start_position()), heuristic::MAX_PHASE);
+    assert_eq!(heuristic::phase(&parse_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1").unwrap()), 0);
+    assert_eq!(heuristic::phase(&parse_fen("3qk3/8/8/8/8/8/8/1N2KR2 w - - 0 1").unwrap()), 7);
+
+    // extra promoted pieces do not take the phase beyond the middlegame
+    let state = parse_fen("QQQQkQQQ/8/8/8/8/8/8/QQQQKQQQ w - - 0 1").unwrap();
+    assert_eq!(heuristic::phase(&state), heuristic::MAX_PHASE);
+}
+
+#[test]
+pub fn heuristic_taper() {
+    let score = Score::new(100, -20);
+    assert_eq!(score.taper(heuristic::MAX_PHASE), 100);
+    assert_eq!(score.taper(0), -20);
+    assert_eq!(score.taper(heuristic::MAX_PHASE / 2), 40);
+    assert_eq!(Score::new(1, 2) + Score::new(3, 4) * 2 - Score::new(1, 1), Score::new(6, 9));
+}
+
+#[test]
+pub fn heuristic_king_endgame() {
+    let params = EvalParams::default();
+    // the king belongs in the corner in the middlegame, but in the centre in the endgame,
+    // with pawns left so that the endgame is not scaled down as drawish
+    let corner = parse_fen("r3k3/p7/8/8/8/8/P7/R5K1 w - - 0 1").unwrap();
+    let centre = parse_fen("r3k3/p7/8/8/8/4K3/P7/R7 w - - 0 1").unwrap();
+    assert!(heuristic::score(&centre, &params) > heuristic::score(&corner, &params));
+
+    let corner = parse_fen("r2qk3/8/8/8/8/8/8/R2Q2K1 w - - 0 1").unwrap();
+    let centre = parse_fen("r2qk3/8/8/8/8/4K3/8/R2Q4 w - - 0 1").unwrap();
+    let corner = heuristic::tapered_score(&corner, &params);
+    let centre = heuristic::tapered_score(&centre, &params);
+    assert!(corner.mg > centre.mg);
+    assert!(corner.eg < centre.eg);
+}
+
+#[test]
+pub fn heuristic_mobility() {
+    let params = EvalParams::default();
+    assert_eq!(diff(heuristic::mobility, &GameState::start_position()), Score::default());
+
+    // a rook on an open file is more mobile than one behind its own pawns, with a black
+    // pawn so that the position is not a known win
+    let open = parse_fen("6k1/7p/8/8/8/8/2PPP3/R5K1 w - - 0 1").unwrap();
+    let closed = parse_fen("6k1/7p/8/8/8/8/2PPP3/3R2K1 w - - 0 1").unwrap();
+    assert_eq!(diff(heuristic::mobility, &open), Score::new(10, 20));
+    assert_eq!(diff(heuristic::mobility, &closed), Score::new(-4, -8));
+    assert!(heuristic::score(&open, &params) > heuristic::score(&closed, &params));
+
+    // squares attacked by enemy pawns are not safe
+    let state = parse_fen("6k1/8/8/8/1p6/8/8/1N4K1 w - - 0 1").unwrap();
+    assert_eq!(diff(heuristic::mobility, &state), Score::new(-12, -12));
+
+    // a rook trapped in the opponent's half
+    let state = parse_fen("6BR/7P/8/k7/8/8/8/6K1 w - - 0 1").unwrap();
+    assert_eq!(diff(heuristic::mobility, &state), Score::new(-64, -58));
+}
+
+#[test]
+pub fn heuristic_defended_pieces() {
+    assert_eq!(diff(heuristic::defended_pieces, &GameState::start_position()), Score::default());
+
+    let state = parse_fen("4k3/1p6/2n5/8/8/2N5/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(diff(heuristic::defended_pieces, &state), Score::new(-15, -10));
+}
+
+#[test]
+pub fn heuristic_piece_terms() {
+    fn term(term: Term, fen: &str) -> Score {
+        diff(term, &parse_fen(fen).unwrap())
+    }
+
+    let terms: [Term; 8] = [
+        heuristic::knight_pawns,
+        heuristic::bishop_pair,
+        heuristic::bad_bishops,
+        heuristic::rook_files,
+        heuristic::rook_seventh,
+        heuristic::rook_queen_file,
+        heuristic::connected_rooks,
+        heuristic::early_queen,
+    ];
+    for term in terms.iter() {
+        assert_eq!(diff(*term, &GameState::start_position()), Score::default());
+    }
+
+    assert_eq!(
+        term(heuristic::knight_pawns, "4k3/8/8/8/8/8/PP6/N3K3 w - - 0 1"),
+        Score::new(-12, -18)
+    );
+    assert_eq!(
+        term(heuristic::bishop_pair, "1b1bk3/8/8/8/8/8/8/2B1KB2 w - - 0 1"),
+        Score::new(30, 50)
+    );
+    assert_eq!(
+        term(heuristic::bad_bishops, "4k3/8/8/8/8/8/1PPP4/2B1K3 w - - 0 1"),
+        Score::new(-6, -10)
+    );
+    assert_eq!(
+        term(heuristic::rook_files, "4k3/3p4/8/8/8/8/7P/R2RK2R w - - 0 1"),
+        Score::new(37, 16)
+    );
+    assert_eq!(
+        term(heuristic::rook_seventh, "6k1/1R6/8/8/8/8/8/4K3 w - - 0 1"),
+        Score::new(20, 30)
+    );
+    assert_eq!(
+        term(heuristic::rook_seventh, "8/1R6/6k1/8/8/8/8/4K3 w - - 0 1"),
+        Score::default()
+    );
+    assert_eq!(
+        term(heuristic::rook_queen_file, "3qk3/8/8/8/8/8/8/3RK3 w - - 0 1"),
+        Score::new(8, 4)
+    );
+    assert_eq!(
+        term(heuristic::connected_rooks, "4k3/8/8/8/8/8/8/R4RK1 w - - 0 1"),
+        Score::new(10, 5)
+    );
+    assert_eq!(
+        term(heuristic::connected_rooks, "4k3/8/8/8/8/8/8/R3KR2 w - - 0 1"),
+        Score::default()
+    );
+    assert_eq!(
+        term(
+            heuristic::early_queen,
+            "rnbqkbnr/pppppppp/8/8/8/3Q4/PPPPPPPP/RNB1KBNR w KQkq - 0 1"
+        ),
+        Score::new(-32, 0)
+    );
+}
+
+#[test]
+pub fn heuristic_trace() {
+    let params = EvalParams::default();
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R w KQ - 0 9",
+        "4k3/8/3n4/3P4/8/8/8/4K3 w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let state = parse_fen(fen).unwrap();
+        let trace = heuristic::trace(&state, &params);
+        assert_eq!(trace.score(), heuristic::score(&state, &params));
+        assert_eq!(trace.total(), heuristic::tapered_score(&state, &params));
+        assert_eq!(trace.phase, heuristic::phase(&state));
+
+        let names: Vec<&str> = trace.terms.iter().map(|term| term.name).collect();
+        let expected: Vec<&str> = heuristic::TERMS.iter().map(|&(name, _)| name).collect();
+        assert_eq!(names, expected);
+        assert!(trace.to_string().contains("Passed pawns"));
+    }
+
+    let state = parse_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    let material = heuristic::trace(&state, &params).terms[0];
+    assert_eq!(material.name, "Material");
+    assert_eq!(material.white, Score::new(900, 940));
+    assert_eq!(material.black, Score::default());
 }
 
 #[test]
@@ -61,7 +233,7 @@ pub fn apply_mask_complex() {
         mask[i] = (i + 1) as i32;
     }
 
-    let bb = bitboard::BitBoard::new(85937205623);
+    let bb = BitBoard::new(85937205623);
     println!("{}", bb);
     let result = heuristic::apply_mask(bb, &mask);
     let expected = 27 + 29 + 34 + 41 + 47 + 49 + 53 + 54 + 56 + 57 + 58 + 59 + 61 + 62 + 63;
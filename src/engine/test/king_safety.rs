@@ -0,0 +1,49 @@
+use engine::heuristic::Score;
+use engine::king_safety;
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::variant::Variant;
+
+fn safety(fen: &str) -> Score {
+    king_safety::king_safety(&parse_fen(fen).unwrap(), &EvalParams::default())
+}
+
+#[test]
+fn king_safety_start() {
+    let params = EvalParams::default();
+    let start = GameState::start_position();
+    assert_eq!(king_safety::king_safety(&start, &params), Score::default());
+
+    // a missing king has no safety to evaluate, leaving black's shield and the storm
+    let horde = GameState::variant_start_position(Variant::Horde);
+    assert_eq!(king_safety::king_safety(&horde, &params), Score::new(-21, 0));
+}
+
+#[test]
+fn king_safety_shelter() {
+    // white's king is behind a full shield, black's is on three semi-open files
+    assert_eq!(safety("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1"), Score::new(90, 0));
+
+    // an advanced shield protects less, and storming pawns are dangerous
+    assert_eq!(safety("6k1/8/8/8/8/5PPP/8/6K1 w - - 0 1"), Score::new(69, 0));
+    assert_eq!(safety("6k1/8/8/8/8/6p1/5PPP/6K1 w - - 0 1"), Score::new(55, 0));
+}
+
+#[test]
+fn king_safety_attackers() {
+    // a lone attacker does not count
+    assert_eq!(safety("6k1/8/8/8/7q/8/5PPP/6K1 w - - 0 1"), Score::new(90, 0));
+
+    // but the queen and knight together attack four squares around the king
+    assert_eq!(safety("6k1/8/8/8/7q/4n3/5PPP/6K1 w - - 0 1"), Score::new(62, 0));
+}
+
+#[test]
+fn king_safety_castling() {
+    assert_eq!(safety("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), Score::default());
+    assert_eq!(safety("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1"), Score::new(-30, 0));
+
+    // a king which has reached a wing is considered castled
+    assert_eq!(safety("r4rk1/8/8/8/8/8/8/R4RK1 w - - 0 1"), Score::default());
+}
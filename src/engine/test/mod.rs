@@ -1,4 +1,14 @@
 //! Tests for the board module.
 
+mod accumulator;
+mod book;
 mod calculator;
+mod endgame;
 mod heuristic;
+mod params;
+mod pawn_structure;
+mod syzygy;
+mod tablebase;
+mod time_manager;
+mod king_safety;
+mod nnue;
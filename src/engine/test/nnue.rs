@@ -0,0 +1,115 @@
+use engine::calculator;
+use engine::evaluator::Evaluator;
+use engine::nnue::{Network, NetworkAccumulator, FEATURES};
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::variant::Variant;
+
+// builds a network file, taking each weight in turn from the given function
+fn network_bytes(hidden: usize, mut weight: impl FnMut(usize) -> i16) -> Vec<u8> {
+    let mut bytes = b"GNN1".to_vec();
+    bytes.extend_from_slice(&(hidden as u32).to_le_bytes());
+    for index in 0..(FEATURES + 3) * hidden {
+        bytes.extend_from_slice(&weight(index).to_le_bytes());
+    }
+    bytes.extend_from_slice(&7i32.to_le_bytes());
+    bytes
+}
+
+// a network with pseudo-random weights
+fn random_network() -> Network {
+    let mut seed: u32 = 12345;
+    let bytes = network_bytes(16, |_| {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        ((seed >> 16) % 64) as i16 - 24
+    });
+    Network::from_bytes(&bytes).unwrap()
+}
+
+#[test]
+fn nnue_load() {
+    let bytes = network_bytes(2, |index| index as i16);
+    let network = Network::from_bytes(&bytes).unwrap();
+    assert_eq!(network.hidden(), 2);
+
+    assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Network::from_bytes(b"GNN2").is_err());
+    assert!(Network::from_bytes(&network_bytes(0, |_| 0)).is_err());
+    assert!(Network::load("/nonexistent/gladius.nnue").is_err());
+}
+
+#[test]
+fn nnue_evaluate() {
+    // one hidden neuron counting the viewer's pawns, weighted for the player to move
+    // against the other player
+    let bytes = network_bytes(1, |index| match index {
+        0..=63 => 30,
+        index if index == FEATURES + 1 => 64,
+        index if index == FEATURES + 2 => -64,
+        _ => 0,
+    });
+    let network = Network::from_bytes(&bytes).unwrap();
+
+    // 8 pawns clip to 240 against 6 pawns at 180, plus the bias of 7
+    let state = parse_fen("4k3/pppppp2/8/8/8/8/PPPPPPPP/4K3 w - - 0 1").unwrap();
+    assert_eq!(network.evaluate(&state), (60 * 64 + 7) * 400 / (255 * 64));
+    let state = parse_fen("4k3/pppppp2/8/8/8/8/PPPPPPPP/4K3 b - - 0 1").unwrap();
+    assert_eq!(network.evaluate(&state), -((-60 * 64 + 7) * 400 / (255 * 64)));
+
+    // the evaluation is symmetric between the players
+    let network = random_network();
+    let white = parse_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let black = parse_fen("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3");
+    assert_eq!(
+        network.evaluate(&white.unwrap()),
+        -network.evaluate(&black.unwrap())
+    );
+}
+
+#[test]
+fn nnue_incremental() {
+    let network = random_network();
+    let states = [
+        parse_fen("r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/P1PBBPPP/R3K2R w KQkq - 0 1").unwrap(),
+        parse_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap(),
+        parse_fen("r3k2r/p1pp1pb1/bn2Qnp1/2qPN3/1p2P3/2N5/PPPBBPPP/R3K2R b KQkq - 3 2")
+            .unwrap()
+            .with_variant(Variant::Atomic),
+    ];
+
+    for original in states.iter() {
+        let mut state = original.clone();
+        let mut evaluator = Evaluator::network(&network, &state);
+        for mv in original.legal_moves() {
+            let undo = evaluator.make_move(&mut state, &mv);
+            assert_eq!(evaluator.evaluate(&state), network.evaluate(&state), "{}", mv);
+            for reply in state.clone().legal_moves() {
+                let reply_undo = evaluator.make_move(&mut state, &reply);
+                assert_eq!(evaluator.evaluate(&state), network.evaluate(&state));
+                evaluator.unmake_move(&mut state, &reply, &reply_undo);
+            }
+            evaluator.unmake_move(&mut state, &mv, &undo);
+            assert_eq!(evaluator.evaluate(&state), network.evaluate(original));
+        }
+    }
+
+    let mut state = GameState::start_position();
+    let accumulator = NetworkAccumulator::new(&network, &state);
+    for mv in GameState::start_position().legal_moves() {
+        let undo = state.make_move(&mv);
+        let mut updated = accumulator.clone();
+        updated.update(&network, &undo.changes);
+        assert_eq!(updated, NetworkAccumulator::new(&network, &state), "{}", mv);
+        state.unmake_move(&mv, &undo);
+    }
+}
+
+#[test]
+fn nnue_search() {
+    // the network finds the only legal move
+    let network = random_network();
+    let state = parse_fen("7k/8/5QK1/8/8/8/8/8 b - - 0 1").unwrap();
+    let (mv, _) = calculator::search(&state, &mut Evaluator::network(&network, &state), 2)
+        .unwrap();
+    assert_eq!(mv.target.to_string(), "G8");
+}
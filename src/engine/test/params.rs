@@ -0,0 +1,72 @@
+use engine::heuristic;
+use engine::heuristic::Score;
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use std::env;
+use std::fs;
+
+#[test]
+fn params_toml_round_trip() {
+    let params = EvalParams::default();
+    let text = params.to_toml().unwrap();
+    assert!(text.contains("[material]\npawn = [100, 120]"));
+    assert_eq!(EvalParams::from_toml(&text).unwrap(), params);
+    assert_eq!(EvalParams::from_toml("").unwrap(), params);
+}
+
+#[test]
+fn params_partial() {
+    let text = "
+        [material]
+        pawn = [90, 130]
+
+        [pawns]
+        doubled = [-40, -55]
+    ";
+    let params = EvalParams::from_toml(text).unwrap();
+    assert_eq!(params.material.pawn, Score::new(90, 130));
+    assert_eq!(params.pawns.doubled, Score::new(-40, -55));
+    assert_eq!(params.material.knight, EvalParams::default().material.knight);
+    assert_eq!(params.squares, EvalParams::default().squares);
+
+    // the weights change the evaluation
+    let state = parse_fen("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap();
+    let default = heuristic::tapered_score(&state, &EvalParams::default());
+    let custom = heuristic::tapered_score(&state, &params);
+    assert_eq!(custom - default, Score::new(-10, 10));
+}
+
+#[test]
+fn params_invalid() {
+    assert!(EvalParams::from_toml("[material]\npawn = 100").is_err());
+    assert!(EvalParams::from_toml("[material]\nbishops = [1, 1]").is_err());
+    assert!(EvalParams::from_toml("[squares.pawn]\nmg = [1, 2, 3]\neg = [1, 2, 3]").is_err());
+}
+
+#[test]
+fn params_load() {
+    let path = env::temp_dir().join("gladius_params_load.toml");
+    fs::write(&path, "[mobility]\ntrapped = [-70, -40]\n").unwrap();
+    let params = EvalParams::load(path.to_str().unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(params.mobility.trapped, Score::new(-70, -40));
+
+    assert!(EvalParams::load("/nonexistent/gladius.toml").is_err());
+}
+
+#[test]
+fn params_weights() {
+    let params = EvalParams::default();
+    let mut weights = params.weights();
+    assert_eq!(weights.len(), 12 * 64 + 2 * (5 + 8 + 7 + 10 + 5 + 3 + 5 + 3) + 5 + 8);
+    assert_eq!(&weights[..2], &[100, 120]);
+
+    let mut changed = params.clone();
+    changed.set_weights(&weights);
+    assert_eq!(changed, params);
+
+    weights[1] = 150;
+    changed.set_weights(&weights);
+    assert_eq!(changed.material.pawn, Score::new(100, 150));
+    assert_eq!(changed.weights(), weights);
+}
@@ -0,0 +1,91 @@
+use board::bitboard::BitBoard;
+use board::file::File;
+use board::player::Player;
+use board::rank::Rank;
+use engine::heuristic::Score;
+use engine::params::EvalParams;
+use engine::pawn_structure;
+use engine::pawn_structure::{PawnEntry, PawnTable};
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+
+fn entry(fen: &str) -> PawnEntry {
+    PawnEntry::new(&parse_fen(fen).unwrap())
+}
+
+#[test]
+fn pawn_structure_start() {
+    let params = EvalParams::default();
+    let entry = PawnEntry::new(&GameState::start_position());
+    assert_eq!(entry.score(&params), Score::default());
+    assert_eq!(entry.passed(Player::White), BitBoard::empty());
+    assert_eq!(entry.passed(Player::Black), BitBoard::empty());
+}
+
+#[test]
+fn pawn_structure_passed() {
+    let params = EvalParams::default();
+
+    // an isolated passed pawn
+    let state = parse_fen("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+    let entry = PawnEntry::new(&state);
+    assert_eq!(entry.score(&params), Score::new(-25, 5));
+    assert_eq!(
+        entry.passed(Player::White),
+        BitBoard::empty().set_coordinate(File::D, Rank::Five)
+    );
+    assert_eq!(entry.white.isolated, 1);
+    assert_eq!(entry.scores(&params, Player::White).passed, Score::new(25, 45));
+    assert_eq!(
+        pawn_structure::blocked_passers(&state, &params, &entry, Player::White),
+        Score::default()
+    );
+
+    // which earns half its bonus when blocked
+    let state = parse_fen("4k3/8/3n4/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+    let entry = PawnEntry::new(&state);
+    assert_eq!(
+        pawn_structure::blocked_passers(&state, &params, &entry, Player::White),
+        Score::new(-12, -22)
+    );
+}
+
+#[test]
+fn pawn_structure_backward() {
+    let params = EvalParams::default();
+    // d3 is backward, e4 is passed and connected, c5 is isolated
+    let entry = entry("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1");
+    assert_eq!(entry.score(&params), Score::new(65, 60));
+    assert_eq!(
+        entry.passed(Player::White),
+        BitBoard::empty().set_coordinate(File::E, Rank::Four)
+    );
+}
+
+#[test]
+fn pawn_structure_phalanx_and_islands() {
+    let params = EvalParams::default();
+    assert_eq!(entry("4k3/8/8/8/3PP3/8/8/4K3 w - - 0 1").score(&params), Score::new(40, 66));
+    assert_eq!(entry("4k3/8/8/8/8/8/P1P1P3/4K3 w - - 0 1").score(&params), Score::new(-155, -120));
+}
+
+#[test]
+fn pawn_structure_candidate() {
+    let params = EvalParams::default();
+    // d4 is a candidate passer, supported by c3 against e6
+    let entry = entry("4k3/8/4p3/8/3P4/2P5/8/4K3 w - - 0 1");
+    assert_eq!(entry.score(&params), Score::new(80, 90));
+    assert_eq!(entry.passed(Player::Black), BitBoard::empty());
+}
+
+#[test]
+fn pawn_structure_table() {
+    let first = parse_fen("4k3/8/4p3/8/3P4/2P5/8/4K3 w - - 0 1").unwrap();
+    let second = parse_fen("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1").unwrap();
+
+    let mut table = PawnTable::new(1);
+    assert_eq!(table.probe(&first), PawnEntry::new(&first));
+    assert_eq!(table.probe(&first), PawnEntry::new(&first));
+    assert_eq!(table.probe(&second), PawnEntry::new(&second));
+    assert_eq!(table.probe(&first), PawnEntry::new(&first));
+}
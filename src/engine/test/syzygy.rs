@@ -0,0 +1,427 @@
+use engine::calculator;
+use engine::evaluator::Evaluator;
+use engine::params::EvalParams;
+use engine::syzygy::{Syzygy, Wdl, SYZYGY_WIN};
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// the codes of pieces in table files
+const WHITE_PAWN: u8 = 1;
+const WHITE_ROOK: u8 = 4;
+const WHITE_KING: u8 = 6;
+const BLACK_KING: u8 = 14;
+
+// the number of positions of each player to move in KRvK, and of each leading pawn file
+// and player to move in KPvK
+const KRVK_SIZE: usize = 31332;
+const KPVK_SIZE: usize = 23436;
+
+// the values coded in each 64-byte block, with 3 bits each
+const VALUES_PER_BLOCK: usize = 170;
+
+// builds a table file with the given magic number and pieces, holding the given flags and
+// values for each leading pawn file and player to move
+fn table_file(magic: [u8; 4], pieces: &[u8], pairs: &[Vec<(u8, Vec<u8>)>]) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.push(0);
+    for _ in pairs {
+        bytes.push(0);
+        bytes.extend(pieces.iter().map(|&piece| piece | piece << 4));
+    }
+    if bytes.len() % 2 == 1 {
+        bytes.push(0);
+    }
+
+    // every value is its own symbol, with a code of 3 bits
+    let all_pairs: Vec<&(u8, Vec<u8>)> = pairs.iter().flatten().collect();
+    for pairs in &all_pairs {
+        bytes.extend(&[pairs.0, 6, 10, 0]);
+        bytes.extend(&(pairs.1.len().div_ceil(VALUES_PER_BLOCK) as u32).to_le_bytes());
+        bytes.extend(&[3, 3, 0, 0, 8, 0]);
+        for value in 0..8 {
+            bytes.extend(&[value, 0xF0, 0xFF]);
+        }
+    }
+    if magic == DTZ_MAGIC && bytes.len() % 2 == 1 {
+        bytes.push(0);
+    }
+
+    // the sparse index locates the middle value of every 1024
+    for values in all_pairs.iter().map(|pairs| &pairs.1) {
+        let blocks = values.len().div_ceil(VALUES_PER_BLOCK);
+        for k in 0..values.len().div_ceil(1024) {
+            let middle = k * 1024 + 512;
+            let block = (middle / VALUES_PER_BLOCK).min(blocks - 1);
+            bytes.extend(&(block as u32).to_le_bytes());
+            bytes.extend(&((middle - block * VALUES_PER_BLOCK) as u16).to_le_bytes());
+        }
+    }
+    for values in all_pairs.iter().map(|pairs| &pairs.1) {
+        for block in values.chunks(VALUES_PER_BLOCK) {
+            bytes.extend(&((block.len() - 1) as u16).to_le_bytes());
+        }
+    }
+    for values in all_pairs.iter().map(|pairs| &pairs.1) {
+        while !bytes.len().is_multiple_of(64) {
+            bytes.push(0);
+        }
+        for block in values.chunks(VALUES_PER_BLOCK) {
+            let mut data = [0u8; 64];
+            for (i, &value) in block.iter().enumerate() {
+                for bit in 0..3 {
+                    let position = 3 * i + bit;
+                    data[position / 8] |= ((value >> (2 - bit)) & 1) << (7 - position % 8);
+                }
+            }
+            bytes.extend(&data[..]);
+        }
+    }
+    bytes
+}
+
+// writes the given files to a new directory
+fn table_dir(name: &str, files: &[(&str, Vec<u8>)]) -> PathBuf {
+    let dir = env::temp_dir().join(name);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, bytes) in files {
+        fs::write(dir.join(file), bytes).unwrap();
+    }
+    dir
+}
+
+// a pseudo-random value from 0 to 4 for each index
+fn pattern(size: usize) -> Vec<u8> {
+    (0..size)
+        .map(|idx| ((idx.wrapping_mul(2654435761) >> 13) % 5) as u8)
+        .collect()
+}
+
+fn wdl(syzygy: &Syzygy, fen: &str) -> Option<Wdl> {
+    syzygy.probe_wdl(&parse_fen(fen).unwrap())
+}
+
+// the KRvK tables, won by white to move and lost by black to move, in 5 plies to zeroing
+fn krvk() -> Vec<(&'static str, Vec<u8>)> {
+    let pieces = [WHITE_KING, WHITE_ROOK, BLACK_KING];
+    vec![
+        (
+            "KRvK.rtbw",
+            table_file(
+                WDL_MAGIC,
+                &pieces,
+                &[vec![(0, vec![4; KRVK_SIZE]), (0, vec![0; KRVK_SIZE])]],
+            ),
+        ),
+        (
+            "KRvK.rtbz",
+            table_file(DTZ_MAGIC, &pieces, &[vec![(4, vec![4; KRVK_SIZE])]]),
+        ),
+    ]
+}
+
+#[test]
+fn syzygy_wdl() {
+    let dir = table_dir("gladius_syzygy_wdl", &krvk());
+    let syzygy = Syzygy::load_dir(dir.to_str().unwrap()).unwrap();
+    assert_eq!(syzygy.len(), 1);
+    assert_eq!(syzygy.max_pieces(), 3);
+
+    assert_eq!(
+        wdl(&syzygy, "k7/8/1K6/8/8/8/8/7R w - - 0 1"),
+        Some(Wdl::Win)
+    );
+    assert_eq!(
+        wdl(&syzygy, "k7/8/2K5/8/8/8/8/7R b - - 0 1"),
+        Some(Wdl::Loss)
+    );
+    assert_eq!(
+        wdl(&syzygy, "K7/8/2k5/8/8/8/8/7r w - - 0 1"),
+        Some(Wdl::Loss)
+    );
+    assert_eq!(
+        wdl(&syzygy, "K7/8/1k6/8/8/8/8/7r b - - 0 1"),
+        Some(Wdl::Win)
+    );
+
+    // the capture of the rook draws, which the stored value does not know
+    assert_eq!(
+        wdl(&syzygy, "k7/1R6/8/3K4/8/8/8/8 b - - 0 1"),
+        Some(Wdl::Draw)
+    );
+    assert_eq!(
+        wdl(&syzygy, "8/8/4k3/8/8/3K4/8/8 w - - 0 1"),
+        Some(Wdl::Draw)
+    );
+
+    // positions outside the tables are not covered
+    assert_eq!(wdl(&syzygy, "k7/8/1K6/8/8/8/8/7Q w - - 0 1"), None);
+    assert_eq!(wdl(&syzygy, "k7/8/1K6/8/8/8/8/6RR w - - 0 1"), None);
+    assert_eq!(wdl(&syzygy, "r3k3/8/8/8/8/8/8/4K3 b q - 0 1"), None);
+    assert_eq!(
+        Syzygy::new().probe_wdl(&parse_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap()),
+        None
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn syzygy_index() {
+    let krvk = [WHITE_KING, WHITE_ROOK, BLACK_KING];
+    let kpvk = [WHITE_PAWN, WHITE_KING, BLACK_KING];
+    let kpvk_pairs: Vec<Vec<(u8, Vec<u8>)>> = (0..4)
+        .map(|_| vec![(0, pattern(KPVK_SIZE)), (0, pattern(KPVK_SIZE))])
+        .collect();
+    let dir = table_dir(
+        "gladius_syzygy_index",
+        &[
+            (
+                "KRvK.rtbw",
+                table_file(
+                    WDL_MAGIC,
+                    &krvk,
+                    &[vec![(0, pattern(KRVK_SIZE)), (0, pattern(KRVK_SIZE))]],
+                ),
+            ),
+            ("KPvK.rtbw", table_file(WDL_MAGIC, &kpvk, &kpvk_pairs)),
+        ],
+    );
+    let syzygy = Syzygy::load_dir(dir.to_str().unwrap()).unwrap();
+
+    // positions mirrored onto each other share their index, and so their value
+    let mut seed = 0x2545F4914F6CDD1Du64;
+    for &(pieces, pawns) in &[("KRk", false), ("KPk", true)] {
+        let mut results = vec![];
+        while results.len() < 200 {
+            let placement = random_placement(pieces, &mut seed);
+            let white_to_move = seed.is_multiple_of(2);
+            let state = match legal_state(&placement, white_to_move) {
+                Some(state) => state,
+                None => continue,
+            };
+            let result = syzygy.probe_wdl(&state).unwrap();
+            let symmetries: &[fn(usize) -> usize] = if pawns {
+                &[|square| square ^ 7]
+            } else {
+                &[
+                    |square| square ^ 7,
+                    |square| square ^ 56,
+                    |square| (square >> 3) | (square << 3) & 63,
+                ]
+            };
+            for symmetry in symmetries {
+                let mirrored: Vec<(usize, char)> = placement
+                    .iter()
+                    .map(|&(square, piece)| (symmetry(square), piece))
+                    .collect();
+                let mirrored = legal_state(&mirrored, white_to_move).unwrap();
+                assert_eq!(
+                    syzygy.probe_wdl(&mirrored),
+                    Some(result),
+                    "{}",
+                    fen(&placement, white_to_move)
+                );
+            }
+            let flipped: Vec<(usize, char)> = placement
+                .iter()
+                .map(|&(square, piece)| {
+                    let piece = if piece.is_ascii_uppercase() {
+                        piece.to_ascii_lowercase()
+                    } else {
+                        piece.to_ascii_uppercase()
+                    };
+                    (square ^ 56, piece)
+                })
+                .collect();
+            let flipped = legal_state(&flipped, !white_to_move).unwrap();
+            assert_eq!(
+                syzygy.probe_wdl(&flipped),
+                Some(result),
+                "{}",
+                fen(&placement, white_to_move)
+            );
+            results.push(result);
+        }
+
+        // the values do differ between positions
+        results.sort();
+        results.dedup();
+        assert!(results.len() >= 3);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn syzygy_reference_index() {
+    // positions whose indices are worked out by hand from the encoding of the reference
+    // probing code, which hold the only wins of otherwise drawn tables
+    let krvk_wins = [
+        ("k7/8/8/8/8/8/8/1K5R w - - 0 1", 426),
+        ("k7/8/8/8/8/2K5/4R3/8 w - - 0 1", 27520),
+        ("1k6/8/6K1/8/8/7R/8/8 w - - 0 1", 12074),
+        ("7k/8/8/8/8/2R5/8/K7 w - - 0 1", 31175),
+        ("8/8/8/8/3R4/8/7k/K7 w - - 0 1", 30448),
+    ];
+    let kpvk_wins = [
+        ("7k/8/8/1P6/8/8/8/4K3 w - - 0 1", 23085),
+        ("8/2k5/8/8/8/6P1/8/K7 w - - 0 1", 19321),
+    ];
+    let values = |size: usize, wins: &[(&str, usize)]| {
+        let mut values = vec![2; size];
+        for &(_, idx) in wins {
+            values[idx] = 4;
+        }
+        values
+    };
+    let draws = |size: usize| vec![(0, vec![2; size]), (0, vec![2; size])];
+
+    // the leading pawns of both KPvK positions are on the B file, and its values are
+    // stored past the start of the file which is kept in memory
+    let mut kpvk_pairs: Vec<Vec<(u8, Vec<u8>)>> = (0..4).map(|_| draws(KPVK_SIZE)).collect();
+    kpvk_pairs[1][0].1 = values(KPVK_SIZE, &kpvk_wins);
+    let kpvk = table_file(WDL_MAGIC, &[WHITE_PAWN, WHITE_KING, BLACK_KING], &kpvk_pairs);
+    assert!(kpvk.len() > 1 << 16);
+    let krvk = table_file(
+        WDL_MAGIC,
+        &[WHITE_KING, WHITE_ROOK, BLACK_KING],
+        &[vec![(0, values(KRVK_SIZE, &krvk_wins)), (0, vec![2; KRVK_SIZE])]],
+    );
+    let dir = table_dir(
+        "gladius_syzygy_reference",
+        &[("KRvK.rtbw", krvk), ("KPvK.rtbw", kpvk)],
+    );
+    let syzygy = Syzygy::load_dir(dir.to_str().unwrap()).unwrap();
+    for &(fen, _) in krvk_wins.iter().chain(kpvk_wins.iter()) {
+        assert_eq!(wdl(&syzygy, fen), Some(Wdl::Win), "{}", fen);
+    }
+    assert_eq!(wdl(&syzygy, "k7/8/8/8/8/8/8/2K4R w - - 0 1"), Some(Wdl::Draw));
+    assert_eq!(wdl(&syzygy, "7k/8/8/2P5/8/8/8/4K3 w - - 0 1"), Some(Wdl::Draw));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// places the given pieces on distinct random squares, pawns off the first and last ranks
+fn random_placement(pieces: &str, seed: &mut u64) -> Vec<(usize, char)> {
+    let mut placement: Vec<(usize, char)> = vec![];
+    for piece in pieces.chars() {
+        loop {
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 7;
+            *seed ^= *seed << 17;
+            let square = (*seed >> 20) as usize % 64;
+            let pawn_rank = !piece.eq_ignore_ascii_case(&'P') || (8..56).contains(&square);
+            if pawn_rank && placement.iter().all(|&(other, _)| other != square) {
+                placement.push((square, piece));
+                break;
+            }
+        }
+    }
+    placement
+}
+
+// returns the state of the given placement, if the player not to move is not in check
+fn legal_state(placement: &[(usize, char)], white_to_move: bool) -> Option<GameState> {
+    let state = parse_fen(&fen(placement, white_to_move)).ok()?;
+    if state.is_check(state.player_turn.other()) {
+        None
+    } else {
+        Some(state)
+    }
+}
+
+fn fen(placement: &[(usize, char)], white_to_move: bool) -> String {
+    let mut ranks = vec![];
+    for rank in (0..8).rev() {
+        let mut text = String::new();
+        let mut empty = 0;
+        for file in 0..8 {
+            match placement
+                .iter()
+                .find(|&&(square, _)| square == rank * 8 + file)
+            {
+                Some(&(_, piece)) => {
+                    if empty > 0 {
+                        text.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    text.push(piece);
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            text.push_str(&empty.to_string());
+        }
+        ranks.push(text);
+    }
+    format!(
+        "{} {} - - 0 1",
+        ranks.join("/"),
+        if white_to_move { "w" } else { "b" }
+    )
+}
+
+#[test]
+fn syzygy_dtz() {
+    let dir = table_dir("gladius_syzygy_dtz", &krvk());
+    let syzygy = Syzygy::load_dir(dir.to_str().unwrap()).unwrap();
+    let dtz = |fen: &str| syzygy.probe_dtz(&parse_fen(fen).unwrap());
+    assert_eq!(dtz("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(5));
+    assert_eq!(dtz("k7/8/2K5/8/8/8/8/7R b - - 0 1"), Some(-6));
+    assert_eq!(dtz("k6R/8/1K6/8/8/8/8/8 b - - 0 1"), Some(-1));
+    assert_eq!(dtz("k7/1R6/8/3K4/8/8/8/8 b - - 0 1"), Some(0));
+
+    // only the mate reaches zeroing faster than the stored distance
+    let state = parse_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+    let moves = syzygy.root_moves(&state).unwrap();
+    assert_eq!(
+        moves.iter().map(|mv| format!("{}", mv)).collect::<Vec<_>>(),
+        vec!["R: H1 -> H8"]
+    );
+    assert!(Syzygy::new().root_moves(&state).is_none());
+
+    // the search only considers the best moves, and probes positions after captures
+    let params = EvalParams::default();
+    let mut evaluator = Evaluator::handcrafted(&params, &state).with_syzygy(&syzygy, 2);
+    let (best, _) = calculator::search(&state, &mut evaluator, 2).unwrap();
+    assert_eq!(format!("{}", best), "R: H1 -> H8");
+    let state = parse_fen("k7/8/2K5/8/8/8/8/7R b - - 0 1").unwrap();
+    assert_eq!(evaluator.probe(&state, 2), Some(SYZYGY_WIN));
+    assert_eq!(evaluator.probe(&state, 1), None);
+    let state = parse_fen("k7/8/2K5/8/8/8/8/7R b - - 1 1").unwrap();
+    assert_eq!(evaluator.probe(&state, 2), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn syzygy_files() {
+    let mut files = krvk();
+    files.push(("KQvK.rtbw", b"not a table".to_vec()));
+    files.push(("README.txt", vec![]));
+    files.push(("KQQQQQQQvK.rtbw", vec![]));
+    let first = table_dir("gladius_syzygy_first", &files[..1]);
+    let second = table_dir("gladius_syzygy_second", &files[1..]);
+    let path = env::join_paths([&first, &second]).unwrap();
+    let syzygy = Syzygy::load_dir(path.to_str().unwrap());
+    let krvk = syzygy
+        .as_ref()
+        .ok()
+        .and_then(|syzygy| syzygy.probe_wdl(&parse_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap()));
+    let kqvk = syzygy
+        .as_ref()
+        .ok()
+        .and_then(|syzygy| syzygy.probe_wdl(&parse_fen("k7/8/1K6/8/8/8/8/7Q w - - 0 1").unwrap()));
+    fs::remove_dir_all(&first).unwrap();
+    fs::remove_dir_all(&second).unwrap();
+
+    // unreadable tables are not covered, and files which are not tables are ignored
+    let syzygy = syzygy.unwrap();
+    assert_eq!(syzygy.len(), 2);
+    assert_eq!(krvk, Some(Wdl::Win));
+    assert_eq!(kqvk, None);
+    assert!(Syzygy::load_dir("/nonexistent/gladius").is_err());
+}
@@ -0,0 +1,150 @@
+use engine::calculator;
+use engine::evaluator::Evaluator;
+use engine::params::EvalParams;
+use engine::tablebase::{all_materials, Material, Table, TableResult, Tablebase};
+use rules::fen_parser::parse_fen;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+// the three-piece tables without pawns, generated once for all tests
+fn tablebase() -> &'static Tablebase {
+    static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+    TABLEBASE.get_or_init(|| {
+        let mut tablebase = Tablebase::new();
+        for name in ["KQvK", "KRvK", "KBvK"].iter() {
+            tablebase.generate(&Material::from_name(name).unwrap()).unwrap();
+        }
+        tablebase
+    })
+}
+
+fn probe(fen: &str) -> Option<TableResult> {
+    tablebase().probe(&parse_fen(fen).unwrap())
+}
+
+#[test]
+fn tablebase_material() {
+    let material = Material::from_name("KRvKQ").unwrap();
+    assert_eq!(material.name(), "KRvKQ");
+    assert_eq!(material.count(), 4);
+    assert!(!material.is_canonical());
+    assert_eq!(material.canonical().name(), "KQvKR");
+    assert_eq!(Material::from_name("KPQvK").unwrap().name(), "KQPvK");
+    assert!(!Material::from_name("KNvKB").unwrap().is_canonical());
+    assert!(Material::from_name("KvKP").unwrap().flipped().is_canonical());
+
+    let state = parse_fen("8/8/4k3/8/1q6/3K4/8/5R2 w - - 0 1").unwrap();
+    assert_eq!(Material::of(&state), material);
+
+    assert!(Material::from_name("KQK").is_err());
+    assert!(Material::from_name("KQvQ").is_err());
+    assert!(Material::from_name("KXvK").is_err());
+
+    let names: Vec<String> = all_materials(3).iter().map(|material| material.name()).collect();
+    assert_eq!(names, vec!["KQvK", "KRvK", "KBvK", "KNvK", "KPvK"]);
+    assert_eq!(all_materials(4).len(), 35);
+}
+
+#[test]
+fn tablebase_index() {
+    let material = Material::from_name("KRvK").unwrap();
+    let mut valid = 0;
+    for index in (0..material.size()).step_by(7) {
+        if let Some(state) = material.position(index) {
+            assert_eq!(material.index(&state), index);
+            valid += 1;
+        }
+    }
+    assert!(valid > 0);
+
+    // mirrored positions share an index
+    let state = parse_fen("8/8/8/8/8/1k6/8/K5R1 w - - 0 1").unwrap();
+    let mirrored = parse_fen("1R5K/8/6k1/8/8/8/8/8 w - - 0 1").unwrap();
+    assert_eq!(material.index(&state), material.index(&mirrored));
+
+    // pawns only allow mirroring between the king's and queen's sides
+    let material = Material::from_name("KPvK").unwrap();
+    let state = parse_fen("8/8/8/8/8/1k6/6P1/K7 w - - 0 1").unwrap();
+    let mirrored = parse_fen("8/8/8/8/8/6k1/1P6/7K w - - 0 1").unwrap();
+    assert_eq!(material.index(&state), material.index(&mirrored));
+    assert!(material.position(material.index(&state)).is_some());
+}
+
+#[test]
+fn tablebase_results() {
+    assert_eq!(probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(TableResult::Win(1)));
+    assert_eq!(probe("k6R/8/1K6/8/8/8/8/8 b - - 0 1"), Some(TableResult::Loss(0)));
+    assert_eq!(probe("k7/8/1KR5/8/8/8/8/8 b - - 0 1"), Some(TableResult::Loss(4)));
+    assert_eq!(probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(TableResult::Draw));
+    assert_eq!(probe("8/8/4k3/8/8/3K4/8/5B2 w - - 0 1"), Some(TableResult::Draw));
+    assert_eq!(probe("8/8/4k3/8/8/3K4/8/8 b - - 0 1"), Some(TableResult::Draw));
+
+    // black's pieces are looked up in white's table
+    assert_eq!(probe("K7/8/1k6/8/8/8/8/7r b - - 0 1"), Some(TableResult::Win(1)));
+
+    // the longest mates are known
+    let longest = |name: &str| {
+        let table = tablebase().table(&Material::from_name(name).unwrap()).unwrap();
+        let material = table.material();
+        (0..material.size())
+            .filter(|&index| material.position(index).is_some())
+            .filter_map(|index| match table.result(index) {
+                Some(TableResult::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max()
+    };
+    assert_eq!(longest("KQvK"), Some(19));
+    assert_eq!(longest("KRvK"), Some(31));
+    assert_eq!(longest("KBvK"), None);
+
+    // positions outside the tables are not covered
+    assert_eq!(probe("k7/8/1K6/8/8/8/8/6RR w - - 0 1"), None);
+    assert_eq!(probe("r3k3/8/8/8/8/8/8/4K3 b q - 0 1"), None);
+    assert_eq!(Tablebase::new().probe(&parse_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap()), None);
+}
+
+#[test]
+fn tablebase_files() {
+    let material = Material::from_name("KRvK").unwrap();
+    let table = tablebase().table(&material).unwrap();
+    let bytes = table.to_bytes();
+    assert!(bytes.len() < material.size());
+    assert_eq!(&Table::from_bytes(&bytes).unwrap(), table);
+    assert!(Table::from_bytes(b"GNN1").is_err());
+    assert!(Table::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    let dir = env::temp_dir().join("gladius_tablebase_files");
+    let dir = dir.to_str().unwrap();
+    let written = tablebase().save_dir(dir).unwrap();
+    let loaded = Tablebase::load_dir(dir);
+    fs::remove_dir_all(dir).unwrap();
+    assert_eq!(written.len(), 3);
+    assert_eq!(&loaded.unwrap(), tablebase());
+    assert!(Tablebase::load_dir("/nonexistent/gladius").is_err());
+}
+
+#[test]
+fn tablebase_search() {
+    let state = parse_fen("8/8/8/3k4/8/8/8/K6R w - - 0 1").unwrap();
+    let (mv, result) = tablebase().best_move(&state).unwrap();
+    let mut next = state.clone();
+    next.make_move(&mv);
+    assert_eq!(tablebase().probe(&state), Some(result));
+    assert_eq!(tablebase().probe(&next).map(|result| result.before_move()), Some(result));
+
+    // the search plays the table's moves, and scores covered positions from the tables
+    let params = EvalParams::default();
+    let mut evaluator = Evaluator::handcrafted(&params, &state).with_tablebase(tablebase());
+    let (best, score) = calculator::search(&state, &mut evaluator, 3).unwrap();
+    assert_eq!(format!("{}", best), format!("{}", mv));
+    assert_eq!(score, result.score());
+    assert_eq!(evaluator.probe(&next, 0), Some(-tablebase().probe(&next).unwrap().score()));
+
+    let state = parse_fen("8/8/8/3k4/8/8/1r6/K7 b - - 0 1").unwrap();
+    let mut evaluator = Evaluator::handcrafted(&params, &state).with_tablebase(tablebase());
+    let (_, score) = calculator::search(&state, &mut evaluator, 1).unwrap();
+    assert!(score < 0);
+}
+
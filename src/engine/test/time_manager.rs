@@ -0,0 +1,174 @@
+use engine::calculator;
+use engine::calculator::SearchLimit;
+use engine::evaluator::Evaluator;
+use engine::params::EvalParams;
+use engine::time_manager::{Iteration, TimeControl, TimeManager};
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// a clock with the given times in milliseconds
+fn clock(time: u64, increment: u64, moves_to_go: Option<u32>) -> TimeControl {
+    TimeControl::Clock {
+        time: Duration::from_millis(time),
+        increment: Duration::from_millis(increment),
+        moves_to_go,
+    }
+}
+
+// an iteration with the given score, which did not change the best move
+fn iteration(score: i32) -> Iteration {
+    Iteration {
+        score,
+        best_move_changed: false,
+        margin: Some(0),
+        moves: 20,
+        duration: Duration::from_millis(0),
+    }
+}
+
+#[test]
+fn time_manager_allocation() {
+    // the time is shared over the moves to go, plus most of the increment
+    let manager = TimeManager::new(clock(60_000, 0, Some(20)), Duration::from_millis(0));
+    assert_eq!(manager.optimum(), Duration::from_millis(3_000));
+    assert_eq!(manager.maximum(), Duration::from_millis(12_000));
+
+    let manager = TimeManager::new(clock(60_000, 1_000, None), Duration::from_millis(0));
+    assert_eq!(manager.optimum(), Duration::from_millis(2_000 + 750));
+
+    // the overhead is kept in hand
+    let manager = TimeManager::new(clock(3_100, 0, Some(1)), Duration::from_millis(100));
+    assert_eq!(manager.maximum(), Duration::from_millis(2_100));
+    assert_eq!(manager.optimum(), Duration::from_millis(2_100));
+    let manager = TimeManager::new(clock(50, 0, None), Duration::from_millis(100));
+    assert_eq!(manager.maximum(), Duration::from_millis(0));
+
+    let manager = TimeManager::new(
+        TimeControl::MoveTime(Duration::from_millis(500)),
+        Duration::from_millis(20),
+    );
+    assert_eq!(manager.optimum(), Duration::from_millis(480));
+    assert_eq!(manager.maximum(), Duration::from_millis(480));
+    assert!(manager.deadline() <= Instant::now() + Duration::from_millis(480));
+}
+
+#[test]
+fn time_manager_iterations() {
+    let control = clock(600_000, 0, None);
+    let overhead = Duration::from_millis(0);
+
+    // a single legal move needs no more search
+    let mut manager = TimeManager::new(control, overhead);
+    assert!(manager.iteration_done(&Iteration {
+        moves: 1,
+        ..iteration(0)
+    }));
+    assert!(!manager.iteration_done(&iteration(0)));
+
+    // a search for a fixed time goes on until its time is up
+    let control = TimeControl::MoveTime(Duration::from_millis(300));
+    let mut manager = TimeManager::new(control, overhead);
+    assert!(!manager.iteration_done(&Iteration {
+        moves: 1,
+        ..iteration(0)
+    }));
+    assert!(!manager.iteration_done(&Iteration {
+        margin: Some(1000),
+        ..iteration(0)
+    }));
+    thread::sleep(Duration::from_millis(300));
+    assert!(manager.iteration_done(&iteration(0)));
+
+    // with no time left, stable and unstable searches alike stop
+    let mut manager = TimeManager::new(clock(0, 0, None), overhead);
+    assert!(manager.iteration_done(&iteration(0)));
+    assert!(manager.iteration_done(&Iteration {
+        best_move_changed: true,
+        ..iteration(-100)
+    }));
+    assert!(manager.iteration_done(&Iteration {
+        margin: Some(1000),
+        ..iteration(0)
+    }));
+}
+
+#[test]
+fn time_manager_scaling() {
+    // an optimum of 500ms and a maximum of 2s
+    let control = clock(15_000, 0, Some(30));
+    let overhead = Duration::from_millis(0);
+    let mut manager = TimeManager::new(control, overhead);
+    assert_eq!(manager.optimum(), Duration::from_millis(500));
+    assert_eq!(manager.maximum(), Duration::from_millis(2_000));
+    thread::sleep(Duration::from_millis(300));
+
+    // a clear best move stops early, before the optimum
+    assert!(manager.iteration_done(&Iteration {
+        margin: Some(500),
+        ..iteration(0)
+    }));
+    assert!(!manager.iteration_done(&iteration(0)));
+    thread::sleep(Duration::from_millis(300));
+    assert!(manager.iteration_done(&iteration(0)));
+
+    // a changing best move or a falling score extends the search past the optimum
+    assert!(!manager.iteration_done(&Iteration {
+        best_move_changed: true,
+        ..iteration(0)
+    }));
+    let mut manager = TimeManager::new(control, overhead);
+    assert!(!manager.iteration_done(&iteration(0)));
+    thread::sleep(Duration::from_millis(600));
+    assert!(!manager.iteration_done(&iteration(-100)));
+
+    // an iteration expected to run past the optimum is not started
+    let mut manager = TimeManager::new(control, overhead);
+    assert!(!manager.iteration_done(&Iteration {
+        duration: Duration::from_millis(50),
+        ..iteration(0)
+    }));
+    assert!(manager.iteration_done(&Iteration {
+        duration: Duration::from_millis(100),
+        ..iteration(0)
+    }));
+}
+
+#[test]
+fn time_manager_search() {
+    let params = EvalParams::default();
+    let state = GameState::start_position();
+    let control = TimeControl::MoveTime(Duration::from_millis(100));
+    let manager = TimeManager::new(control, Duration::from_millis(0));
+    let limit = SearchLimit::Time(manager);
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let best = calculator::search_limited(&state, &mut evaluator, limit);
+    assert!(best.is_some());
+    assert!(manager.elapsed() < Duration::from_millis(400));
+
+    // the clock is kept from the first iteration, however long each node takes
+    let state = parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+        .unwrap();
+    let manager = TimeManager::new(control, Duration::from_millis(0));
+    let limit = SearchLimit::Time(manager);
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    assert!(calculator::search_limited(&state, &mut evaluator, limit).is_some());
+    assert!(manager.elapsed() < Duration::from_millis(400));
+
+    // a search out of time still finds a move
+    let manager = TimeManager::new(clock(0, 0, None), Duration::from_millis(0));
+    let limit = SearchLimit::Time(manager);
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let best = calculator::search_limited(&state, &mut evaluator, limit);
+    assert!(best.is_some());
+
+    // a single legal move is played after one iteration
+    let state = parse_fen("k7/8/8/8/8/8/8/KR6 b - - 0 1").unwrap();
+    let manager = TimeManager::new(clock(600_000, 0, None), Duration::from_millis(0));
+    let limit = SearchLimit::Time(manager);
+    let mut evaluator = Evaluator::handcrafted(&params, &state);
+    let best = calculator::search_limited(&state, &mut evaluator, limit);
+    assert_eq!(best.unwrap().0.to_string(), "K: A8 -> A7");
+    assert!(manager.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,217 @@
+//! Time management.
+//!
+//! A search under a time control is given two limits when it starts: an optimum time,
+//! which it aims to spend on the move, and a maximum time, at which it is stopped even in
+//! the middle of an iteration. The optimum is the remaining time shared out over the moves
+//! left to play, plus most of the increment, after setting aside the move overhead: the
+//! time lost between the engine choosing a move and the GUI's clock stopping.
+//!
+//! After each iteration of the deepening search on the clock, the optimum is scaled to the
+//! state of the search. The time allowed grows while the best move keeps changing or the
+//! score drops, and shrinks while one move is clearly better than the rest. An iteration
+//! expected to run past the time allowed is not started, each taking several times as
+//! long as the last. With only one legal move, the search stops after its first
+//! iteration. A search for a fixed time always searches until its time is up.
+//!
+//! A search pondering on the opponent's time keeps its time manager, restarting its clock
+//! when the opponent plays the move pondered on.
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+/// The number of moves the remaining time is shared over when the GUI does not say how
+/// many moves are left until the next time control.
+pub const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The most moves the remaining time is shared over.
+pub const MAX_MOVES_TO_GO: u32 = 50;
+
+/// The percentage of the increment added to the optimum time.
+pub const INCREMENT_PERCENT: u32 = 75;
+
+/// The maximum time as a multiple of the optimum time.
+pub const MAXIMUM_FACTOR: u32 = 4;
+
+/// The percentage of the remaining time the maximum time may use.
+pub const MAXIMUM_PERCENT: u32 = 70;
+
+/// The number of times longer each iteration is expected to take than the one before.
+pub const ITERATION_GROWTH: u32 = 6;
+
+/// The percentage added to the time allowed each time the best move changes, which
+/// halves with every iteration after the change.
+pub const INSTABILITY_PERCENT: u32 = 60;
+
+/// The fall in score, in centipawns for the player to move, from one iteration to the
+/// next which is taken as the score dropping.
+pub const SCORE_DROP: i32 = 30;
+
+/// The percentage added to the time allowed after the score drops.
+pub const SCORE_DROP_PERCENT: u32 = 50;
+
+/// The lead, in centipawns, of the best move over the second best which makes it clearly
+/// best.
+pub const CLEAR_MARGIN: i32 = 150;
+
+/// The percentage of the time allowed used when one move is clearly best.
+pub const CLEAR_PERCENT: u32 = 40;
+
+/// How the time for a move is set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeControl {
+    /// Search for exactly the given time.
+    MoveTime(Duration),
+
+    /// Search within the time on the player's clock.
+    Clock {
+        /// The time left on the clock.
+        time: Duration,
+
+        /// The time added to the clock after each move.
+        increment: Duration,
+
+        /// The number of moves to make before the next time control, if there is one.
+        moves_to_go: Option<u32>,
+    },
+}
+
+/// The result of an iteration of a search, as seen by the [TimeManager].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Iteration {
+    /// The score of the best move, in centipawns for the player to move.
+    pub score: i32,
+
+    /// True if the best move differs from that of the previous iteration.
+    pub best_move_changed: bool,
+
+    /// How far the best move's score is ahead of the second best, if there are two moves.
+    pub margin: Option<i32>,
+
+    /// The number of moves searched at the root.
+    pub moves: usize,
+
+    /// The time the iteration took.
+    pub duration: Duration,
+}
+
+/// Decides how long a search may take.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TimeManager {
+    /// When the search started.
+    start: Instant,
+
+    /// The time the search aims to take.
+    optimum: Duration,
+
+    /// The time after which the search is stopped.
+    maximum: Duration,
+
+    /// True if the search is for a fixed time, so the optimum is not scaled.
+    fixed: bool,
+
+    /// The percentage added to the time allowed by recent changes of best move.
+    instability: u32,
+
+    /// The score of the previous iteration, in centipawns for the player to move.
+    last_score: Option<i32>,
+}
+
+impl TimeManager {
+    /// Returns a time manager for a search starting now under the given time control,
+    /// keeping the given overhead in hand for each move.
+    pub fn new(control: TimeControl, overhead: Duration) -> Self {
+        let (optimum, maximum) = match control {
+            TimeControl::MoveTime(time) => {
+                let time = time.checked_sub(overhead).unwrap_or_default();
+                (time, time)
+            }
+            TimeControl::Clock {
+                time,
+                increment,
+                moves_to_go,
+            } => {
+                let moves = moves_to_go
+                    .unwrap_or(DEFAULT_MOVES_TO_GO)
+                    .clamp(1, MAX_MOVES_TO_GO);
+                let available = time.checked_sub(overhead).unwrap_or_default();
+                let optimum = available / moves + increment * INCREMENT_PERCENT / 100;
+                let maximum = cmp::min(optimum * MAXIMUM_FACTOR, available * MAXIMUM_PERCENT / 100);
+                (cmp::min(optimum, maximum), maximum)
+            }
+        };
+        TimeManager {
+            start: Instant::now(),
+            optimum,
+            maximum,
+            fixed: match control {
+                TimeControl::MoveTime(_) => true,
+                TimeControl::Clock { .. } => false,
+            },
+            instability: 0,
+            last_score: None,
+        }
+    }
+
+    /// Returns the time the search aims to take, before scaling to the state of the
+    /// search.
+    pub fn optimum(&self) -> Duration {
+        self.optimum
+    }
+
+    /// Returns the time after which the search is stopped.
+    pub fn maximum(&self) -> Duration {
+        self.maximum
+    }
+
+    /// Returns the time elapsed since the search started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Restarts the clock, keeping what was learned from the iterations so far, as when a
+    /// pondering search becomes a timed search.
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// Returns the instant at which the search is stopped.
+    pub fn deadline(&self) -> Instant {
+        self.start + self.maximum
+    }
+
+    /// Records a completed iteration, returning true if the search should stop rather
+    /// than start the next one, as when the next is expected to take longer than the time
+    /// left.
+    ///
+    /// A search for a fixed time only stops once its time is up.
+    pub fn iteration_done(&mut self, iteration: &Iteration) -> bool {
+        if self.fixed {
+            return self.elapsed() >= self.maximum;
+        }
+        if iteration.moves <= 1 {
+            return true;
+        }
+        self.instability /= 2;
+        if iteration.best_move_changed {
+            self.instability += INSTABILITY_PERCENT;
+        }
+        let dropped = self
+            .last_score
+            .is_some_and(|last| last - iteration.score >= SCORE_DROP);
+        self.last_score = Some(iteration.score);
+
+        let mut percent = 100 + self.instability;
+        if dropped {
+            percent += SCORE_DROP_PERCENT;
+        }
+        if !iteration.best_move_changed && iteration.margin.is_some_and(|m| m >= CLEAR_MARGIN) {
+            percent = percent * CLEAR_PERCENT / 100;
+        }
+        self.elapsed() + iteration.duration * ITERATION_GROWTH >= self.allowed(percent)
+    }
+
+    // the time allowed at the given percentage of the optimum, up to the maximum
+    fn allowed(&self, percent: u32) -> Duration {
+        cmp::min(self.optimum * percent / 100, self.maximum)
+    }
+}
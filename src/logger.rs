@@ -20,7 +20,7 @@ pub fn setup() -> Result<(), fern::InitError> {
             ))
         })
         .level(log::LevelFilter::Debug)
-        .chain(std::io::stdout())
+        .chain(std::io::stderr())
 //        .chain(fern::log_file("output.log")?)
         .apply()?;
     Ok(())
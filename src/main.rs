@@ -10,7 +10,10 @@
 pub mod board;
 pub mod engine;
 pub mod logger;
+pub mod random;
 pub mod rules;
+pub mod tools;
+pub mod uci;
 
 extern crate bit_reverse;
 
@@ -25,8 +28,31 @@ extern crate fern;
 extern crate chrono;
 extern crate futures;
 
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+use std::env;
+
 /// The main method.
+///
+/// Starts a UCI session, unless arguments are given to run one of the command line tools.
 fn main() -> Result<(), ()> {
-    logger::setup().map_err(|_| ());
-    Ok(())
+    logger::setup().map_err(|_| ())?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        uci::run();
+        return Ok(());
+    }
+    match tools::run(&args) {
+        Ok(output) => {
+            println!("{}", output);
+            Ok(())
+        }
+        Err(error) => {
+            error!("{}", error);
+            Err(())
+        }
+    }
 }
@@ -0,0 +1,30 @@
+//! A small random number generator.
+//!
+//! The generator is a xorshift, which is fast and good enough for choosing openings and
+//! book moves, and which always gives the same sequence for the same seed, so that
+//! self-play games can be replayed.
+
+/// A xorshift random number generator.
+#[derive(Clone, Debug)]
+pub struct Random(u64);
+
+impl Random {
+    /// Returns a generator with the given seed.
+    pub fn new(seed: u64) -> Self {
+        // the generator must not start at zero
+        Random(seed.wrapping_mul(0x2545_F491_4F6C_DD1D) | 1)
+    }
+
+    /// Returns the next value of the generator.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns the next value in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
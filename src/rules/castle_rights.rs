@@ -1,5 +1,6 @@
 //! The castle rights.
 
+use board::file::File;
 use std::fmt::{Display, Formatter, Result};
 
 /// The castle rights.
@@ -77,3 +78,25 @@ impl Display for CastleRights {
         write!(f, "Castle Rights: {:?}", self)
     }
 }
+
+/// The files of the rooks which castle on each side of the king.
+///
+/// These are the H and A files in standard chess, but depend on the start position
+/// in Chess960. Both players share the same files.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct CastleFiles {
+    /// The file of the king-side rook.
+    pub king_side: File,
+
+    /// The file of the queen-side rook.
+    pub queen_side: File,
+}
+
+impl Default for CastleFiles {
+    fn default() -> Self {
+        CastleFiles {
+            king_side: File::H,
+            queen_side: File::A,
+        }
+    }
+}
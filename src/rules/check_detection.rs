@@ -2,20 +2,41 @@
 
 use board::piece::Piece;
 use board::player::Player;
+use board::square::Square;
+use rules::basic_moves;
 use rules::game_state::GameState;
+use rules::variant::Variant;
 
 impl GameState {
     /// Returns true if the given player is is in check.
+    ///
+    /// A player without a king, or playing Antichess, is never in check. In Atomic, kings
+    /// next to each other are not in check, as capturing either would explode both.
     pub fn is_check(&self, player: Player) -> bool {
-        let own_board = self.player_board(player);
-        let king_square = own_board.king.to_square();
-        let own_pieces = own_board.all();
-        let opponent_pieces = self.player_board(player.other());
+        let king = self.player_board(player).king;
+        if king.is_empty() || self.variant == Variant::Antichess {
+            return false;
+        }
+        let king_square = king.to_square();
+        if self.variant == Variant::Atomic
+            && !(basic_moves::king(king_square) & self.player_board(player.other()).king)
+                .is_empty()
+        {
+            return false;
+        }
+        self.is_attacked(king_square, player.other())
+    }
+
+    /// Returns true if the given square is attacked by any of the given player's pieces.
+    pub fn is_attacked(&self, square: Square, attacker: Player) -> bool {
+        let defender = attacker.other();
+        let own_pieces = self.player_board(defender).all();
+        let opponent_pieces = self.player_board(attacker);
 
-        // if for example, bishop attacks from the king contains a bishop,
-        // then a bishop can attack the king. Repeat this for all pieces.
+        // if for example, bishop attacks from the square contains a bishop,
+        // then a bishop can attack the square. Repeat this for all pieces.
         Piece::iter().any(|p| {
-            !(p.attacks(king_square, player, own_pieces, opponent_pieces.all())
+            !(p.attacks(square, defender, own_pieces, opponent_pieces.all())
                 & opponent_pieces.piece(p))
                 .is_empty()
         })
@@ -0,0 +1,111 @@
+//! Chess960 (Fischer Random) start positions.
+
+use board::file::File;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use board::square::Square;
+use rules::castle_rights::{CastleFiles, CastleRights};
+use rules::game_state::GameState;
+use rules::player_board::PlayerBoard;
+
+/// The number of distinct Chess960 start positions.
+pub const POSITION_COUNT: u16 = 960;
+
+/// The Chess960 position number of the standard chess start position.
+pub const STANDARD_POSITION: u16 = 518;
+
+impl GameState {
+    /// Returns the Chess960 start position with the given number, from 0 to 959.
+    ///
+    /// Positions are numbered using Scharnagl's scheme, where position 518 is the
+    /// standard chess start position.
+    ///
+    /// Will panic if the number is out of range.
+    pub fn chess960_start_position(number: u16) -> Self {
+        assert!(
+            number < POSITION_COUNT,
+            "Chess960 position {} does not exist",
+            number
+        );
+        let back_rank = chess960_back_rank(number);
+
+        let mut castle_files = CastleFiles::default();
+        let rooks: Vec<_> = File::iter()
+            .filter(|&&file| back_rank[file.to_index() as usize] == Piece::Rook)
+            .collect();
+        castle_files.queen_side = *rooks[0];
+        castle_files.king_side = *rooks[1];
+
+        GameState::default()
+            .with_white_board(chess960_player_board(&back_rank, Player::White))
+            .with_black_board(chess960_player_board(&back_rank, Player::Black))
+            .with_white_castle_rights(CastleRights::Both)
+            .with_black_castle_rights(CastleRights::Both)
+            .with_castle_files(castle_files)
+    }
+}
+
+// returns the pieces on the back rank, from the A file to the H file
+fn chess960_back_rank(number: u16) -> [Piece; 8] {
+    // the placements of the two knights amongst the five squares left after placing
+    // the bishops and queen
+    static KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+
+    let mut squares: [Option<Piece>; 8] = [None; 8];
+    let number = number as usize;
+    squares[2 * (number % 4) + 1] = Some(Piece::Bishop);
+    squares[2 * ((number / 4) % 4)] = Some(Piece::Bishop);
+
+    let empty = |squares: &[Option<Piece>; 8]| -> Vec<usize> {
+        (0..8).filter(|&i| squares[i].is_none()).collect()
+    };
+
+    let queen = empty(&squares)[(number / 16) % 6];
+    squares[queen] = Some(Piece::Queen);
+
+    let (first, second) = KNIGHTS[number / 96];
+    let remaining = empty(&squares);
+    squares[remaining[first]] = Some(Piece::Knight);
+    squares[remaining[second]] = Some(Piece::Knight);
+
+    // the king goes between the two rooks
+    let remaining = empty(&squares);
+    squares[remaining[0]] = Some(Piece::Rook);
+    squares[remaining[1]] = Some(Piece::King);
+    squares[remaining[2]] = Some(Piece::Rook);
+
+    let mut back_rank = [Piece::Pawn; 8];
+    for (i, square) in squares.iter().enumerate() {
+        back_rank[i] = square.expect("Chess960 back rank has an empty square");
+    }
+    back_rank
+}
+
+// returns the player board for the given back rank
+fn chess960_player_board(back_rank: &[Piece; 8], player: Player) -> PlayerBoard {
+    let (piece_rank, pawn_rank) = match player {
+        Player::White => (Rank::One, Rank::Two),
+        Player::Black => (Rank::Eight, Rank::Seven),
+    };
+
+    File::iter().fold(
+        PlayerBoard::default().with_pawns(pawn_rank.to_bitboard()),
+        |board, &file| {
+            let piece = back_rank[file.to_index() as usize];
+            let square = Square::from_coordinates(file, piece_rank);
+            board.with_piece(piece, board.piece(piece).set_square(square))
+        },
+    )
+}
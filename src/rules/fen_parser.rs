@@ -1,11 +1,16 @@
-//! Parses FEN notation into game state.
+//! Parses FEN notation into game state, and writes game states as FEN.
 
+use board::bitboard::BitBoard;
+use board::file::File;
 use board::piece::Piece;
 use board::player::Player;
+use board::rank::Rank;
 use board::square::Square;
-use rules::castle_rights::CastleRights;
+use rules::castle_rights::{CastleFiles, CastleRights};
 use rules::game_state::GameState;
 use rules::player_board::PlayerBoard;
+use rules::pocket::Pocket;
+use rules::variant::{Variant, CHECKS_TO_WIN};
 use std::cmp::max;
 use std::str::FromStr;
 
@@ -25,6 +30,9 @@ use std::str::FromStr;
 /// - Castling availability. If neither side can castle, this is "-". Otherwise, this
 ///   has one or more letters: "K" (White can castle kingside), "Q" (White can castle queenside),
 ///   "k" (Black can castle kingside), and/or "q" (Black can castle queenside).
+///   For Chess960, the X-FEN and Shredder-FEN forms are also accepted, where the letter
+///   of the file of the castling rook ("A" to "H", or "a" to "h" for black) is given
+///   instead. "K" and "Q" then refer to the outermost rook on that side of the king.
 /// - En passant target square in algebraic notation. If there's no en passant target square,
 ///   this is "-". If a pawn has just made a two-square move, this is the position "behind"
 ///   the pawn. This is recorded regardless of whether there is a pawn in position to make an
@@ -33,31 +41,183 @@ use std::str::FromStr;
 ///   This is used to determine if a draw can be claimed under the fifty-move rule.
 /// - Fullmove number: The number of the full move. It starts at 1, and is incremented
 ///   after Black's move.
+///
+/// For Crazyhouse, the pieces held in hand may follow the piece placement, either in
+/// brackets ("[Qp]") or as a ninth rank ("/Qp"), and promoted pieces are marked with a
+/// following "~". The state's variant is then set to Crazyhouse.
+///
+/// For Three-check, a seventh field with the check counts is also accepted, either as the
+/// remaining checks before the halfmove clock ("3+3"), or as the checks given after the
+/// fullmove number ("+0+0"). The state's variant is then set to Three-check.
 pub fn parse_fen(fen: &str) -> Result<GameState, String> {
     info!("Parsing FEN: '{}'", fen);
-    let parts = fen.split(" ").collect::<Vec<_>>();
+    let mut parts = fen.split(" ").collect::<Vec<_>>();
 
-    if parts.len() != 6 {
-        return Err(format!("FEN hasn't got exactly 6 required parts: {}", fen));
-    }
+    let checks = match parts.len() {
+        6 => None,
+        7 if parts[6].starts_with('+') => Some(parse_checks_given(parts.remove(6))?),
+        7 => Some(parse_checks_remaining(parts.remove(4))?),
+        _ => return Err(format!("FEN hasn't got exactly 6 required parts: {}", fen)),
+    };
 
-    let pieces = parse_piece_placement(parts[0])?;
+    let (placement, pockets) = split_pockets(parts[0])?;
+    let (pieces, promoted) = parse_piece_placement(placement)?;
     let active = parse_active_color(parts[1])?;
-    let castling = parse_castling_ability(parts[2])?;
+    let (castling, castle_files) = parse_castling_ability(parts[2], &pieces)?;
     let en_passant = parse_en_passant(parts[3])?;
     let half_moves = parse_number(parts[4])?;
     let full_moves = parse_number(parts[5])?;
+    let variant = match (&checks, &pockets) {
+        (Some(_), Some(_)) => return Err(format!("FEN has both checks and pockets: {}", fen)),
+        (Some(_), None) => Variant::ThreeCheck,
+        (None, Some(_)) => Variant::Crazyhouse,
+        (None, None) => Variant::Standard,
+    };
+    let checks = checks.unwrap_or(PlayerValues { white: 0, black: 0 });
+    let pockets = pockets.unwrap_or(PlayerValues {
+        white: Pocket::new(),
+        black: Pocket::new(),
+    });
 
-    Ok(GameState {
-        white_board: pieces.white,
-        black_board: pieces.black,
-        player_turn: active,
+    let mut state = GameState::default()
+        .with_player_turn(active)
+        .with_en_passant(en_passant)
+        .with_white_castle_rights(castling.white)
+        .with_black_castle_rights(castling.black)
+        .with_castle_files(castle_files)
+        .with_draw_plies(half_moves)
+        .with_full_turns(max(full_moves, 1) - 1)
+        .with_variant(variant)
+        .with_white_checks(checks.white)
+        .with_black_checks(checks.black)
+        .with_white_pocket(pockets.white)
+        .with_black_pocket(pockets.black)
+        .with_promoted(promoted);
+    state.set_boards(pieces.white, pieces.black);
+    Ok(state)
+}
+
+/// Writes the given game state as a FEN string, in the form read by [parse_fen].
+///
+/// Castling rights are written as "KQkq" for rooks on their standard files, and
+/// otherwise with the file of the castling rook as in Shredder-FEN. Crazyhouse states
+/// include their pockets and promoted pieces, and Three-check states include the checks
+/// given as a seventh field. Other variants are not recorded.
+pub fn to_fen(state: &GameState) -> String {
+    let mut placement = String::new();
+    for &rank in Rank::iter().rev() {
+        let mut empty = 0;
+        for &file in File::iter() {
+            let square = Square::from_coordinates(file, rank);
+            match state.piece_at(square) {
+                Some((piece, player)) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push(fen_piece(piece, player));
+                    if state.variant == Variant::Crazyhouse && state.promoted.is_square_set(square)
+                    {
+                        placement.push('~');
+                    }
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if rank != Rank::One {
+            placement.push('/');
+        }
+    }
+    if state.variant == Variant::Crazyhouse {
+        placement.push('[');
+        for &(pocket, player) in [
+            (state.white_pocket, Player::White),
+            (state.black_pocket, Player::Black),
+        ].iter()
+        {
+            for &(piece, count) in [
+                (Piece::Queen, pocket.queens),
+                (Piece::Rook, pocket.rooks),
+                (Piece::Bishop, pocket.bishops),
+                (Piece::Knight, pocket.knights),
+                (Piece::Pawn, pocket.pawns),
+            ].iter()
+            {
+                for _ in 0..count {
+                    placement.push(fen_piece(piece, player));
+                }
+            }
+        }
+        placement.push(']');
+    }
+
+    let mut castling = String::new();
+    for &(rights, player) in [
+        (state.white_castle_rights, Player::White),
+        (state.black_castle_rights, Player::Black),
+    ].iter()
+    {
+        let sides = [
+            (rights.is_king_side_available(), state.castle_files.king_side, File::H, 'K'),
+            (rights.is_queen_side_available(), state.castle_files.queen_side, File::A, 'Q'),
+        ];
+        for &(available, file, standard_file, standard) in sides.iter() {
+            if !available {
+                continue;
+            }
+            let ch = if file == standard_file {
+                standard
+            } else {
+                file.to_string().chars().next().unwrap()
+            };
+            castling.push(match player {
+                Player::White => ch,
+                Player::Black => ch.to_ascii_lowercase(),
+            });
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    let en_passant = state
+        .en_passant
+        .map_or("-".to_string(), |square| square.to_string().to_lowercase());
+    let mut fen = format!(
+        "{} {} {} {} {} {}",
+        placement,
+        match state.player_turn {
+            Player::White => "w",
+            Player::Black => "b",
+        },
+        castling,
         en_passant,
-        white_castle_rights: castling.white,
-        black_castle_rights: castling.black,
-        draw_plies: half_moves,
-        full_turns: max(full_moves, 1) - 1,
-    })
+        state.draw_plies,
+        state.full_turns as u32 + 1
+    );
+    if state.variant == Variant::ThreeCheck {
+        fen.push_str(&format!(" +{}+{}", state.white_checks, state.black_checks));
+    }
+    fen
+}
+
+// returns the FEN identifier of the given piece
+fn fen_piece(piece: Piece, player: Player) -> char {
+    let ch = match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    };
+    match player {
+        Player::White => ch,
+        Player::Black => ch.to_ascii_lowercase(),
+    }
 }
 
 // represents a result for each player
@@ -69,13 +229,25 @@ struct PlayerValues<T> {
 // ---------------------------------------------------
 // parses the piece placement part
 // TODO this needs to be flipped??
-fn parse_piece_placement(part: &str) -> Result<PlayerValues<PlayerBoard>, String> {
+fn parse_piece_placement(part: &str) -> Result<(PlayerValues<PlayerBoard>, BitBoard), String> {
     // can literally step through the string and parse it as needed.
     let mut white = PlayerBoard::new();
     let mut black = PlayerBoard::new();
+    let mut promoted = BitBoard::empty();
+    let mut follows_piece = false;
     let mut square_index: u8 = 0;
     let chars = part.chars();
     for ch in chars {
+        // mark the previous piece as promoted
+        if ch == '~' {
+            if !follows_piece {
+                return Err(format!("Promotion marker without a piece: {}", part));
+            }
+            promoted = promoted.set_square(Square::new(square_index - 1).mirror_horizontal());
+            follows_piece = false;
+            continue;
+        }
+        follows_piece = ch.is_alphabetic();
         // move to next rank
         if ch == '/' {
             if square_index % 8 != 0 {
@@ -100,7 +272,38 @@ fn parse_piece_placement(part: &str) -> Result<PlayerValues<PlayerBoard>, String
     if square_index != 64 {
         return Err(format!("Piece sequence doesn't cover 64 squares: {}", part));
     }
-    Ok(PlayerValues { white, black })
+    Ok((PlayerValues { white, black }, promoted))
+}
+
+// splits the pieces held in hand, if any, from the piece placement part
+fn split_pockets(part: &str) -> Result<(&str, Option<PlayerValues<Pocket>>), String> {
+    let (placement, held) = if let Some(index) = part.find('[') {
+        if !part.ends_with(']') {
+            return Err(format!("Unterminated pocket: {}", part));
+        }
+        (&part[..index], &part[index + 1..part.len() - 1])
+    } else if part.matches('/').count() == 8 {
+        let index = part.rfind('/').unwrap();
+        (&part[..index], &part[index + 1..])
+    } else {
+        return Ok((part, None));
+    };
+
+    let mut pockets = PlayerValues {
+        white: Pocket::new(),
+        black: Pocket::new(),
+    };
+    for ch in held.chars().filter(|&ch| ch != '-') {
+        let (player, piece) = parse_fen_piece(ch)?;
+        if piece == Piece::King {
+            return Err(format!("Kings cannot be held in a pocket: {}", part));
+        }
+        match player {
+            Player::White => pockets.white.add(piece),
+            Player::Black => pockets.black.add(piece),
+        }
+    }
+    Ok((placement, Some(pockets)))
 }
 
 // parses the given FEN piece identifier
@@ -135,25 +338,75 @@ fn parse_active_color(part: &str) -> Result<Player, String> {
 }
 
 // parses the castling ability part
-fn parse_castling_ability(part: &str) -> Result<PlayerValues<CastleRights>, String> {
+fn parse_castling_ability(
+    part: &str,
+    pieces: &PlayerValues<PlayerBoard>,
+) -> Result<(PlayerValues<CastleRights>, CastleFiles), String> {
+    let mut castling = PlayerValues {
+        white: CastleRights::None,
+        black: CastleRights::None,
+    };
+    let mut castle_files = CastleFiles::default();
     if part == "-" {
-        return Ok(PlayerValues {
-            black: CastleRights::None,
-            white: CastleRights::None,
-        });
+        return Ok((castling, castle_files));
     }
-    let mut white = CastleRights::None;
-    let mut black = CastleRights::None;
+
+    let mut king_side_file = None;
+    let mut queen_side_file = None;
     for char in part.chars() {
-        match char {
-            'K' => white = white.with_king_side(),
-            'Q' => white = white.with_queen_side(),
-            'k' => black = black.with_king_side(),
-            'q' => black = black.with_queen_side(),
+        let (player, board, rights) = if char.is_ascii_uppercase() {
+            (Player::White, &pieces.white, &mut castling.white)
+        } else {
+            (Player::Black, &pieces.black, &mut castling.black)
+        };
+        let back_rank = match player {
+            Player::White => Rank::One,
+            Player::Black => Rank::Eight,
+        };
+        let king_file = (board.king & back_rank.to_bitboard())
+            .iter()
+            .next()
+            .map_or(File::E, |square| square.file());
+        let mut rooks = (board.rooks & back_rank.to_bitboard()).iter();
+
+        // rights without a matching rook fall back to the standard files
+        let (king_side, file) = match char.to_ascii_uppercase() {
+            'K' => (
+                true,
+                rooks
+                    .filter(|square| square.file().to_index() > king_file.to_index())
+                    .last()
+                    .map_or(File::H, |square| square.file()),
+            ),
+            'Q' => (
+                false,
+                rooks
+                    .find(|square| square.file().to_index() < king_file.to_index())
+                    .map_or(File::A, |square| square.file()),
+            ),
+            'A'..='H' => {
+                let file = File::from_str(char.to_string().as_ref())?;
+                (file.to_index() > king_file.to_index(), file)
+            }
             _ => return Err(format!("Unknown castle ability character {}", char)),
+        };
+
+        let side_file = if king_side {
+            *rights = rights.with_king_side();
+            &mut king_side_file
+        } else {
+            *rights = rights.with_queen_side();
+            &mut queen_side_file
+        };
+        if side_file.is_some() && *side_file != Some(file) {
+            return Err(format!("Castle rights on different rook files: {}", part));
         }
+        *side_file = Some(file);
     }
-    Ok(PlayerValues { black, white })
+
+    castle_files.king_side = king_side_file.unwrap_or(castle_files.king_side);
+    castle_files.queen_side = queen_side_file.unwrap_or(castle_files.queen_side);
+    Ok((castling, castle_files))
 }
 
 // parses the en passant square part
@@ -168,3 +421,28 @@ fn parse_en_passant(part: &str) -> Result<Option<Square>, String> {
 fn parse_number(part: &str) -> Result<u8, String> {
     u8::from_str(part).map_err(|er| er.to_string())
 }
+
+// parses the checks given by each player, in the form "+1+0"
+fn parse_checks_given(part: &str) -> Result<PlayerValues<u8>, String> {
+    let counts = part.split('+').skip(1).collect::<Vec<_>>();
+    if counts.len() != 2 {
+        return Err(format!("Invalid check counts: {}", part));
+    }
+    let checks = PlayerValues {
+        white: parse_number(counts[0])?,
+        black: parse_number(counts[1])?,
+    };
+    if checks.white > CHECKS_TO_WIN || checks.black > CHECKS_TO_WIN {
+        return Err(format!("Invalid check counts: {}", part));
+    }
+    Ok(checks)
+}
+
+// parses the checks remaining for each player, in the form "2+3"
+fn parse_checks_remaining(part: &str) -> Result<PlayerValues<u8>, String> {
+    let remaining = parse_checks_given(&format!("+{}", part))?;
+    Ok(PlayerValues {
+        white: CHECKS_TO_WIN - remaining.white,
+        black: CHECKS_TO_WIN - remaining.black,
+    })
+}
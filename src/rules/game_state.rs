@@ -4,22 +4,31 @@ use std::char;
 use std::default::Default;
 use std::fmt::{Display, Formatter, Result};
 
+use board::bitboard::BitBoard;
 use board::file::File;
 use board::piece::Piece;
 use board::player::Player;
 use board::rank::Rank;
 use board::square::Square;
-use rules::castle_rights::CastleRights;
+use rules::castle_rights::{CastleFiles, CastleRights};
+use rules::mailbox::Mailbox;
 use rules::player_board::PlayerBoard;
+use rules::pocket::Pocket;
+use rules::variant::Variant;
+use rules::zobrist;
 
 /// Represents a complete state of a chess board.
-#[derive(Clone, Debug, Eq, PartialEq, Withers)]
+///
+/// The mailbox and pawn key are derived from the player boards, so they are not
+/// considered when comparing states. The boards are only changed through methods which
+/// keep both in sync with them.
+#[derive(Clone, Debug, Eq)]
 pub struct GameState {
     /// The white player board.
-    pub white_board: PlayerBoard,
+    white_board: PlayerBoard,
 
     /// The black player board.
-    pub black_board: PlayerBoard,
+    black_board: PlayerBoard,
 
     /// The player whose turn it is.
     pub player_turn: Player,
@@ -33,11 +42,38 @@ pub struct GameState {
     /// Black players castle rights.
     pub black_castle_rights: CastleRights,
 
+    /// The files of the rooks the castle rights apply to.
+    pub castle_files: CastleFiles,
+
     /// The number of half-turns since the last capture or pawn advance.
     pub draw_plies: u8,
 
     /// The number of full turns elapsed (such that a new game starts at 0)
     pub full_turns: u8,
+
+    /// The variant the game is played under.
+    pub variant: Variant,
+
+    /// The number of checks given by the white player.
+    pub white_checks: u8,
+
+    /// The number of checks given by the black player.
+    pub black_checks: u8,
+
+    /// The pieces held in hand by the white player.
+    pub white_pocket: Pocket,
+
+    /// The pieces held in hand by the black player.
+    pub black_pocket: Pocket,
+
+    /// The squares of pieces which were promoted from pawns, of either player.
+    pub promoted: BitBoard,
+
+    /// The piece on each square, kept in sync with the player boards.
+    mailbox: Mailbox,
+
+    /// The Zobrist key of the pawns, kept in sync with the player boards.
+    pawn_key: u64,
 }
 
 impl GameState {
@@ -50,11 +86,50 @@ impl GameState {
             en_passant: None,
             white_castle_rights: CastleRights::Both,
             black_castle_rights: CastleRights::Both,
+            castle_files: CastleFiles::default(),
             draw_plies: 0,
             full_turns: 0,
+            variant: Variant::Standard,
+            white_checks: 0,
+            black_checks: 0,
+            white_pocket: Pocket::new(),
+            black_pocket: Pocket::new(),
+            promoted: BitBoard::empty(),
+            mailbox: Mailbox::from_boards(
+                &PlayerBoard::start_position(Player::White),
+                &PlayerBoard::start_position(Player::Black),
+            ),
+            pawn_key: zobrist::pawn_key(
+                &PlayerBoard::start_position(Player::White),
+                &PlayerBoard::start_position(Player::Black),
+            ),
         }
     }
 
+    /// Returns the piece and its owner on the given square, if any.
+    pub fn piece_at(&self, square: Square) -> Option<(Piece, Player)> {
+        self.mailbox.get(square)
+    }
+
+    /// Returns the piece on each square.
+    pub fn mailbox(&self) -> &Mailbox {
+        &self.mailbox
+    }
+
+    /// Returns the Zobrist key of the pawns alone.
+    ///
+    /// The key is the same for any two positions with the same pawn structure, whatever
+    /// the other pieces.
+    pub fn pawn_key(&self) -> u64 {
+        self.pawn_key
+    }
+
+    /// Returns true if the mailbox and the pawn key match the player boards.
+    pub fn is_mailbox_consistent(&self) -> bool {
+        self.mailbox == Mailbox::from_boards(&self.white_board, &self.black_board)
+            && self.pawn_key == zobrist::pawn_key(&self.white_board, &self.black_board)
+    }
+
     /// Returns the player board of the given player.
     pub fn player_board(&self, player: Player) -> PlayerBoard {
         match player {
@@ -63,6 +138,52 @@ impl GameState {
         }
     }
 
+    /// Returns the squares occupied by the pieces of either player.
+    pub fn occupied(&self) -> BitBoard {
+        self.white_board.all() | self.black_board.all()
+    }
+
+    /// Places a piece of the given player on the given square, which must be empty.
+    pub fn put_piece(&mut self, square: Square, piece: Piece, player: Player) {
+        debug_assert!(self.piece_at(square).is_none());
+        let board = self.player_board_mut(player);
+        *board = board.with_piece(piece, board.piece(piece).set_square(square));
+        self.mailbox.set(square, Some((piece, player)));
+        if piece == Piece::Pawn {
+            self.pawn_key ^= zobrist::pawn_square_key(square, player);
+        }
+    }
+
+    /// Removes the piece on the given square, returning it and its owner, if any.
+    pub fn remove_piece(&mut self, square: Square) -> Option<(Piece, Player)> {
+        let removed = self.mailbox.get(square);
+        if let Some((piece, player)) = removed {
+            let board = self.player_board_mut(player);
+            *board = board.with_piece(piece, board.piece(piece).unset_square(square));
+            self.mailbox.set(square, None);
+            if piece == Piece::Pawn {
+                self.pawn_key ^= zobrist::pawn_square_key(square, player);
+            }
+        }
+        removed
+    }
+
+    /// Replaces both player boards.
+    pub fn set_boards(&mut self, white_board: PlayerBoard, black_board: PlayerBoard) {
+        self.white_board = white_board;
+        self.black_board = black_board;
+        self.mailbox = Mailbox::from_boards(&white_board, &black_board);
+        self.pawn_key = zobrist::pawn_key(&white_board, &black_board);
+    }
+
+    // returns a mutable reference to the player board of the given player
+    fn player_board_mut(&mut self, player: Player) -> &mut PlayerBoard {
+        match player {
+            Player::White => &mut self.white_board,
+            Player::Black => &mut self.black_board,
+        }
+    }
+
     /// Returns the castle rights of the given player.
     pub fn castle_rights(&self, player: Player) -> CastleRights {
         match player {
@@ -78,6 +199,131 @@ impl GameState {
             Player::Black => self.black_castle_rights = castle_rights,
         };
     }
+
+    /// Returns the number of checks given by the given player.
+    pub fn checks(&self, player: Player) -> u8 {
+        match player {
+            Player::White => self.white_checks,
+            Player::Black => self.black_checks,
+        }
+    }
+
+    /// Sets the number of checks given by the given player.
+    pub fn set_checks(&mut self, player: Player, checks: u8) {
+        match player {
+            Player::White => self.white_checks = checks,
+            Player::Black => self.black_checks = checks,
+        };
+    }
+
+    /// Returns the pieces held in hand by the given player.
+    pub fn pocket(&self, player: Player) -> Pocket {
+        match player {
+            Player::White => self.white_pocket,
+            Player::Black => self.black_pocket,
+        }
+    }
+
+    /// Returns a mutable reference to the pieces held in hand by the given player.
+    pub fn pocket_mut(&mut self, player: Player) -> &mut Pocket {
+        match player {
+            Player::White => &mut self.white_pocket,
+            Player::Black => &mut self.black_pocket,
+        }
+    }
+
+    // --------------------------
+    /// Returns the state with the given white player board.
+    pub fn with_white_board(mut self, white_board: PlayerBoard) -> Self {
+        let black_board = self.black_board;
+        self.set_boards(white_board, black_board);
+        self
+    }
+
+    /// Returns the state with the given black player board.
+    pub fn with_black_board(mut self, black_board: PlayerBoard) -> Self {
+        let white_board = self.white_board;
+        self.set_boards(white_board, black_board);
+        self
+    }
+
+    /// Returns the state with the given player turn.
+    pub fn with_player_turn(mut self, player_turn: Player) -> Self {
+        self.player_turn = player_turn;
+        self
+    }
+
+    /// Returns the state with the given en-passant square.
+    pub fn with_en_passant(mut self, en_passant: Option<Square>) -> Self {
+        self.en_passant = en_passant;
+        self
+    }
+
+    /// Returns the state with the given white castle rights.
+    pub fn with_white_castle_rights(mut self, castle_rights: CastleRights) -> Self {
+        self.white_castle_rights = castle_rights;
+        self
+    }
+
+    /// Returns the state with the given black castle rights.
+    pub fn with_black_castle_rights(mut self, castle_rights: CastleRights) -> Self {
+        self.black_castle_rights = castle_rights;
+        self
+    }
+
+    /// Returns the state with the given castle rook files.
+    pub fn with_castle_files(mut self, castle_files: CastleFiles) -> Self {
+        self.castle_files = castle_files;
+        self
+    }
+
+    /// Returns the state with the given draw plies count.
+    pub fn with_draw_plies(mut self, draw_plies: u8) -> Self {
+        self.draw_plies = draw_plies;
+        self
+    }
+
+    /// Returns the state with the given full turns count.
+    pub fn with_full_turns(mut self, full_turns: u8) -> Self {
+        self.full_turns = full_turns;
+        self
+    }
+
+    /// Returns the state with the given variant.
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Returns the state with the given number of checks given by white.
+    pub fn with_white_checks(mut self, white_checks: u8) -> Self {
+        self.white_checks = white_checks;
+        self
+    }
+
+    /// Returns the state with the given number of checks given by black.
+    pub fn with_black_checks(mut self, black_checks: u8) -> Self {
+        self.black_checks = black_checks;
+        self
+    }
+
+    /// Returns the state with the given pieces held by white.
+    pub fn with_white_pocket(mut self, white_pocket: Pocket) -> Self {
+        self.white_pocket = white_pocket;
+        self
+    }
+
+    /// Returns the state with the given pieces held by black.
+    pub fn with_black_pocket(mut self, black_pocket: Pocket) -> Self {
+        self.black_pocket = black_pocket;
+        self
+    }
+
+    /// Returns the state with the given promoted piece squares.
+    pub fn with_promoted(mut self, promoted: BitBoard) -> Self {
+        self.promoted = promoted;
+        self
+    }
 }
 
 impl Default for GameState {
@@ -89,12 +335,36 @@ impl Default for GameState {
             en_passant: None,
             white_castle_rights: CastleRights::None,
             black_castle_rights: CastleRights::None,
+            castle_files: CastleFiles::default(),
             draw_plies: 0,
             full_turns: 0,
+            variant: Variant::Standard,
+            white_checks: 0,
+            black_checks: 0,
+            white_pocket: Pocket::new(),
+            black_pocket: Pocket::new(),
+            promoted: BitBoard::empty(),
+            mailbox: Mailbox::new(),
+            pawn_key: 0,
         }
     }
 }
 
+impl PartialEq for GameState {
+    fn eq(&self, other: &Self) -> bool {
+        self.white_board == other.white_board && self.black_board == other.black_board
+            && self.player_turn == other.player_turn && self.en_passant == other.en_passant
+            && self.white_castle_rights == other.white_castle_rights
+            && self.black_castle_rights == other.black_castle_rights
+            && self.castle_files == other.castle_files
+            && self.draw_plies == other.draw_plies && self.full_turns == other.full_turns
+            && self.variant == other.variant && self.white_checks == other.white_checks
+            && self.black_checks == other.black_checks
+            && self.white_pocket == other.white_pocket && self.black_pocket == other.black_pocket
+            && self.promoted == other.promoted
+    }
+}
+
 //---------------------------------------------------------------------------
 // Display
 impl Display for GameState {
@@ -129,33 +399,8 @@ impl Display for GameState {
                     .and_then(|sq| if sq == square { Some(sq) } else { None })
                     .map(|_| 'e')
                     .or_else(|| {
-                        if self.white_board.pawns.is_square_set(square) {
-                            Some((Piece::Pawn, Player::White))
-                        } else if self.white_board.rooks.is_square_set(square) {
-                            Some((Piece::Rook, Player::White))
-                        } else if self.white_board.knights.is_square_set(square) {
-                            Some((Piece::Knight, Player::White))
-                        } else if self.white_board.bishops.is_square_set(square) {
-                            Some((Piece::Bishop, Player::White))
-                        } else if self.white_board.queens.is_square_set(square) {
-                            Some((Piece::Queen, Player::White))
-                        } else if self.white_board.king.is_square_set(square) {
-                            Some((Piece::King, Player::White))
-                        } else if self.black_board.pawns.is_square_set(square) {
-                            Some((Piece::Pawn, Player::Black))
-                        } else if self.black_board.rooks.is_square_set(square) {
-                            Some((Piece::Rook, Player::Black))
-                        } else if self.black_board.knights.is_square_set(square) {
-                            Some((Piece::Knight, Player::Black))
-                        } else if self.black_board.bishops.is_square_set(square) {
-                            Some((Piece::Bishop, Player::Black))
-                        } else if self.black_board.queens.is_square_set(square) {
-                            Some((Piece::Queen, Player::Black))
-                        } else if self.black_board.king.is_square_set(square) {
-                            Some((Piece::King, Player::Black))
-                        } else {
-                            None
-                        }.map(|(piece, player)| piece_char(piece, player))
+                        self.piece_at(square)
+                            .map(|(piece, player)| piece_char(piece, player))
                     })
                     .unwrap_or(' ');
                 str.push(char);
@@ -1,19 +1,29 @@
 //! Generates legal moves.
 
 use board::bitboard::BitBoard;
-use board::file::File;
 use board::piece::Piece;
 use board::player::Player;
 use board::rank::Rank;
 use board::square::Square;
-use rules::castle_rights::CastleRights;
 use rules::game_state::GameState;
 use rules::move_application::{CastleMove, Move};
+use rules::variant::Variant;
 use std::iter;
 
 impl GameState {
     /// Returns a list of legal moves for the state.
+    ///
+    /// There are no legal moves once the game has ended through a rule of the variant.
     pub fn legal_moves(&self) -> Vec<Move> {
+        if self.variant_outcome().is_some() {
+            return vec![];
+        }
+        self.generate_legal_moves()
+    }
+
+    /// Returns a list of legal moves for the state, regardless of whether the game has
+    /// ended through a rule of the variant.
+    pub fn generate_legal_moves(&self) -> Vec<Move> {
         let mut moves = vec![];
         let own_board = self.player_board(self.player_turn);
         let opponent_board = self.player_board(self.player_turn.other());
@@ -38,6 +48,7 @@ impl GameState {
                             en_passant: false,
                             promotion: None,
                             castle: None,
+                            drop: false,
                         })
                 })
             })
@@ -49,12 +60,18 @@ impl GameState {
             Player::White => Rank::Eight,
             Player::Black => Rank::One,
         };
+        let king_promotion = self.variant == Variant::Antichess;
 
         // TODO: This is a bit of a shame - couldn't get it working otherwise though
         // (maybe impl traits will fix this?)
-        fn process_pawn_moves(mv: Move, last_rank: Rank) -> Box<Iterator<Item = Move>> {
+        fn process_pawn_moves(
+            mv: Move,
+            last_rank: Rank,
+            king_promotion: bool,
+        ) -> Box<Iterator<Item = Move>> {
             if mv.target.rank() == last_rank {
-                let iter = Piece::iter_pieces().map(move |piece| Move {
+                let kings = if king_promotion { Some(Piece::King) } else { None };
+                let iter = Piece::iter_pieces().chain(kings).map(move |piece| Move {
                     promotion: Some(piece),
                     ..mv
                 });
@@ -84,9 +101,10 @@ impl GameState {
                         en_passant: false,
                         promotion: None,
                         castle: None,
+                        drop: false,
                     })
             })
-            .flat_map(move |mv| process_pawn_moves(mv, last_rank))
+            .flat_map(move |mv| process_pawn_moves(mv, last_rank, king_promotion))
             .collect();
 
         moves.append(&mut pawn_captures);
@@ -111,6 +129,7 @@ impl GameState {
                             en_passant: false,
                             promotion: None,
                             castle: None,
+                            drop: false,
                         })
                 })
             })
@@ -137,13 +156,26 @@ impl GameState {
                         en_passant: false,
                         promotion: None,
                         castle: None,
+                        drop: false,
                     })
             })
-            .flat_map(move |mv| process_pawn_moves(mv, last_rank))
+            .flat_map(move |mv| process_pawn_moves(mv, last_rank, king_promotion))
             .collect();
 
         moves.append(&mut pawn_moves);
 
+        // in horde, white pawns on the first rank may also move two squares
+        moves.extend(self.horde_double_pushes().iter().map(|origin| Move {
+            piece: Piece::Pawn,
+            origin,
+            target: Square::from_coordinates(origin.file(), Rank::Three),
+            capture: false,
+            en_passant: false,
+            promotion: None,
+            castle: None,
+            drop: false,
+        }));
+
         if let Some(en_passant_target) = self.en_passant {
             let origin_rank = match self.player_turn {
                 Player::White => Rank::Five,
@@ -161,6 +193,7 @@ impl GameState {
                         en_passant: true,
                         promotion: None,
                         castle: None,
+                        drop: false,
                     })
                 }
             }
@@ -176,106 +209,108 @@ impl GameState {
                         en_passant: true,
                         promotion: None,
                         castle: None,
+                        drop: false,
                     })
                 }
             }
         }
 
         let castle_rights = self.castle_rights(self.player_turn);
-
-        if castle_rights == CastleRights::None {
-            let all_attacks = Piece::iter()
-                .map(|piece| {
-                    let piece_board = opponent_board.piece(piece);
-                    piece_board
-                        .iter()
-                        .map(move |square| {
-                            piece.attacks(
-                                square,
-                                self.player_turn,
-                                own_board.all(),
-                                opponent_board.all(),
-                            )
-                        })
-                        .fold(BitBoard::empty(), |a, b| a ^ b)
+        let castle_moves = [
+            (CastleMove::KingSide, castle_rights.is_king_side_available()),
+            (CastleMove::QueenSide, castle_rights.is_queen_side_available()),
+        ];
+        for &(castle_move, available) in castle_moves.iter() {
+            // the king may not castle out of, through or into check, nor castle at all in
+            // antichess
+            if available && self.variant != Variant::Antichess
+                && self.is_castle_path_clear(castle_move)
+                && !self.castle_king_path(castle_move)
+                    .iter()
+                    .any(|square| self.is_attacked(square, self.player_turn.other()))
+            {
+                moves.push(Move {
+                    piece: Piece::King,
+                    origin: own_board.king.to_square(),
+                    target: self.castle_king_square(castle_move, self.player_turn),
+                    capture: false,
+                    en_passant: false,
+                    promotion: None,
+                    castle: Some(castle_move),
+                    drop: false,
                 })
-                .fold(BitBoard::empty(), |a, b| a ^ b);
-
-            // TODO const these bitboards.
-            // TODO names are awful here
-            // prevent castling if king movement squares are being attacked
-            let king_check_blockers = match self.player_turn {
-                Player::White => BitBoard::empty()
-                    .set_coordinate(File::E, Rank::One)
-                    .set_coordinate(File::F, Rank::One)
-                    .set_coordinate(File::G, Rank::One),
-                Player::Black => BitBoard::empty()
-                    .set_coordinate(File::E, Rank::Eight)
-                    .set_coordinate(File::F, Rank::Eight)
-                    .set_coordinate(File::G, Rank::Eight),
-            };
-
-            let queen_check_blockers = match self.player_turn {
-                Player::White => BitBoard::empty()
-                    .set_coordinate(File::E, Rank::One)
-                    .set_coordinate(File::D, Rank::One)
-                    .set_coordinate(File::C, Rank::One),
-                Player::Black => BitBoard::empty()
-                    .set_coordinate(File::E, Rank::Eight)
-                    .set_coordinate(File::D, Rank::Eight)
-                    .set_coordinate(File::C, Rank::Eight),
-            };
-
-            let can_ks_castle = (king_check_blockers & all_attacks).is_empty();
-            let can_qs_castle = (queen_check_blockers & all_attacks).is_empty();
+            }
+        }
 
-            if castle_rights.is_king_side_available() && can_ks_castle {
-                let king_rank = match self.player_turn {
-                    Player::White => Rank::One,
-                    Player::Black => Rank::Eight,
+        // held pieces may be dropped on any empty square, except pawns on the back ranks
+        if self.variant == Variant::Crazyhouse {
+            let empty = !(own_board.all() | opponent_board.all());
+            let pocket = self.pocket(self.player_turn);
+            for piece in Piece::iter().filter(|&piece| pocket.count(piece) > 0) {
+                let targets = match piece {
+                    Piece::Pawn => empty & !(Rank::One.to_bitboard() | Rank::Eight.to_bitboard()),
+                    _ => empty,
                 };
-                let all = opponent_board.all() | own_board.all();
-                if !all.is_square_set(Square::from_coordinates(File::G, king_rank))
-                    && !all.is_square_set(Square::from_coordinates(File::F, king_rank))
-                {
-                    moves.push(Move {
-                        piece: Piece::King,
-                        origin: Square::from_coordinates(File::E, king_rank),
-                        target: Square::from_coordinates(File::G, king_rank),
-                        capture: false,
-                        en_passant: false,
-                        promotion: None,
-                        castle: Some(CastleMove::KingSide),
-                    })
-                }
+                moves.extend(targets.iter().map(|target| Move {
+                    piece,
+                    origin: target,
+                    target,
+                    capture: false,
+                    en_passant: false,
+                    promotion: None,
+                    castle: None,
+                    drop: true,
+                }));
             }
+        }
 
-            if castle_rights.is_queen_side_available() && can_qs_castle {
-                let king_rank = match self.player_turn {
-                    Player::White => Rank::One,
-                    Player::Black => Rank::Eight,
-                };
-                let all = opponent_board.all() | own_board.all();
-                if !all.is_square_set(Square::from_coordinates(File::B, king_rank))
-                    && !all.is_square_set(Square::from_coordinates(File::C, king_rank))
-                    && !all.is_square_set(Square::from_coordinates(File::D, king_rank))
-                {
-                    moves.push(Move {
-                        piece: Piece::King,
-                        origin: Square::from_coordinates(File::E, king_rank),
-                        target: Square::from_coordinates(File::C, king_rank),
-                        capture: false,
-                        en_passant: false,
-                        promotion: None,
-                        castle: Some(CastleMove::QueenSide),
-                    })
-                }
+        // in antichess there is no check, but captures are compulsory
+        if self.variant == Variant::Antichess {
+            if moves.iter().any(|mv| mv.capture) {
+                moves.retain(|mv| mv.capture);
             }
+            return moves;
         }
 
+        let player = self.player_turn;
+        let mut state = self.clone();
         moves
             .into_iter()
-            .filter(|mv| !self.apply_move(mv).is_check(self.player_turn))
+            .filter(|mv| {
+                // in atomic, kings can't capture as they would explode themselves
+                if self.variant == Variant::Atomic && mv.piece == Piece::King && mv.capture {
+                    return false;
+                }
+                let undo = state.make_move(mv);
+                let legal = match self.variant {
+                    // exploding the opponent's king wins, even when in check, but exploding
+                    // one's own king is never allowed
+                    Variant::Atomic => {
+                        !state.player_board(player).king.is_empty()
+                            && (state.player_board(player.other()).king.is_empty()
+                                || !state.is_check(player))
+                    }
+                    // in racing kings, giving check is not allowed either
+                    Variant::RacingKings => {
+                        !state.is_check(player) && !state.is_check(player.other())
+                    }
+                    _ => !state.is_check(player),
+                };
+                state.unmake_move(mv, &undo);
+                legal
+            })
             .collect::<Vec<Move>>()
     }
+
+    /// Returns the white pawns on the first rank which may move two squares in Horde.
+    ///
+    /// Such moves do not allow an en passant capture.
+    pub fn horde_double_pushes(&self) -> BitBoard {
+        if self.variant != Variant::Horde || self.player_turn != Player::White {
+            return BitBoard::empty();
+        }
+        let empty = !self.occupied();
+        let pawns = self.player_board(Player::White).pawns;
+        pawns & Rank::One.to_bitboard() & (empty >> 8) & (empty >> 16)
+    }
 }
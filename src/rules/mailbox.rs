@@ -0,0 +1,49 @@
+//! Lookup of the piece on a given square.
+
+use board::piece::Piece;
+use board::player::Player;
+use board::square::Square;
+use rules::player_board::PlayerBoard;
+
+/// A square-indexed array of the pieces on the board.
+///
+/// This duplicates the information in the player boards, but allows the piece on a
+/// given square to be found without testing every bitboard.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Mailbox([Option<(Piece, Player)>; 64]);
+
+impl Mailbox {
+    /// Returns a new, empty instance.
+    pub fn new() -> Self {
+        Mailbox([None; 64])
+    }
+
+    /// Creates a new instance from the given player boards.
+    pub fn from_boards(white_board: &PlayerBoard, black_board: &PlayerBoard) -> Self {
+        let mut mailbox = Mailbox::new();
+        for (board, player) in [(white_board, Player::White), (black_board, Player::Black)].iter() {
+            for piece in Piece::iter() {
+                for square in board.piece(piece).iter() {
+                    mailbox.set(square, Some((piece, *player)));
+                }
+            }
+        }
+        mailbox
+    }
+
+    /// Returns the piece and its owner on the given square, if any.
+    pub fn get(&self, square: Square) -> Option<(Piece, Player)> {
+        self.0[square.to_index() as usize]
+    }
+
+    /// Sets the contents of the given square.
+    pub fn set(&mut self, square: Square, value: Option<(Piece, Player)>) {
+        self.0[square.to_index() as usize] = value;
+    }
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Mailbox::new()
+    }
+}
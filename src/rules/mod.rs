@@ -12,16 +12,23 @@
 //! * Checkmates
 //! * Stalemate
 //! * Draws via the 50-move rule
+//! * Variants, such as King of the Hill, Three-check and Crazyhouse
 
 pub mod basic_moves;
 pub mod castle_rights;
+pub mod chess960;
 pub mod check_detection;
 pub mod fen_parser;
 pub mod game_state;
 pub mod legal_moves;
+pub mod mailbox;
 pub mod move_application;
+pub mod perft;
 pub mod player_board;
+pub mod pocket;
 pub mod semilegal_moves;
+pub mod variant;
+pub mod zobrist;
 
 #[cfg(test)]
 mod test;
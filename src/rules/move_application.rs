@@ -1,12 +1,17 @@
 //! A movement of a piece
 
+use board::bitboard::BitBoard;
 use board::file::File;
 use board::piece::Piece;
 use board::player::Player;
 use board::rank::Rank;
 use board::square::Square;
+use rules::basic_moves;
 use rules::castle_rights::CastleRights;
 use rules::game_state::GameState;
+use rules::player_board::PlayerBoard;
+use rules::pocket::Pocket;
+use rules::variant::Variant;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::result::Result;
 
@@ -27,7 +32,7 @@ impl Display for CastleMove {
 }
 
 /// A movement of a piece (a 'move')
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Move {
     /// The type of the piece which moved, king if castle.
     pub piece: Piece,
@@ -49,6 +54,10 @@ pub struct Move {
 
     /// Present and contains the castle direction if the move was a castle.
     pub castle: Option<CastleMove>,
+
+    /// True if the move was a drop of a piece held in hand, in which case the origin is
+    /// the same as the target.
+    pub drop: bool,
 }
 
 impl Display for Move {
@@ -61,6 +70,14 @@ impl Display for Move {
             Piece::Queen => "Q",
             Piece::King => "K",
         };
+        if self.drop {
+            return write!(
+                f,
+                "{}@{}",
+                if piece.is_empty() { "P" } else { piece },
+                self.target
+            );
+        }
         write!(
             f,
             "{}: {} {} {}",
@@ -72,10 +89,95 @@ impl Display for Move {
     }
 }
 
+/// The state lost when applying a move, used to revert it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MoveUndo {
+    /// The type of the piece captured by the move, if any.
+    pub captured: Option<Piece>,
+
+    /// White players castle rights before the move.
+    pub white_castle_rights: CastleRights,
+
+    /// Black players castle rights before the move.
+    pub black_castle_rights: CastleRights,
+
+    /// The en-passant square before the move.
+    pub en_passant: Option<Square>,
+
+    /// The draw plies count before the move.
+    pub draw_plies: u8,
+
+    /// The number of checks given by the moving player before the move.
+    pub checks: u8,
+
+    /// The pieces held by the moving player before the move.
+    pub pocket: Pocket,
+
+    /// The promoted piece squares before the move.
+    pub promoted: BitBoard,
+
+    /// The white and black player boards before the move, if it caused an explosion.
+    pub explosion: Option<(PlayerBoard, PlayerBoard)>,
+
+    /// The pieces added to and removed from the board by the move.
+    pub changes: PieceChanges,
+}
+
+/// A piece added to or removed from a square of the board.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PieceChange {
+    /// The square of the piece.
+    pub square: Square,
+
+    /// The type of the piece.
+    pub piece: Piece,
+
+    /// The player owning the piece.
+    pub player: Player,
+
+    /// Whether the piece was added to the square, rather than removed from it.
+    pub added: bool,
+}
+
+// the most changes a single move makes: an atomic capture removes the captured and
+// moving pieces and places the moving piece, which then explodes with its neighbours
+const MAX_CHANGES: usize = 12;
+
+/// The pieces added to and removed from the board by a move, in the order they changed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PieceChanges {
+    changes: [Option<PieceChange>; MAX_CHANGES],
+    len: usize,
+}
+
+impl PieceChanges {
+    /// Returns an iterator over the changes.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = PieceChange> + 'a {
+        self.changes[..self.len].iter().filter_map(|change| *change)
+    }
+
+    // records a change
+    fn push(&mut self, change: PieceChange) {
+        self.changes[self.len] = Some(change);
+        self.len += 1;
+    }
+}
+
 impl GameState {
     /// Apply a given move to the board.
     ///
-    /// Returns a game state modified based on the move provided
+    /// Returns a game state modified based on the move provided. This is a convenience
+    /// wrapper around [GameState::make_move] which leaves the original state untouched.
+    ///
+    /// Invalid game states or moves will give undefined behaviour.
+    pub fn apply_move(&self, mv: &Move) -> Self {
+        let mut new_state = self.clone();
+        new_state.make_move(mv);
+        new_state
+    }
+
+    /// Applies a given move to the board in place.
+    ///
     /// - If the move is a capture, removes the opponent piece and moves the
     ///   capturing piece to the captured square.
     /// - If the move is just a movement, the piece is simply moved.
@@ -92,77 +194,178 @@ impl GameState {
     /// - Increases the draw plies count if necessary.
     /// - Updates the castle rights if a rook or king move
     ///
+    /// Returns the record required to revert the move with [GameState::unmake_move].
+    ///
     /// Invalid game states or moves will give undefined behaviour.
-    pub fn apply_move(&self, mv: &Move) -> Self {
+    pub fn make_move(&mut self, mv: &Move) -> MoveUndo {
         debug_assert!({
-            let result = self.validate(&mv);
+            let result = self.validate(mv);
             if let Err(ref message) = result {
                 println!("{}", message);
             }
             result.is_ok()
         });
 
-        let mut new_state = self.clone();
-        new_state.en_passant = None;
+        let player = self.player_turn;
+        let mut undo = MoveUndo {
+            captured: self.captured_piece(mv),
+            white_castle_rights: self.white_castle_rights,
+            black_castle_rights: self.black_castle_rights,
+            en_passant: self.en_passant,
+            draw_plies: self.draw_plies,
+            checks: self.checks(self.player_turn),
+            pocket: self.pocket(self.player_turn),
+            promoted: self.promoted,
+            explosion: if self.variant == Variant::Atomic && mv.capture {
+                Some((
+                    self.player_board(Player::White),
+                    self.player_board(Player::Black),
+                ))
+            } else {
+                None
+            },
+            changes: PieceChanges::default(),
+        };
 
+        self.en_passant = None;
+
+        // Drops
+        if mv.drop {
+            self.apply_drop(mv, &mut undo.changes);
+        }
         // Castling
-        if let Some(castle_move) = mv.castle {
-            new_state.apply_castle(castle_move);
+        else if let Some(castle_move) = mv.castle {
+            self.apply_castle(castle_move, &mut undo.changes);
         }
         // Promotion
         else if mv.promotion.is_some() {
-            new_state.apply_promotion(&mv);
+            self.apply_promotion(mv, &mut undo.changes);
         }
         // All other non-capture moves
         else if !mv.capture {
-            new_state.apply_non_capture(&mv);
+            self.apply_non_capture(mv, &mut undo.changes);
         }
         // En passant
         else if mv.en_passant {
-            new_state.apply_en_passant(&mv);
+            self.apply_en_passant(mv, &mut undo.changes);
         }
         // All other capture moves
         else {
-            new_state.apply_capture(mv);
+            self.apply_capture(mv, &mut undo.changes);
         }
 
-        if self.player_turn == Player::Black {
-            new_state.full_turns += 1;
+        if player == Player::Black {
+            self.full_turns += 1;
         }
 
-        new_state.player_turn = match self.player_turn {
-            Player::Black => Player::White,
-            Player::White => Player::Black,
-        };
+        self.player_turn = player.other();
 
         if mv.capture || mv.piece == Piece::Pawn {
-            new_state.draw_plies = 0;
+            self.draw_plies = 0;
         } else {
-            new_state.draw_plies += 1;
+            self.draw_plies += 1;
         }
 
         if mv.piece == Piece::King {
-            new_state.set_castle_rights(self.player_turn, CastleRights::None);
+            self.set_castle_rights(player, CastleRights::None);
         } else if mv.piece == Piece::Rook {
-            let castle_rights = self.castle_rights(self.player_turn);
-            if castle_rights.is_king_side_available() && mv.origin.file() == File::H {
-                new_state.set_castle_rights(self.player_turn, castle_rights.without_king_side());
-            } else if castle_rights.is_queen_side_available() && mv.origin.file() == File::A {
-                new_state.set_castle_rights(self.player_turn, castle_rights.without_queen_side());
-            }
+            self.remove_rook_castle_rights(player, mv.origin);
         }
 
-        new_state
+        if undo.captured == Some(Piece::Rook) {
+            self.remove_rook_castle_rights(player.other(), mv.target);
+        }
+
+        if self.variant == Variant::Crazyhouse {
+            self.update_pockets(mv, undo.captured);
+        }
+
+        if undo.explosion.is_some() {
+            self.explode(mv.target, &mut undo.changes);
+        }
+
+        if self.variant == Variant::ThreeCheck && self.is_check(player.other()) {
+            self.set_checks(player, undo.checks + 1);
+        }
+
+        debug_assert!(self.is_mailbox_consistent());
+        undo
+    }
+
+    /// Reverts a move previously applied with [GameState::make_move].
+    ///
+    /// The move and undo record must be those of the last move made on the state,
+    /// otherwise the behaviour is undefined.
+    pub fn unmake_move(&mut self, mv: &Move, undo: &MoveUndo) {
+        let player = self.player_turn.other();
+
+        self.player_turn = player;
+        if player == Player::Black {
+            self.full_turns -= 1;
+        }
+        self.white_castle_rights = undo.white_castle_rights;
+        self.black_castle_rights = undo.black_castle_rights;
+        self.en_passant = undo.en_passant;
+        self.draw_plies = undo.draw_plies;
+        self.set_checks(player, undo.checks);
+        *self.pocket_mut(player) = undo.pocket;
+        self.promoted = undo.promoted;
+
+        if let Some((white_board, black_board)) = undo.explosion {
+            self.set_boards(white_board, black_board);
+            return;
+        }
+
+        if mv.drop {
+            self.remove_piece(mv.target);
+            debug_assert!(self.is_mailbox_consistent());
+            return;
+        }
+
+        if let Some(castle_move) = mv.castle {
+            let (rook_origin, rook_target) = self.castle_rook_squares(castle_move, player);
+            self.remove_piece(self.castle_king_square(castle_move, player));
+            self.remove_piece(rook_target);
+            self.put_piece(mv.origin, Piece::King, player);
+            self.put_piece(rook_origin, Piece::Rook, player);
+        } else {
+            self.remove_piece(mv.target);
+            self.put_piece(mv.origin, mv.piece, player);
+        }
+
+        if let Some(captured) = undo.captured {
+            let square = if mv.en_passant {
+                en_passant_capture_square(mv.target, player)
+            } else {
+                mv.target
+            };
+            self.put_piece(square, captured, player.other());
+        }
+
+        debug_assert!(self.is_mailbox_consistent());
+    }
+
+    // returns the type of the piece the move captures, if any
+    fn captured_piece(&self, mv: &Move) -> Option<Piece> {
+        if !mv.capture {
+            None
+        } else if mv.en_passant {
+            Some(Piece::Pawn)
+        } else {
+            self.piece_at(mv.target).map(|(piece, _)| piece)
+        }
     }
 
     // ----------------------------------------------------------------
     // returns None if the move and board are valid, or an error
     fn validate(&self, mv: &Move) -> Result<(), String> {
+        if mv.drop {
+            return self.validate_drop(mv);
+        }
+
         // valid origin
-        if !match self.player_turn {
-            Player::White => self.white_board.piece(mv.piece).is_square_set(mv.origin),
-            Player::Black => self.black_board.piece(mv.piece).is_square_set(mv.origin),
-        } {
+        let own_pieces = self.player_board(self.player_turn).piece(mv.piece);
+        if !own_pieces.is_square_set(mv.origin) {
             return Err(format!(
                 "The moved {} from square {} for player {} was not in place",
                 mv.piece, mv.origin, self.player_turn
@@ -171,17 +374,15 @@ impl GameState {
 
         // validate target - en passant doesn't capture where a piece is
         if mv.capture && !mv.en_passant {
-            if !match self.player_turn {
-                Player::White => self.black_board.all().is_square_set(mv.target),
-                Player::Black => self.white_board.all().is_square_set(mv.target),
-            } {
+            let opponent_pieces = self.player_board(self.player_turn.other()).all();
+            if !opponent_pieces.is_square_set(mv.target) {
                 return Err(format!(
                     "The captured piece from square {} for player {} was not in place",
                     mv.target, self.player_turn
                 ));
             }
-        } else {
-            let all_pieces = self.black_board.all() | self.white_board.all();
+        } else if mv.castle.is_none() {
+            let all_pieces = self.occupied();
             if all_pieces.is_square_set(mv.target) {
                 return Err(format!(
                     "The target square {} for player {} isn't empty",
@@ -223,33 +424,11 @@ impl GameState {
                 ));
             }
 
-            let all = self.black_board.all() | self.white_board.all();
-            let king_rank = match self.player_turn {
-                Player::White => Rank::One,
-                Player::Black => Rank::Eight,
-            };
-
-            if castle_move == CastleMove::KingSide {
-                if all.is_square_set(Square::from_coordinates(File::G, king_rank))
-                    || all.is_square_set(Square::from_coordinates(File::F, king_rank))
-                {
-                    return Err(format!(
-                        "{} is invalid as there are pieces in the way",
-                        castle_move
-                    ));
-                }
-            }
-
-            if castle_move == CastleMove::QueenSide {
-                if all.is_square_set(Square::from_coordinates(File::B, king_rank))
-                    || all.is_square_set(Square::from_coordinates(File::C, king_rank))
-                    || all.is_square_set(Square::from_coordinates(File::D, king_rank))
-                {
-                    return Err(format!(
-                        "{} is invalid as there are pieces in the way",
-                        castle_move
-                    ));
-                }
+            if !self.is_castle_path_clear(castle_move) {
+                return Err(format!(
+                    "{} is invalid as there are pieces in the way",
+                    castle_move
+                ));
             }
         }
 
@@ -298,9 +477,12 @@ impl GameState {
                 ));
             }
         } else if mv.castle.is_none() {
-            let blockers = self.white_board.all() | self.black_board.all();
+            let blockers = self.occupied();
             let valid_moves = mv.piece.moves(mv.origin, self.player_turn, blockers);
-            if !valid_moves.is_square_set(mv.target) {
+            let horde_push = mv.piece == Piece::Pawn
+                && self.horde_double_pushes().is_square_set(mv.origin)
+                && mv.target == Square::from_coordinates(mv.origin.file(), Rank::Three);
+            if !valid_moves.is_square_set(mv.target) && !horde_push {
                 return Err(format!(
                     "{} move from {} to {} is not valid",
                     mv.piece, mv.origin, mv.target
@@ -311,102 +493,138 @@ impl GameState {
         Ok(())
     }
 
+    // returns None if the drop move and board are valid, or an error
+    fn validate_drop(&self, mv: &Move) -> Result<(), String> {
+        if self.pocket(self.player_turn).count(mv.piece) == 0 {
+            return Err(format!(
+                "The dropped {} is not held by player {}",
+                mv.piece, self.player_turn
+            ));
+        }
+
+        if mv.origin != mv.target
+            || mv.capture
+            || mv.en_passant
+            || mv.promotion.is_some()
+            || mv.castle.is_some()
+        {
+            return Err(format!(
+                "Drop of {} on {} is malformed",
+                mv.piece, mv.target
+            ));
+        }
+
+        if self.piece_at(mv.target).is_some() {
+            return Err(format!(
+                "The target square {} for player {} isn't empty",
+                mv.target, self.player_turn
+            ));
+        }
+
+        if mv.piece == Piece::Pawn
+            && (mv.target.rank() == Rank::Eight || mv.target.rank() == Rank::One)
+        {
+            return Err(format!("Cannot drop a pawn on {}", mv.target));
+        }
+
+        Ok(())
+    }
+
+    // takes captured pieces in hand, as pawns if they were promoted, and moves the
+    // promoted marker with the moved piece
+    fn update_pockets(&mut self, mv: &Move, captured: Option<Piece>) {
+        let player = self.player_turn.other();
+        if let Some(captured) = captured {
+            let square = if mv.en_passant {
+                en_passant_capture_square(mv.target, player)
+            } else {
+                mv.target
+            };
+            let held = if self.promoted.is_square_set(square) {
+                Piece::Pawn
+            } else {
+                captured
+            };
+            self.pocket_mut(player).add(held);
+            self.promoted = self.promoted.unset_square(square);
+        }
+        if mv.promotion.is_some() || self.promoted.is_square_set(mv.origin) {
+            self.promoted = self.promoted.unset_square(mv.origin).set_square(mv.target);
+        }
+    }
+
+    // removes the piece on the target square and all non-pawn pieces around it, after an
+    // atomic capture
+    fn explode(&mut self, target: Square, changes: &mut PieceChanges) {
+        for square in (basic_moves::king(target) | target.to_bitboard()).iter() {
+            let (piece, player) = match self.piece_at(square) {
+                Some((Piece::Pawn, _)) if square != target => continue,
+                Some(occupant) => occupant,
+                None => continue,
+            };
+            self.take_piece(changes, square);
+
+            match piece {
+                Piece::King => self.set_castle_rights(player, CastleRights::None),
+                Piece::Rook => self.remove_rook_castle_rights(player, square),
+                _ => {}
+            }
+        }
+    }
+
     // ----------------------------------------------------------------
+    /// Applies a drop of a piece held in hand to the state.
+    fn apply_drop(&mut self, mv: &Move, changes: &mut PieceChanges) {
+        let player = self.player_turn;
+        self.pocket_mut(player).remove(mv.piece);
+        self.place_piece(changes, mv.target, mv.piece, player);
+    }
+
     /// Applies castling to the state.
-    fn apply_castle(&mut self, castle_move: CastleMove) {
-        match self.player_turn {
-            Player::White => {
-                match castle_move {
-                    CastleMove::KingSide => {
-                        self.white_board = self.white_board // TODO: const these
-                            .with_king(Square::new(6).to_bitboard()) //G1
-                            .with_rooks(self.white_board.rooks
-                                .unset_square(Square::new(7)) // H1
-                                .set_square(Square::new(5))); // F1
-                    }
-                    CastleMove::QueenSide => {
-                        self.white_board = self.white_board
-                            .with_king(Square::new(2).to_bitboard()) // C1
-                            .with_rooks(self.white_board.rooks
-                                .unset_square(Square::new(0)) // A1
-                                .set_square(Square::new(3))); // D1
-                    }
-                }
-                self.white_castle_rights = CastleRights::None;
-            }
-            Player::Black => {
-                match castle_move {
-                    CastleMove::KingSide => {
-                        self.black_board = self.black_board
-                            .with_king(Square::new(62).to_bitboard()) // G8
-                            .with_rooks(self.black_board.rooks
-                                .unset_square(Square::new(63)) // H8
-                                .set_square(Square::new(61))); // F8
-                    }
-                    CastleMove::QueenSide => {
-                        self.black_board = self.black_board
-                            .with_king(Square::new(58).to_bitboard()) // C8
-                            .with_rooks(self.black_board.rooks
-                                .unset_square(Square::new(56)) // A8
-                                .set_square(Square::new(59))); // D8
-                    }
-                }
-                self.black_castle_rights = CastleRights::None;
-            }
-        };
+    fn apply_castle(&mut self, castle_move: CastleMove, changes: &mut PieceChanges) {
+        let player = self.player_turn;
+        let king_origin = self.player_board(player).king.to_square();
+        let king_target = self.castle_king_square(castle_move, player);
+        let (rook_origin, rook_target) = self.castle_rook_squares(castle_move, player);
+
+        // in chess960 the king or rook may end on the other's starting square
+        self.take_piece(changes, king_origin);
+        self.take_piece(changes, rook_origin);
+        self.place_piece(changes, king_target, Piece::King, player);
+        self.place_piece(changes, rook_target, Piece::Rook, player);
+        self.set_castle_rights(player, CastleRights::None);
     }
 
     /// Applies a promoting move to the state.
-    fn apply_promotion(&mut self, mv: &Move) {
-        match self.player_turn {
-            Player::White => {
-                self.white_board = self.white_board
-                    .with_pawns(self.white_board.pawns.unset_square(mv.origin))
-                    .with_piece(
-                        mv.promotion
-                            .expect("apply_promotion called without promotion piece"),
-                        mv.target.to_bitboard(),
-                    )
-            }
-            Player::Black => {
-                self.black_board = self.black_board
-                    .with_pawns(self.black_board.pawns.unset_square(mv.origin))
-                    .with_piece(
-                        mv.promotion
-                            .expect("apply_promotion called without promotion piece"),
-                        mv.target.to_bitboard(),
-                    )
-            }
-        }
+    fn apply_promotion(&mut self, mv: &Move, changes: &mut PieceChanges) {
+        let promotion = mv
+            .promotion
+            .expect("apply_promotion called without promotion piece");
+        let player = self.player_turn;
+
+        self.take_piece(changes, mv.target);
+        self.take_piece(changes, mv.origin);
+        self.place_piece(changes, mv.target, promotion, player);
     }
 
     /// Applies a non-capturing move to the state.
-    fn apply_non_capture(&mut self, mv: &Move) {
-        match self.player_turn {
+    fn apply_non_capture(&mut self, mv: &Move, changes: &mut PieceChanges) {
+        let player = self.player_turn;
+        self.take_piece(changes, mv.origin);
+        self.place_piece(changes, mv.target, mv.piece, player);
+
+        match player {
             Player::White => {
-                self.white_board = self.white_board.with_piece(
-                    mv.piece,
-                    self.white_board
-                        .piece(mv.piece)
-                        .unset_square(mv.origin)
-                        .set_square(mv.target),
-                );
-
-                if mv.piece == Piece::Pawn && mv.origin.rank() == Rank::Two
+                if mv.piece == Piece::Pawn
+                    && mv.origin.rank() == Rank::Two
                     && mv.target.rank() == Rank::Four
                 {
                     self.en_passant = Some(Square::from_coordinates(mv.origin.file(), Rank::Three))
                 }
             }
             Player::Black => {
-                self.black_board = self.black_board.with_piece(
-                    mv.piece,
-                    self.black_board
-                        .piece(mv.piece)
-                        .unset_square(mv.origin)
-                        .set_square(mv.target),
-                );
-                if mv.piece == Piece::Pawn && mv.origin.rank() == Rank::Seven
+                if mv.piece == Piece::Pawn
+                    && mv.origin.rank() == Rank::Seven
                     && mv.target.rank() == Rank::Five
                 {
                     self.en_passant = Some(Square::from_coordinates(mv.origin.file(), Rank::Six))
@@ -416,86 +634,150 @@ impl GameState {
     }
 
     /// Applies an en-passant capture to the state.
-    fn apply_en_passant(&mut self, mv: &Move) {
-        match self.player_turn {
-            Player::White => {
-                self.white_board = self.white_board.with_pawns(
-                    self.white_board
-                        .pawns
-                        .unset_square(mv.origin)
-                        .set_square(mv.target),
-                );
-                let target =
-                    Square::from_coordinates(mv.target.file(), mv.target.rank().prev().unwrap());
-                self.black_board = self.black_board
-                    .with_pawns(self.black_board.pawns.unset_square(target));
-            }
-            Player::Black => {
-                self.black_board = self.black_board.with_pawns(
-                    self.black_board
-                        .pawns
-                        .unset_square(mv.origin)
-                        .set_square(mv.target),
-                );
-                let target =
-                    Square::from_coordinates(mv.target.file(), mv.target.rank().next().unwrap());
-                self.white_board = self.white_board
-                    .with_pawns(self.white_board.pawns.unset_square(target));
-            }
-        }
+    fn apply_en_passant(&mut self, mv: &Move, changes: &mut PieceChanges) {
+        let player = self.player_turn;
+        self.take_piece(changes, en_passant_capture_square(mv.target, player));
+        self.take_piece(changes, mv.origin);
+        self.place_piece(changes, mv.target, Piece::Pawn, player);
     }
 
     /// Applies an ordinary capture move to the state.
-    fn apply_capture(&mut self, mv: &Move) {
-        match self.player_turn {
-            Player::White => {
-                self.white_board = self.white_board.with_piece(
-                    mv.piece,
-                    self.white_board
-                        .piece(mv.piece)
-                        .unset_square(mv.origin)
-                        .set_square(mv.target),
-                );
-                self.black_board = match self.black_board {
-                    board if board.pawns.is_square_set(mv.target) => {
-                        board.with_pawns(board.pawns.unset_square(mv.target))
-                    }
-                    board if board.rooks.is_square_set(mv.target) => {
-                        board.with_rooks(board.rooks.unset_square(mv.target))
-                    }
-                    board if board.knights.is_square_set(mv.target) => {
-                        board.with_knights(board.knights.unset_square(mv.target))
-                    }
-                    board if board.bishops.is_square_set(mv.target) => {
-                        board.with_bishops(board.bishops.unset_square(mv.target))
-                    }
-                    board => board.with_queens(board.queens.unset_square(mv.target)),
-                }
-            }
-            Player::Black => {
-                self.black_board = self.black_board.with_piece(
-                    mv.piece,
-                    self.black_board
-                        .piece(mv.piece)
-                        .unset_square(mv.origin)
-                        .set_square(mv.target),
-                );
-                self.white_board = match self.white_board {
-                    board if board.pawns.is_square_set(mv.target) => {
-                        board.with_pawns(board.pawns.unset_square(mv.target))
-                    }
-                    board if board.rooks.is_square_set(mv.target) => {
-                        board.with_rooks(board.rooks.unset_square(mv.target))
-                    }
-                    board if board.knights.is_square_set(mv.target) => {
-                        board.with_knights(board.knights.unset_square(mv.target))
-                    }
-                    board if board.bishops.is_square_set(mv.target) => {
-                        board.with_bishops(board.bishops.unset_square(mv.target))
-                    }
-                    board => board.with_queens(board.queens.unset_square(mv.target)),
-                }
-            }
+    fn apply_capture(&mut self, mv: &Move, changes: &mut PieceChanges) {
+        let player = self.player_turn;
+        self.take_piece(changes, mv.target)
+            .expect("apply_capture called without a piece to capture");
+        self.take_piece(changes, mv.origin);
+        self.place_piece(changes, mv.target, mv.piece, player);
+    }
+
+    // puts a piece on the board, recording the change
+    fn place_piece(
+        &mut self,
+        changes: &mut PieceChanges,
+        square: Square,
+        piece: Piece,
+        player: Player,
+    ) {
+        self.put_piece(square, piece, player);
+        changes.push(PieceChange {
+            square,
+            piece,
+            player,
+            added: true,
+        });
+    }
+
+    // removes the piece on a square from the board, if any, recording the change
+    fn take_piece(
+        &mut self,
+        changes: &mut PieceChanges,
+        square: Square,
+    ) -> Option<(Piece, Player)> {
+        let removed = self.remove_piece(square);
+        if let Some((piece, player)) = removed {
+            changes.push(PieceChange {
+                square,
+                piece,
+                player,
+                added: false,
+            });
+        }
+        removed
+    }
+
+    // removes the castle right of the rook starting on the given square, if any
+    fn remove_rook_castle_rights(&mut self, player: Player, square: Square) {
+        if square.rank() != back_rank(player) {
+            return;
         }
+        let castle_rights = self.castle_rights(player);
+        if castle_rights.is_king_side_available() && square.file() == self.castle_files.king_side {
+            self.set_castle_rights(player, castle_rights.without_king_side());
+        } else if castle_rights.is_queen_side_available()
+            && square.file() == self.castle_files.queen_side
+        {
+            self.set_castle_rights(player, castle_rights.without_queen_side());
+        }
+    }
+
+    // ----------------------------------------------------------------
+    /// Returns the square the king moves to in the given castle move.
+    pub fn castle_king_square(&self, castle_move: CastleMove, player: Player) -> Square {
+        match castle_move {
+            CastleMove::KingSide => Square::from_coordinates(File::G, back_rank(player)),
+            CastleMove::QueenSide => Square::from_coordinates(File::C, back_rank(player)),
+        }
+    }
+
+    /// Returns the origin and target squares of the rook in the given castle move.
+    pub fn castle_rook_squares(&self, castle_move: CastleMove, player: Player) -> (Square, Square) {
+        let rank = back_rank(player);
+        match castle_move {
+            CastleMove::KingSide => (
+                Square::from_coordinates(self.castle_files.king_side, rank),
+                Square::from_coordinates(File::F, rank),
+            ),
+            CastleMove::QueenSide => (
+                Square::from_coordinates(self.castle_files.queen_side, rank),
+                Square::from_coordinates(File::D, rank),
+            ),
+        }
+    }
+
+    /// Returns the squares the king passes through when the player whose turn it is
+    /// castles, including its origin and target squares.
+    pub fn castle_king_path(&self, castle_move: CastleMove) -> BitBoard {
+        let player = self.player_turn;
+        let king_origin = self.player_board(player).king.to_square();
+        rank_span(king_origin, self.castle_king_square(castle_move, player))
+    }
+
+    /// Returns true if the player whose turn it is has the castling rook in place and no
+    /// pieces other than the king and that rook on the squares either of them pass through.
+    ///
+    /// This does not consider castle rights or whether the squares are attacked.
+    pub fn is_castle_path_clear(&self, castle_move: CastleMove) -> bool {
+        let player = self.player_turn;
+        let own_board = self.player_board(player);
+        let king_origin = own_board.king.to_square();
+        let (rook_origin, rook_target) = self.castle_rook_squares(castle_move, player);
+        if !own_board.rooks.is_square_set(rook_origin) {
+            return false;
+        }
+
+        let blockers = self
+            .occupied()
+            .unset_square(king_origin)
+            .unset_square(rook_origin);
+        let path = self.castle_king_path(castle_move) | rank_span(rook_origin, rook_target);
+        (path & blockers).is_empty()
+    }
+}
+
+// returns the back rank of the given player
+fn back_rank(player: Player) -> Rank {
+    match player {
+        Player::White => Rank::One,
+        Player::Black => Rank::Eight,
+    }
+}
+
+// returns the squares from one square to another on the same rank, inclusive
+fn rank_span(from: Square, to: Square) -> BitBoard {
+    let (low, high) = if from.to_index() < to.to_index() {
+        (from.to_index(), to.to_index())
+    } else {
+        (to.to_index(), from.to_index())
+    };
+    (low..high + 1).fold(BitBoard::empty(), |bb, index| {
+        bb.set_square(Square::new(index))
+    })
+}
+
+// returns the square of the pawn captured by an en-passant capture onto the given target
+fn en_passant_capture_square(target: Square, player: Player) -> Square {
+    match player {
+        Player::White => Square::from_coordinates(target.file(), target.rank().prev().unwrap()),
+        Player::Black => Square::from_coordinates(target.file(), target.rank().next().unwrap()),
     }
 }
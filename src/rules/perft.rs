@@ -0,0 +1,30 @@
+//! Move path enumeration, used to verify move generation.
+
+use rules::game_state::GameState;
+
+impl GameState {
+    /// Returns the number of distinct move sequences of the given depth from the state.
+    pub fn perft(&self, depth: usize) -> u64 {
+        perft_internal(&mut self.clone(), depth)
+    }
+}
+
+// counts the leaf nodes by making and unmaking moves on a single state
+fn perft_internal(state: &mut GameState, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = state.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let undo = state.make_move(&mv);
+        nodes += perft_internal(state, depth - 1);
+        state.unmake_move(&mv, &undo);
+    }
+    nodes
+}
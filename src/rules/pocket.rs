@@ -0,0 +1,76 @@
+//! The pieces a player holds in hand, as in Crazyhouse.
+
+use board::piece::Piece;
+
+/// Represents the number of each piece a player holds in hand.
+///
+/// Kings can never be held.
+#[derive(Clone, Debug, Copy, Eq, PartialEq, Default)]
+pub struct Pocket {
+    /// The number of pawns.
+    pub pawns: u8,
+
+    /// The number of rooks.
+    pub rooks: u8,
+
+    /// The number of knights.
+    pub knights: u8,
+
+    /// The number of bishops.
+    pub bishops: u8,
+
+    /// The number of queens.
+    pub queens: u8,
+}
+
+impl Pocket {
+    /// Returns a new, empty instance.
+    pub fn new() -> Self {
+        Pocket::default()
+    }
+
+    /// Returns the number of the given piece held.
+    pub fn count(&self, piece: Piece) -> u8 {
+        match piece {
+            Piece::Pawn => self.pawns,
+            Piece::Rook => self.rooks,
+            Piece::Knight => self.knights,
+            Piece::Bishop => self.bishops,
+            Piece::Queen => self.queens,
+            Piece::King => 0,
+        }
+    }
+
+    /// Adds one of the given piece.
+    ///
+    /// Will panic if the piece is a king.
+    pub fn add(&mut self, piece: Piece) {
+        *self.count_mut(piece) += 1;
+    }
+
+    /// Removes one of the given piece.
+    ///
+    /// Will panic if none of the piece are held.
+    pub fn remove(&mut self, piece: Piece) {
+        let count = self.count_mut(piece);
+        assert!(*count > 0, "No {:?} is held in the pocket", piece);
+        *count -= 1;
+    }
+
+    /// Returns true if no pieces are held.
+    pub fn is_empty(&self) -> bool {
+        Piece::iter_non_pawn().all(|piece| self.count(piece) == 0) && self.pawns == 0
+    }
+
+    // returns a mutable reference to the count of the given piece
+    fn count_mut(&mut self, piece: Piece) -> &mut u8 {
+        match piece {
+            Piece::Pawn => &mut self.pawns,
+            Piece::Rook => &mut self.rooks,
+            Piece::Knight => &mut self.knights,
+            Piece::Bishop => &mut self.bishops,
+            Piece::Queen => &mut self.queens,
+            Piece::King => panic!("Kings cannot be held in a pocket"),
+        }
+    }
+}
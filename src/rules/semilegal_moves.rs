@@ -39,6 +39,19 @@ impl Piece {
             Piece::King => king_attacks(square, opponent_pieces),
         }
     }
+
+    /// Returns every square the piece attacks, whether it is empty or occupied by
+    /// either player.
+    pub fn attacked_squares(&self, square: Square, player: Player, blockers: BitBoard) -> BitBoard {
+        match *self {
+            Piece::Pawn => basic_moves::pawn_attacks(square, player),
+            Piece::Rook => rook_all_moves(square, blockers),
+            Piece::Knight => basic_moves::knight(square),
+            Piece::Bishop => bishop_all_moves(square, blockers),
+            Piece::Queen => rook_all_moves(square, blockers) | bishop_all_moves(square, blockers),
+            Piece::King => basic_moves::king(square),
+        }
+    }
 }
 
 /// Returns the moves a given pawn can make.
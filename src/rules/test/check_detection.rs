@@ -22,7 +22,7 @@ fn is_check_rook() {
 
     let state = state.clone().with_white_board(
         state
-            .white_board
+            .player_board(Player::White)
             .with_pawns(Square::from_coordinates(File::B, Rank::Three).to_bitboard()),
     );
 
@@ -79,7 +79,7 @@ fn is_check_bishop() {
 
     let state = state.clone().with_white_board(
         state
-            .white_board
+            .player_board(Player::White)
             .with_pawns(Square::from_coordinates(File::C, Rank::Three).to_bitboard()),
     );
 
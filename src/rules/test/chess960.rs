@@ -0,0 +1,150 @@
+use board::file::File;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use board::square::Square;
+use rules::castle_rights::CastleFiles;
+use rules::chess960;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::move_application::CastleMove;
+
+#[test]
+fn chess960_standard_position() {
+    assert_eq!(
+        GameState::chess960_start_position(chess960::STANDARD_POSITION),
+        GameState::start_position()
+    );
+}
+
+#[test]
+fn chess960_start_positions() {
+    let state = GameState::chess960_start_position(0);
+    assert_eq!(
+        state,
+        parse_fen("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1").unwrap()
+    );
+    assert_eq!(
+        state.castle_files,
+        CastleFiles {
+            king_side: File::H,
+            queen_side: File::F,
+        }
+    );
+
+    assert_eq!(
+        GameState::chess960_start_position(959),
+        parse_fen("rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1").unwrap()
+    );
+
+    for number in 0..chess960::POSITION_COUNT {
+        let state = GameState::chess960_start_position(number);
+        let king = state.player_board(Player::White).king.to_square();
+        assert!(state.castle_files.queen_side.to_index() < king.file().to_index());
+        assert!(state.castle_files.king_side.to_index() > king.file().to_index());
+        assert_eq!(state.player_board(Player::White).bishops.count(), 2);
+        assert_eq!(
+            state.player_board(Player::Black).all(),
+            state.player_board(Player::White).all().mirror_horizontal()
+        );
+        assert!(state.is_mailbox_consistent());
+    }
+}
+
+#[test]
+fn chess960_parse_shredder_fen() {
+    let state = parse_fen("1r2k1r1/8/8/8/8/8/8/R3K1R1 w GAg - 0 1").unwrap();
+    assert_eq!(
+        state.castle_files,
+        CastleFiles {
+            king_side: File::G,
+            queen_side: File::A,
+        }
+    );
+    assert!(state.white_castle_rights.is_king_side_available());
+    assert!(state.white_castle_rights.is_queen_side_available());
+    assert!(state.black_castle_rights.is_king_side_available());
+    assert!(!state.black_castle_rights.is_queen_side_available());
+
+    assert!(parse_fen("1r2k1r1/8/8/8/8/8/8/R3K1R1 w GAb - 0 1").is_err());
+}
+
+#[test]
+fn chess960_castle_overlapping_squares() {
+    // the king starts on the castle target square of the rook, and the rook on the
+    // target square of the king.
+    let state = parse_fen("4k3/8/8/8/8/8/8/4RK1R w E - 0 1").unwrap();
+    let castle = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.castle == Some(CastleMove::QueenSide))
+        .unwrap();
+    assert_eq!(castle.origin, Square::from_coordinates(File::F, Rank::One));
+    assert_eq!(castle.target, Square::from_coordinates(File::C, Rank::One));
+
+    let castled = state.apply_move(&castle);
+    assert_eq!(
+        castled.piece_at(Square::from_coordinates(File::C, Rank::One)),
+        Some((Piece::King, Player::White))
+    );
+    assert_eq!(
+        castled.piece_at(Square::from_coordinates(File::D, Rank::One)),
+        Some((Piece::Rook, Player::White))
+    );
+    assert_eq!(
+        castled.piece_at(Square::from_coordinates(File::E, Rank::One)),
+        None
+    );
+
+    // the king does not move, only the rook
+    let state = parse_fen("4k3/8/8/8/8/8/8/6KR w H - 0 1").unwrap();
+    let castled = state.apply_move(&state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.castle == Some(CastleMove::KingSide))
+        .unwrap());
+    assert_eq!(
+        castled.piece_at(Square::from_coordinates(File::G, Rank::One)),
+        Some((Piece::King, Player::White))
+    );
+    assert_eq!(
+        castled.piece_at(Square::from_coordinates(File::F, Rank::One)),
+        Some((Piece::Rook, Player::White))
+    );
+
+    // a rook attacking the king's target square prevents castling, even though the
+    // rook it attacks through moves out of the way
+    let state = parse_fen("2r1k3/8/8/8/8/8/8/1R1K4 w B - 0 1").unwrap();
+    assert!(state.legal_moves().iter().all(|mv| mv.castle.is_none()));
+}
+
+// https://www.chessprogramming.org/Chess960_Perft_Results
+#[test]
+fn chess960_perft() {
+    let positions: [(&str, &[u64]); 4] = [
+        (
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            &[21, 528, 12189],
+        ),
+        (
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            &[21, 807, 18002],
+        ),
+        (
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            &[20, 479, 10471],
+        ),
+        (
+            "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            &[22, 593],
+        ),
+    ];
+
+    for &(fen, counts) in positions.iter() {
+        let state = parse_fen(fen).unwrap();
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(state.perft(depth + 1), count, "{} at depth {}", fen, depth + 1);
+        }
+    }
+}
+
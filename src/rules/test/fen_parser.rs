@@ -4,7 +4,7 @@ use board::player::Player;
 use board::rank::Rank;
 use board::square::Square;
 use rules::castle_rights::CastleRights;
-use rules::fen_parser::parse_fen;
+use rules::fen_parser::{parse_fen, to_fen};
 use rules::game_state::GameState;
 use rules::player_board::PlayerBoard;
 
@@ -21,8 +21,8 @@ fn parse_end_game() {
 
     use board::file::File::*;
     use board::rank::Rank::*;
-    let expected = GameState {
-        white_board: PlayerBoard {
+    let expected = GameState::default()
+        .with_white_board(PlayerBoard {
             pawns: BitBoard::empty()
                 .set_coordinate(A, Two)
                 .set_coordinate(B, Two)
@@ -34,8 +34,8 @@ fn parse_end_game() {
             bishops: BitBoard::empty(),
             queens: BitBoard::empty(),
             king: BitBoard::empty().set_coordinate(B, Three),
-        },
-        black_board: PlayerBoard {
+        })
+        .with_black_board(PlayerBoard {
             pawns: BitBoard::empty()
                 .set_coordinate(A, Seven)
                 .set_coordinate(B, Seven)
@@ -46,14 +46,13 @@ fn parse_end_game() {
             bishops: BitBoard::empty(),
             queens: BitBoard::empty(),
             king: BitBoard::empty().set_coordinate(F, Eight),
-        },
-        player_turn: Player::Black,
-        en_passant: None,
-        white_castle_rights: CastleRights::None,
-        black_castle_rights: CastleRights::None,
-        draw_plies: 1,
-        full_turns: 31,
-    };
+        })
+        .with_player_turn(Player::Black)
+        .with_en_passant(None)
+        .with_white_castle_rights(CastleRights::None)
+        .with_black_castle_rights(CastleRights::None)
+        .with_draw_plies(1)
+        .with_full_turns(31);
 
     assert_eq!(state, expected);
 }
@@ -66,3 +65,22 @@ fn parse_en_passant() {
         Some(Square::from_coordinates(File::E, Rank::Three))
     );
 }
+
+#[test]
+fn write_fen() {
+    assert_eq!(
+        to_fen(&GameState::start_position()),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+
+    let fens = [
+        "5k2/ppp5/4P3/3R3p/6P1/1K2Nr2/PP3P2/8 b - - 1 32",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "1r4kr/8/8/8/8/8/8/1R4KR w KBkb - 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 4 12 +1+2",
+        "4k3/8/8/8/4N~3/8/8/4K3[QRpp] w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        assert_eq!(to_fen(&parse_fen(fen).unwrap()), *fen);
+    }
+}
@@ -0,0 +1,94 @@
+use board::file::File;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use board::square::Square;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::mailbox::Mailbox;
+use rules::player_board::PlayerBoard;
+
+#[test]
+fn mailbox_start_position() {
+    let state = GameState::start_position();
+
+    assert_eq!(
+        state.piece_at(Square::from_coordinates(File::E, Rank::One)),
+        Some((Piece::King, Player::White))
+    );
+    assert_eq!(
+        state.piece_at(Square::from_coordinates(File::D, Rank::Eight)),
+        Some((Piece::Queen, Player::Black))
+    );
+    assert_eq!(
+        state.piece_at(Square::from_coordinates(File::B, Rank::Seven)),
+        Some((Piece::Pawn, Player::Black))
+    );
+    assert_eq!(
+        state.piece_at(Square::from_coordinates(File::E, Rank::Four)),
+        None
+    );
+    assert!(state.is_mailbox_consistent());
+}
+
+#[test]
+fn mailbox_from_boards() {
+    let white = PlayerBoard::default()
+        .with_knights(Square::from_coordinates(File::C, Rank::Three).to_bitboard());
+    let black = PlayerBoard::default()
+        .with_rooks(Square::from_coordinates(File::H, Rank::Five).to_bitboard());
+    let mailbox = Mailbox::from_boards(&white, &black);
+
+    assert_eq!(
+        mailbox.get(Square::from_coordinates(File::C, Rank::Three)),
+        Some((Piece::Knight, Player::White))
+    );
+    assert_eq!(
+        mailbox.get(Square::from_coordinates(File::H, Rank::Five)),
+        Some((Piece::Rook, Player::Black))
+    );
+    assert_eq!(mailbox.get(Square::from_coordinates(File::A, Rank::One)), None);
+
+    let state = GameState::default()
+        .with_white_board(white)
+        .with_black_board(black);
+    assert_eq!(*state.mailbox(), mailbox);
+}
+
+#[test]
+fn mailbox_move_application() {
+    let mut state =
+        parse_fen("r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/P1PBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    assert!(state.is_mailbox_consistent());
+
+    for mv in state.clone().legal_moves() {
+        let undo = state.make_move(&mv);
+        assert!(state.is_mailbox_consistent(), "{} desynced the mailbox", mv);
+        assert_eq!(
+            state.piece_at(mv.target),
+            Some((mv.promotion.unwrap_or(mv.piece), Player::White))
+        );
+        assert_eq!(state.piece_at(mv.origin), None);
+
+        state.unmake_move(&mv, &undo);
+        assert!(state.is_mailbox_consistent(), "{} desynced the mailbox", mv);
+    }
+}
+
+#[test]
+fn mailbox_put_remove() {
+    let mut state = GameState::start_position();
+    let e2 = Square::from_coordinates(File::E, Rank::Two);
+    let e4 = Square::from_coordinates(File::E, Rank::Four);
+
+    assert_eq!(state.remove_piece(e2), Some((Piece::Pawn, Player::White)));
+    assert_eq!(state.remove_piece(e2), None);
+    state.put_piece(e4, Piece::Queen, Player::Black);
+    assert!(state.is_mailbox_consistent());
+    assert_eq!(state.piece_at(e4), Some((Piece::Queen, Player::Black)));
+    assert!(state.player_board(Player::Black).queens.is_square_set(e4));
+
+    state.set_boards(PlayerBoard::default(), PlayerBoard::default());
+    assert!(state.is_mailbox_consistent());
+    assert_eq!(state.piece_at(e4), None);
+}
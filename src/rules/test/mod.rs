@@ -2,10 +2,16 @@
 
 mod basic_moves;
 mod castle_rights;
+mod chess960;
 mod check_detection;
 mod fen_parser;
 mod game_state;
 mod legal_moves;
+mod mailbox;
 mod move_application;
+mod perft;
 mod player_board;
+mod pocket;
 mod semilegal_moves;
+mod variant;
+mod zobrist;
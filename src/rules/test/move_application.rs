@@ -6,6 +6,7 @@ use board::player::Player;
 use board::rank::Rank;
 use board::square::Square;
 use rules::castle_rights::CastleRights;
+use rules::fen_parser::parse_fen;
 use rules::game_state::GameState;
 use rules::move_application::CastleMove;
 use rules::move_application::Move;
@@ -36,6 +37,7 @@ fn move_castle_white() {
         en_passant: false,
         promotion: None,
         castle: Some(CastleMove::KingSide),
+        drop: false,
     };
 
     let ks_state = initial_state.apply_move(&ks_move);
@@ -45,24 +47,27 @@ fn move_castle_white() {
 
     assert_eq!(
         ks_state,
-        GameState {
-            white_board: PlayerBoard::default()
-                .with_rooks(
-                    BitBoard::empty()
-                        .set_coordinate(File::A, Rank::One)
-                        .set_coordinate(File::F, Rank::One)
-                )
-                .with_king(BitBoard::empty().set_coordinate(File::G, Rank::One)),
-            black_board: PlayerBoard::default()
-                .with_rooks(bitboard::BLACK_START_ROOKS)
-                .with_king(bitboard::BLACK_START_KINGS),
-            player_turn: Player::Black,
-            en_passant: None,
-            white_castle_rights: CastleRights::None,
-            black_castle_rights: CastleRights::Both,
-            draw_plies: 1,
-            full_turns: 0,
-        }
+        GameState::default()
+            .with_white_board(
+                PlayerBoard::default()
+                    .with_rooks(
+                        BitBoard::empty()
+                            .set_coordinate(File::A, Rank::One)
+                            .set_coordinate(File::F, Rank::One)
+                    )
+                    .with_king(BitBoard::empty().set_coordinate(File::G, Rank::One)),
+            )
+            .with_black_board(
+                PlayerBoard::default()
+                    .with_rooks(bitboard::BLACK_START_ROOKS)
+                    .with_king(bitboard::BLACK_START_KINGS),
+            )
+            .with_player_turn(Player::Black)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::None)
+            .with_black_castle_rights(CastleRights::Both)
+            .with_draw_plies(1)
+            .with_full_turns(0)
     );
 
     // ------
@@ -74,6 +79,7 @@ fn move_castle_white() {
         en_passant: false,
         promotion: None,
         castle: Some(CastleMove::QueenSide),
+        drop: false,
     };
 
     let qs_state = initial_state.apply_move(&qs_move);
@@ -83,24 +89,27 @@ fn move_castle_white() {
 
     assert_eq!(
         qs_state,
-        GameState {
-            white_board: PlayerBoard::default()
-                .with_rooks(
-                    BitBoard::empty()
-                        .set_coordinate(File::H, Rank::One)
-                        .set_coordinate(File::D, Rank::One)
-                )
-                .with_king(BitBoard::empty().set_coordinate(File::C, Rank::One)),
-            black_board: PlayerBoard::default()
-                .with_rooks(bitboard::BLACK_START_ROOKS)
-                .with_king(bitboard::BLACK_START_KINGS),
-            player_turn: Player::Black,
-            en_passant: None,
-            white_castle_rights: CastleRights::None,
-            black_castle_rights: CastleRights::Both,
-            draw_plies: 1,
-            full_turns: 0,
-        }
+        GameState::default()
+            .with_white_board(
+                PlayerBoard::default()
+                    .with_rooks(
+                        BitBoard::empty()
+                            .set_coordinate(File::H, Rank::One)
+                            .set_coordinate(File::D, Rank::One)
+                    )
+                    .with_king(BitBoard::empty().set_coordinate(File::C, Rank::One)),
+            )
+            .with_black_board(
+                PlayerBoard::default()
+                    .with_rooks(bitboard::BLACK_START_ROOKS)
+                    .with_king(bitboard::BLACK_START_KINGS),
+            )
+            .with_player_turn(Player::Black)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::None)
+            .with_black_castle_rights(CastleRights::Both)
+            .with_draw_plies(1)
+            .with_full_turns(0)
     );
 }
 
@@ -129,6 +138,7 @@ fn move_castle_black() {
         en_passant: false,
         promotion: None,
         castle: Some(CastleMove::KingSide),
+        drop: false,
     };
 
     let ks_state = initial_state.apply_move(&ks_move);
@@ -138,24 +148,27 @@ fn move_castle_black() {
 
     assert_eq!(
         ks_state,
-        GameState {
-            white_board: PlayerBoard::default()
-                .with_rooks(bitboard::WHITE_START_ROOKS)
-                .with_king(bitboard::WHITE_START_KINGS),
-            black_board: PlayerBoard::default()
-                .with_rooks(
-                    BitBoard::empty()
-                        .set_coordinate(File::A, Rank::Eight)
-                        .set_coordinate(File::F, Rank::Eight)
-                )
-                .with_king(BitBoard::empty().set_coordinate(File::G, Rank::Eight)),
-            player_turn: Player::White,
-            en_passant: None,
-            white_castle_rights: CastleRights::Both,
-            black_castle_rights: CastleRights::None,
-            draw_plies: 1,
-            full_turns: 1,
-        }
+        GameState::default()
+            .with_white_board(
+                PlayerBoard::default()
+                    .with_rooks(bitboard::WHITE_START_ROOKS)
+                    .with_king(bitboard::WHITE_START_KINGS),
+            )
+            .with_black_board(
+                PlayerBoard::default()
+                    .with_rooks(
+                        BitBoard::empty()
+                            .set_coordinate(File::A, Rank::Eight)
+                            .set_coordinate(File::F, Rank::Eight)
+                    )
+                    .with_king(BitBoard::empty().set_coordinate(File::G, Rank::Eight)),
+            )
+            .with_player_turn(Player::White)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::Both)
+            .with_black_castle_rights(CastleRights::None)
+            .with_draw_plies(1)
+            .with_full_turns(1)
     );
 
     // ------
@@ -167,6 +180,7 @@ fn move_castle_black() {
         en_passant: false,
         promotion: None,
         castle: Some(CastleMove::QueenSide),
+        drop: false,
     };
 
     let qs_state = initial_state.apply_move(&qs_move);
@@ -176,24 +190,27 @@ fn move_castle_black() {
 
     assert_eq!(
         qs_state,
-        GameState {
-            white_board: PlayerBoard::default()
-                .with_rooks(bitboard::WHITE_START_ROOKS)
-                .with_king(bitboard::WHITE_START_KINGS),
-            black_board: PlayerBoard::default()
-                .with_rooks(
-                    BitBoard::empty()
-                        .set_coordinate(File::H, Rank::Eight)
-                        .set_coordinate(File::D, Rank::Eight)
-                )
-                .with_king(BitBoard::empty().set_coordinate(File::C, Rank::Eight)),
-            player_turn: Player::White,
-            en_passant: None,
-            white_castle_rights: CastleRights::Both,
-            black_castle_rights: CastleRights::None,
-            draw_plies: 1,
-            full_turns: 1,
-        }
+        GameState::default()
+            .with_white_board(
+                PlayerBoard::default()
+                    .with_rooks(bitboard::WHITE_START_ROOKS)
+                    .with_king(bitboard::WHITE_START_KINGS),
+            )
+            .with_black_board(
+                PlayerBoard::default()
+                    .with_rooks(
+                        BitBoard::empty()
+                            .set_coordinate(File::H, Rank::Eight)
+                            .set_coordinate(File::D, Rank::Eight)
+                    )
+                    .with_king(BitBoard::empty().set_coordinate(File::C, Rank::Eight)),
+            )
+            .with_player_turn(Player::White)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::Both)
+            .with_black_castle_rights(CastleRights::None)
+            .with_draw_plies(1)
+            .with_full_turns(1)
     );
 }
 
@@ -214,6 +231,7 @@ fn move_promote_white() {
         en_passant: false,
         promotion: Some(Piece::Queen),
         castle: None,
+        drop: false,
     };
 
     let state = initial_state.apply_move(&move_);
@@ -223,17 +241,18 @@ fn move_promote_white() {
 
     assert_eq!(
         state,
-        GameState {
-            white_board: PlayerBoard::default()
-                .with_queens(BitBoard::empty().set_coordinate(File::G, Rank::Eight)),
-            black_board: PlayerBoard::default(),
-            player_turn: Player::Black,
-            en_passant: None,
-            white_castle_rights: CastleRights::None,
-            black_castle_rights: CastleRights::None,
-            draw_plies: 0,
-            full_turns: 0,
-        }
+        GameState::default()
+            .with_white_board(
+                PlayerBoard::default()
+                    .with_queens(BitBoard::empty().set_coordinate(File::G, Rank::Eight)),
+            )
+            .with_black_board(PlayerBoard::default())
+            .with_player_turn(Player::Black)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::None)
+            .with_black_castle_rights(CastleRights::None)
+            .with_draw_plies(0)
+            .with_full_turns(0)
     );
 }
 
@@ -253,6 +272,7 @@ fn move_promote_black() {
         en_passant: false,
         promotion: Some(Piece::Knight),
         castle: None,
+        drop: false,
     };
 
     let state = initial_state.apply_move(&move_);
@@ -262,17 +282,18 @@ fn move_promote_black() {
 
     assert_eq!(
         state,
-        GameState {
-            white_board: PlayerBoard::default(),
-            black_board: PlayerBoard::default()
-                .with_knights(BitBoard::empty().set_coordinate(File::B, Rank::One)),
-            player_turn: Player::White,
-            en_passant: None,
-            white_castle_rights: CastleRights::None,
-            black_castle_rights: CastleRights::None,
-            draw_plies: 0,
-            full_turns: 1,
-        }
+        GameState::default()
+            .with_white_board(PlayerBoard::default())
+            .with_black_board(
+                PlayerBoard::default()
+                    .with_knights(BitBoard::empty().set_coordinate(File::B, Rank::One)),
+            )
+            .with_player_turn(Player::White)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::None)
+            .with_black_castle_rights(CastleRights::None)
+            .with_draw_plies(0)
+            .with_full_turns(1)
     );
 }
 
@@ -289,6 +310,7 @@ fn move_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move1);
@@ -302,6 +324,7 @@ fn move_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move2);
@@ -315,6 +338,7 @@ fn move_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move3);
@@ -328,6 +352,7 @@ fn move_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move4);
@@ -335,30 +360,29 @@ fn move_sequence() {
 
     assert_eq!(
         state,
-        GameState {
-            white_board: PlayerBoard {
+        GameState::default()
+            .with_white_board(PlayerBoard {
                 pawns: BitBoard::new(134280960),
                 rooks: BitBoard::new(129),
                 knights: BitBoard::new(66),
                 bishops: BitBoard::new(536870944),
                 queens: BitBoard::new(8),
                 king: BitBoard::new(16),
-            },
-            black_board: PlayerBoard {
+            })
+            .with_black_board(PlayerBoard {
                 pawns: BitBoard::new(65020822699376640),
                 rooks: BitBoard::new(9295429630892703744),
                 knights: BitBoard::new(4755801206503243776),
                 bishops: BitBoard::new(2594073385365405696),
                 queens: BitBoard::new(576460752303423488),
                 king: BitBoard::new(1152921504606846976),
-            },
-            player_turn: Player::White,
-            en_passant: Some(Square::new(44)),
-            white_castle_rights: CastleRights::Both,
-            black_castle_rights: CastleRights::Both,
-            draw_plies: 0,
-            full_turns: 2,
-        }
+            })
+            .with_player_turn(Player::White)
+            .with_en_passant(Some(Square::new(44)))
+            .with_white_castle_rights(CastleRights::Both)
+            .with_black_castle_rights(CastleRights::Both)
+            .with_draw_plies(0)
+            .with_full_turns(2)
     );
 }
 
@@ -375,6 +399,7 @@ fn attack_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move1);
@@ -388,6 +413,7 @@ fn attack_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move2);
@@ -401,6 +427,7 @@ fn attack_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move3);
@@ -414,6 +441,7 @@ fn attack_sequence() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move4);
@@ -421,30 +449,29 @@ fn attack_sequence() {
 
     assert_eq!(
         state,
-        GameState {
-            white_board: PlayerBoard {
+        GameState::default()
+            .with_white_board(PlayerBoard {
                 pawns: BitBoard::new(61184),
                 rooks: BitBoard::new(129),
                 knights: BitBoard::new(66),
                 bishops: BitBoard::new(36),
                 queens: BitBoard::new(8),
                 king: BitBoard::new(16),
-            },
-            black_board: PlayerBoard {
+            })
+            .with_black_board(PlayerBoard {
                 pawns: BitBoard::new(69524319247532032),
                 rooks: BitBoard::new(9295429630892703744),
                 knights: BitBoard::new(4755801206503243776),
                 bishops: BitBoard::new(2594073385365405696),
                 queens: BitBoard::new(34359738368),
                 king: BitBoard::new(1152921504606846976),
-            },
-            player_turn: Player::White,
-            en_passant: None,
-            white_castle_rights: CastleRights::Both,
-            black_castle_rights: CastleRights::Both,
-            draw_plies: 0,
-            full_turns: 2,
-        }
+            })
+            .with_player_turn(Player::White)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::Both)
+            .with_black_castle_rights(CastleRights::Both)
+            .with_draw_plies(0)
+            .with_full_turns(2)
     );
 }
 
@@ -464,6 +491,7 @@ fn en_passant() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move1);
@@ -477,6 +505,7 @@ fn en_passant() {
         en_passant: true,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move2);
@@ -484,30 +513,29 @@ fn en_passant() {
 
     assert_eq!(
         state,
-        GameState {
-            white_board: PlayerBoard {
+        GameState::default()
+            .with_white_board(PlayerBoard {
                 pawns: BitBoard::new(63232),
                 rooks: BitBoard::new(129),
                 knights: BitBoard::new(66),
                 bishops: BitBoard::new(36),
                 queens: BitBoard::new(8),
                 king: BitBoard::new(16),
-            },
-            black_board: PlayerBoard {
+            })
+            .with_black_board(PlayerBoard {
                 pawns: BitBoard::new(524288),
                 rooks: BitBoard::new(0),
                 knights: BitBoard::new(0),
                 bishops: BitBoard::new(0),
                 queens: BitBoard::new(0),
                 king: BitBoard::new(0),
-            },
-            player_turn: Player::White,
-            en_passant: None,
-            white_castle_rights: CastleRights::Both,
-            black_castle_rights: CastleRights::Both,
-            draw_plies: 0,
-            full_turns: 1,
-        }
+            })
+            .with_player_turn(Player::White)
+            .with_en_passant(None)
+            .with_white_castle_rights(CastleRights::Both)
+            .with_black_castle_rights(CastleRights::Both)
+            .with_draw_plies(0)
+            .with_full_turns(1)
     );
 }
 
@@ -527,6 +555,7 @@ fn castle_rights_set_on_move() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move1);
@@ -541,6 +570,7 @@ fn castle_rights_set_on_move() {
         en_passant: false,
         promotion: None,
         castle: None,
+        drop: false,
     };
 
     state = state.apply_move(&move2);
@@ -575,6 +605,7 @@ fn castle_rights_set_on_castle() {
         en_passant: false,
         promotion: None,
         castle: Some(CastleMove::KingSide),
+        drop: false,
     };
 
     state = state.apply_move(&move1);
@@ -589,9 +620,72 @@ fn castle_rights_set_on_castle() {
         en_passant: false,
         promotion: None,
         castle: Some(CastleMove::QueenSide),
+        drop: false,
     };
 
     state = state.apply_move(&move2);
     assert_eq!(state.white_castle_rights, CastleRights::None);
     assert_eq!(state.black_castle_rights, CastleRights::None);
 }
+
+#[test]
+fn make_unmake_move() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/P1PBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 2",
+    ];
+
+    for fen in fens.iter() {
+        let original = parse_fen(fen).unwrap();
+        let mut state = original.clone();
+        for mv in original.legal_moves() {
+            let undo = state.make_move(&mv);
+            assert_eq!(state, original.apply_move(&mv));
+            state.unmake_move(&mv, &undo);
+            assert_eq!(state, original, "{} was not reverted in {}", mv, fen);
+        }
+    }
+}
+
+#[test]
+fn make_unmake_castle() {
+    let original = GameState::default()
+        .with_white_castle_rights(CastleRights::Both)
+        .with_black_castle_rights(CastleRights::Both)
+        .with_draw_plies(4)
+        .with_white_board(
+            PlayerBoard::default()
+                .with_rooks(bitboard::WHITE_START_ROOKS)
+                .with_king(bitboard::WHITE_START_KINGS),
+        )
+        .with_black_board(
+            PlayerBoard::default()
+                .with_rooks(bitboard::BLACK_START_ROOKS)
+                .with_king(bitboard::BLACK_START_KINGS),
+        );
+
+    let qs_move = Move {
+        piece: Piece::King,
+        origin: Square::from_coordinates(File::E, Rank::One),
+        target: Square::from_coordinates(File::C, Rank::One),
+        capture: false,
+        en_passant: false,
+        promotion: None,
+        castle: Some(CastleMove::QueenSide),
+        drop: false,
+    };
+
+    let mut state = original.clone();
+    let undo = state.make_move(&qs_move);
+    assert_eq!(state.white_castle_rights, CastleRights::None);
+    assert_eq!(undo.white_castle_rights, CastleRights::Both);
+    assert_eq!(undo.draw_plies, 4);
+    assert_eq!(undo.captured, None);
+
+    state.unmake_move(&qs_move, &undo);
+    assert_eq!(state, original);
+}
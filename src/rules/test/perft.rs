@@ -0,0 +1,88 @@
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::variant::Variant;
+
+// https://www.chessprogramming.org/Perft_Results
+#[test]
+fn perft_standard() {
+    let positions: [(&str, &[u64]); 5] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902],
+        ),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        ),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        ),
+    ];
+
+    for &(fen, counts) in positions.iter() {
+        let state = parse_fen(fen).unwrap();
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(state.perft(depth + 1), count, "{} at depth {}", fen, depth + 1);
+        }
+    }
+}
+
+
+// known results for the variant start positions and a few positions from play
+#[test]
+fn perft_variants() {
+    let positions: [(Variant, &[u64]); 4] = [
+        (Variant::Atomic, &[20, 400, 8902, 197326]),
+        (Variant::Antichess, &[20, 400, 8067, 153299]),
+        (Variant::Horde, &[8, 128, 1274, 23310]),
+        (Variant::RacingKings, &[21, 421, 11264, 296242]),
+    ];
+
+    for &(variant, counts) in positions.iter() {
+        let state = GameState::variant_start_position(variant);
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(state.perft(depth + 1), count, "{} at depth {}", variant, depth + 1);
+        }
+    }
+
+    // mid-game positions from the python-chess and Fairy-Stockfish variant suites
+    let positions: [(Variant, &str, &[u64]); 8] = [
+        (
+            Variant::Atomic,
+            "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1",
+            &[40, 1238, 45237],
+        ),
+        (
+            Variant::Atomic,
+            "rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1",
+            &[28, 833, 23353],
+        ),
+        (Variant::Antichess, "8/1p6/8/8/8/8/P7/8 w - - 0 1", &[2, 4, 4, 3, 1, 0]),
+        (Variant::Antichess, "8/2p5/8/8/8/8/P7/8 w - - 0 1", &[2, 4, 4, 4, 4, 4, 4, 4]),
+        (
+            Variant::Horde,
+            "4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1",
+            &[30, 241, 6633, 56539],
+        ),
+        (
+            Variant::Horde,
+            "k7/5p2/4p2P/3p2P1/2p2P2/1p2P2P/p2P2P1/2P2P2 w - - 0 1",
+            &[13, 172, 2205, 33781],
+        ),
+        (Variant::RacingKings, "4brn1/2K2k2/8/8/8/8/8/8 w - - 0 1", &[6, 33, 178, 3151]),
+        (Variant::RacingKings, "6r1/2K5/5k2/8/3R4/8/8/8 w - - 0 1", &[17, 322, 5493, 86041]),
+    ];
+
+    for &(variant, fen, counts) in positions.iter() {
+        let state = parse_fen(fen).unwrap().with_variant(variant);
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(state.perft(depth + 1), count, "{} at depth {}", fen, depth + 1);
+        }
+    }
+}
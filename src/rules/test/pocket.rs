@@ -0,0 +1,35 @@
+use board::piece::Piece;
+use rules::pocket::Pocket;
+
+#[test]
+fn pocket_add_remove() {
+    let mut pocket = Pocket::new();
+    assert!(pocket.is_empty());
+
+    pocket.add(Piece::Knight);
+    pocket.add(Piece::Knight);
+    pocket.add(Piece::Pawn);
+    assert_eq!(pocket.count(Piece::Knight), 2);
+    assert_eq!(pocket.count(Piece::Pawn), 1);
+    assert_eq!(pocket.count(Piece::King), 0);
+    assert!(!pocket.is_empty());
+
+    pocket.remove(Piece::Knight);
+    pocket.remove(Piece::Knight);
+    pocket.remove(Piece::Pawn);
+    assert_eq!(pocket, Pocket::new());
+}
+
+#[test]
+#[should_panic]
+fn pocket_add_king() {
+    Pocket::new().add(Piece::King);
+}
+
+#[test]
+#[should_panic]
+fn pocket_remove_missing() {
+    let mut pocket = Pocket::new();
+    pocket.add(Piece::Rook);
+    pocket.remove(Piece::Bishop);
+}
@@ -1,8 +1,10 @@
 use board::bitboard::BitBoard;
 use board::file::File;
+use board::piece::Piece;
 use board::player::Player;
 use board::rank::Rank;
 use board::square::Square;
+use rules::basic_moves;
 use rules::semilegal_moves;
 
 #[test]
@@ -108,6 +110,10 @@ fn semilegal_moves_pawn() {
         Player::White,
         0x80800000
     );
+
+    // pawns on the first rank only move two squares in horde
+    test_pawn!(File::A, Rank::One, File::A, Rank::Two, Player::White, 0x0);
+    test_pawn!(File::A, Rank::One, File::H, Rank::Eight, Player::White, 0x100);
 }
 
 #[test]
@@ -505,3 +511,27 @@ fn semilegal_attack_queen() {
     test_queen!(File::D, Rank::Seven, 0x40000200000000);
     test_queen!(File::F, Rank::Eight, 0x40000000000000);
 }
+
+#[test]
+fn semilegal_attacked_squares() {
+    let square = |file, rank| Square::from_coordinates(file, rank);
+    let blockers =
+        square(File::A, Rank::Three).to_bitboard() | square(File::C, Rank::One).to_bitboard();
+
+    assert_eq!(
+        Piece::Rook.attacked_squares(square(File::A, Rank::One), Player::White, blockers),
+        BitBoard::new(0x10106)
+    );
+    assert_eq!(
+        Piece::Knight.attacked_squares(square(File::A, Rank::One), Player::White, blockers),
+        BitBoard::new(0x20400)
+    );
+    assert_eq!(
+        Piece::Pawn.attacked_squares(square(File::E, Rank::Four), Player::White, blockers),
+        BitBoard::new(0x2800000000)
+    );
+    assert_eq!(
+        Piece::Queen.attacked_squares(square(File::A, Rank::One), Player::Black, blockers),
+        BitBoard::new(0x10106) | basic_moves::bishop(square(File::A, Rank::One))
+    );
+}
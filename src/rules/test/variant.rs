@@ -0,0 +1,455 @@
+use board::file::File;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use board::square::Square;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::variant::{Outcome, Variant};
+
+#[test]
+fn variant_from_str() {
+    for variant in Variant::iter() {
+        assert_eq!(variant.name().parse::<Variant>(), Ok(variant));
+    }
+    assert_eq!("3Check".parse::<Variant>(), Ok(Variant::ThreeCheck));
+    assert!("fourcheck".parse::<Variant>().is_err());
+}
+
+#[test]
+fn outcome_standard() {
+    assert_eq!(GameState::start_position().outcome(), None);
+
+    let mate = parse_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    assert_eq!(mate.outcome(), Some(Outcome::Win(Player::Black)));
+
+    let stale_mate = parse_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(stale_mate.outcome(), Some(Outcome::Draw));
+
+    // the king on the hill means nothing in standard chess
+    let hill = parse_fen("4k3/8/8/8/4K3/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(hill.outcome(), None);
+}
+
+#[test]
+fn king_of_the_hill() {
+    let state = parse_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::KingOfTheHill);
+    assert_eq!(state.outcome(), None);
+
+    let winning_moves = state
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| {
+            state.apply_move(mv).variant_outcome() == Some(Outcome::Win(Player::White))
+        })
+        .count();
+    assert_eq!(winning_moves, 2);
+
+    let state = parse_fen("4k3/8/8/3K4/8/8/8/8 b - - 0 1")
+        .unwrap()
+        .with_variant(Variant::KingOfTheHill);
+    assert_eq!(state.outcome(), Some(Outcome::Win(Player::White)));
+    assert!(state.legal_moves().is_empty());
+}
+
+#[test]
+fn three_check() {
+    let state = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0").unwrap();
+    assert_eq!(state.variant, Variant::ThreeCheck);
+    assert_eq!(state.checks(Player::White), 2);
+    assert_eq!(state.checks(Player::Black), 0);
+    assert_eq!(state.outcome(), None);
+
+    let mut checked = state.clone();
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.to_string() == "A8")
+        .unwrap();
+    let undo = checked.make_move(&mv);
+    assert_eq!(checked.checks(Player::White), 3);
+    assert_eq!(checked.outcome(), Some(Outcome::Win(Player::White)));
+    assert!(checked.legal_moves().is_empty());
+
+    checked.unmake_move(&mv, &undo);
+    assert_eq!(checked, state);
+
+    // checks aren't counted outside of three-check
+    let standard = state.clone().with_variant(Variant::Standard);
+    assert_eq!(standard.apply_move(&mv).checks(Player::White), 2);
+}
+
+#[test]
+fn three_check_fen() {
+    let given = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +1+2").unwrap();
+    let remaining =
+        parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 2+1 0 1").unwrap();
+    assert_eq!(given, remaining);
+    assert_eq!(
+        given,
+        GameState::start_position()
+            .with_variant(Variant::ThreeCheck)
+            .with_white_checks(1)
+            .with_black_checks(2)
+    );
+
+    assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 +4+0").is_err());
+    assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 +1").is_err());
+    assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 1 1").is_err());
+}
+
+#[test]
+fn crazyhouse_fen() {
+    let bracket = parse_fen("4k3/8/8/8/8/8/8/4K2Q~[NPp] w - - 0 1").unwrap();
+    let rank = parse_fen("4k3/8/8/8/8/8/8/4K2Q~/NPp w - - 0 1").unwrap();
+    assert_eq!(bracket, rank);
+    assert_eq!(bracket.variant, Variant::Crazyhouse);
+    assert_eq!(bracket.pocket(Player::White).count(Piece::Knight), 1);
+    assert_eq!(bracket.pocket(Player::White).count(Piece::Pawn), 1);
+    assert_eq!(bracket.pocket(Player::Black).count(Piece::Pawn), 1);
+    assert_eq!(
+        bracket.promoted,
+        Square::from_coordinates(File::H, Rank::One).to_bitboard()
+    );
+
+    let empty = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[-] w KQkq - 0 1").unwrap();
+    assert_eq!(
+        empty,
+        GameState::start_position().with_variant(Variant::Crazyhouse)
+    );
+
+    assert!(parse_fen("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1").is_err());
+    assert!(parse_fen("4k3/8/8/8/8/8/8/4K3[N w - - 0 1").is_err());
+    assert!(parse_fen("4k3/8/8/8/8/8/8/~4K3[] w - - 0 1").is_err());
+}
+
+#[test]
+fn crazyhouse_drops() {
+    // any piece may be dropped on any empty square
+    let state = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[Nn] w KQkq - 0 1").unwrap();
+    assert_eq!(state.legal_moves().len(), 20 + 32);
+
+    // but pawns may not be dropped on the back ranks
+    let state = parse_fen("4k3/8/8/8/8/8/8/4K3[P] w - - 0 1").unwrap();
+    assert_eq!(state.legal_moves().len(), 5 + 48);
+
+    // drops must evade check
+    let state = parse_fen("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1").unwrap();
+    let drops = state.legal_moves().into_iter().filter(|mv| mv.drop).collect::<Vec<_>>();
+    assert_eq!(drops.len(), 3);
+    assert!(drops.iter().all(|mv| mv.target.rank() == Rank::One));
+
+    // nothing is held outside of crazyhouse
+    let state = state.with_variant(Variant::Standard);
+    assert!(state.legal_moves().iter().all(|mv| !mv.drop));
+}
+
+#[test]
+fn crazyhouse_captures() {
+    let state = parse_fen("4k3/8/8/8/8/3q~1r2/4P3/4K3[] w - - 0 1").unwrap();
+    let mut captured = state.clone();
+
+    // capturing a promoted piece takes a pawn in hand
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.to_string() == "D3")
+        .unwrap();
+    let undo = captured.make_move(&mv);
+    assert_eq!(captured.pocket(Player::White).count(Piece::Pawn), 1);
+    assert_eq!(captured.pocket(Player::White).count(Piece::Queen), 0);
+    assert!(captured.promoted.is_empty());
+    captured.unmake_move(&mv, &undo);
+    assert_eq!(captured, state);
+
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.to_string() == "F3")
+        .unwrap();
+    let undo = captured.make_move(&mv);
+    assert_eq!(captured.pocket(Player::White).count(Piece::Rook), 1);
+
+    // the held piece can then be dropped
+    let reply = captured.legal_moves()[0].clone();
+    let reply_undo = captured.make_move(&reply);
+    let drop = captured
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.drop && mv.target.to_string() == "A8")
+        .unwrap();
+    let drop_undo = captured.make_move(&drop);
+    assert_eq!(
+        captured.piece_at(drop.target),
+        Some((Piece::Rook, Player::White))
+    );
+    assert!(captured.pocket(Player::White).is_empty());
+
+    captured.unmake_move(&drop, &drop_undo);
+    captured.unmake_move(&reply, &reply_undo);
+    captured.unmake_move(&mv, &undo);
+    assert_eq!(captured, state);
+}
+
+#[test]
+fn crazyhouse_promotion() {
+    let state = parse_fen("4k3/1P6/8/8/8/8/8/4K3[] w - - 0 1").unwrap();
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.promotion == Some(Piece::Queen))
+        .unwrap();
+    let promoted = state.apply_move(&mv);
+    assert_eq!(promoted.promoted, mv.target.to_bitboard());
+
+    // the promoted marker follows the piece
+    let moved = parse_fen("1Q~6/8/8/8/8/8/8/k3K3[] w - - 0 1").unwrap();
+    let queen_move = moved
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.piece == Piece::Queen && mv.target.to_string() == "B4")
+        .unwrap();
+    assert_eq!(
+        moved.apply_move(&queen_move).promoted,
+        queen_move.target.to_bitboard()
+    );
+
+    // but promotions aren't tracked outside of crazyhouse
+    let state = state.with_variant(Variant::Standard);
+    assert!(state.apply_move(&mv).promoted.is_empty());
+}
+
+#[test]
+fn atomic_explosion() {
+    let state = parse_fen("4k3/8/8/2npb3/3R4/8/8/4K2R w K - 0 1")
+        .unwrap()
+        .with_variant(Variant::Atomic);
+    let mut exploded = state.clone();
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.capture && mv.target.to_string() == "D5")
+        .unwrap();
+
+    // the capturing rook and the pieces around the target explode, except pawns
+    let undo = exploded.make_move(&mv);
+    assert_eq!(
+        exploded,
+        parse_fen("4k3/8/8/8/8/8/8/4K2R b K - 0 1")
+            .unwrap()
+            .with_variant(Variant::Atomic)
+    );
+    exploded.unmake_move(&mv, &undo);
+    assert_eq!(exploded, state);
+    assert!(exploded.is_mailbox_consistent());
+
+    // exploding a rook loses its castle rights
+    let state = parse_fen("4k3/8/8/8/8/8/6p1/4K2R b K - 0 1")
+        .unwrap()
+        .with_variant(Variant::Atomic);
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.capture)
+        .unwrap();
+    assert!(mv.promotion.is_some());
+    assert_eq!(
+        state.apply_move(&mv).white_castle_rights,
+        ::rules::castle_rights::CastleRights::None
+    );
+}
+
+#[test]
+fn atomic_kings() {
+    // kings can't capture, and kings next to each other aren't in check
+    let state = parse_fen("8/8/8/8/8/3kq3/8/4K3 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Atomic);
+    assert!(state.is_check(Player::White));
+    assert!(state.legal_moves().iter().all(|mv| !mv.capture));
+    let adjacent = parse_fen("8/8/8/8/8/4q3/3k4/4K3 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Atomic);
+    assert!(!adjacent.is_check(Player::White));
+
+    // exploding the opponent's king wins, even when in check
+    let state = parse_fen("4k3/3p4/8/8/8/8/8/3QK2r w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Atomic);
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.capture)
+        .unwrap();
+    let won = state.apply_move(&mv);
+    assert_eq!(won.outcome(), Some(Outcome::Win(Player::White)));
+    assert!(won.legal_moves().is_empty());
+
+    // a capture which explodes one's own king is illegal
+    let state = parse_fen("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Atomic);
+    assert!(state.legal_moves().iter().all(|mv| !mv.capture));
+}
+
+#[test]
+fn antichess() {
+    // captures are compulsory, including with the king
+    let state = parse_fen("8/8/8/8/8/3p4/4K3/R7 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Antichess);
+    let moves = state.legal_moves();
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].piece, Piece::King);
+
+    // the king may be captured, and there is no check
+    let state = parse_fen("8/p7/8/8/8/8/4K3/4r3 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Antichess);
+    assert!(!state.is_check(Player::White));
+    let captured = state.apply_move(&state.legal_moves()[0]);
+    assert_eq!(captured.outcome(), None);
+
+    // pawns may promote to kings
+    let state = parse_fen("8/4P3/8/8/8/8/8/k7 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Antichess);
+    assert!(state.legal_moves().iter().any(|mv| mv.promotion == Some(Piece::King)));
+
+    // losing all pieces wins
+    let state = parse_fen("8/8/8/8/8/8/p7/R7 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Antichess);
+    let moves = state.legal_moves();
+    assert_eq!(moves.len(), 1);
+    assert_eq!(
+        state.apply_move(&moves[0]).outcome(),
+        Some(Outcome::Win(Player::Black))
+    );
+
+    // as does having no legal moves
+    let stale_mate = parse_fen("8/8/8/8/8/p7/P7/8 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Antichess);
+    assert_eq!(stale_mate.outcome(), Some(Outcome::Win(Player::White)));
+}
+
+#[test]
+fn variant_start_positions() {
+    for variant in Variant::iter() {
+        let state = GameState::variant_start_position(variant);
+        assert_eq!(state.variant, variant);
+        assert!(state.is_mailbox_consistent());
+        assert_eq!(state.outcome(), None);
+    }
+    assert_eq!(
+        GameState::variant_start_position(Variant::Standard),
+        GameState::start_position()
+    );
+    assert_eq!(
+        GameState::variant_start_position(Variant::Horde)
+            .player_board(Player::White)
+            .pawns
+            .count(),
+        36
+    );
+}
+
+#[test]
+fn horde() {
+    // pawns on the first rank may move two squares, without allowing en passant
+    let state = parse_fen("4k3/8/8/8/8/8/8/P7 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Horde);
+    let moves = state.legal_moves();
+    assert_eq!(moves.len(), 2);
+    let double = moves
+        .into_iter()
+        .find(|mv| mv.target.to_string() == "A3")
+        .unwrap();
+    assert_eq!(state.apply_move(&double).en_passant, None);
+
+    // but not in other variants
+    let standard = parse_fen("4k3/8/8/8/8/8/8/P3K3 w - - 0 1").unwrap();
+    assert!(!standard.legal_moves().contains(&double));
+
+    // white has no king, so is never in check
+    assert!(!state.is_check(Player::White));
+
+    // black wins by capturing all of white's pieces
+    let state = parse_fen("4k3/8/8/8/8/8/1p6/P7 b - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Horde);
+    let capture = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.capture)
+        .unwrap();
+    assert_eq!(
+        state.apply_move(&capture).outcome(),
+        Some(Outcome::Win(Player::Black))
+    );
+
+    // but white still wins by checkmate
+    let mate = parse_fen("3QkQ2/8/4P3/8/8/8/8/8 b - - 0 1")
+        .unwrap()
+        .with_variant(Variant::Horde);
+    assert_eq!(mate.outcome(), Some(Outcome::Win(Player::White)));
+}
+
+#[test]
+fn racing_kings() {
+    // checks may not be given
+    let state = parse_fen("8/8/8/8/8/k7/8/1R5K w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::RacingKings);
+    assert!(state
+        .legal_moves()
+        .iter()
+        .filter(|mv| mv.piece == Piece::Rook)
+        .all(|mv| mv.target.file() != File::A && mv.target.rank() != Rank::Three));
+
+    // reaching the eighth rank wins
+    let state = parse_fen("8/6K1/8/8/8/8/k7/8 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::RacingKings);
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.rank() == Rank::Eight)
+        .unwrap();
+    assert_eq!(
+        state.apply_move(&mv).outcome(),
+        Some(Outcome::Win(Player::White))
+    );
+
+    // unless black can also reach it with the following move
+    let state = parse_fen("8/k5K1/8/8/8/8/8/8 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::RacingKings);
+    let reached = state.apply_move(&mv);
+    assert_eq!(reached.outcome(), None);
+    let reply = reached
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.rank() == Rank::Eight)
+        .unwrap();
+    assert_eq!(reached.apply_move(&reply).outcome(), Some(Outcome::Draw));
+    let reply = reached
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.rank() != Rank::Eight)
+        .unwrap();
+    assert_eq!(
+        reached.apply_move(&reply).outcome(),
+        Some(Outcome::Win(Player::White))
+    );
+
+    // black reaching it first wins outright
+    let state = parse_fen("k7/8/8/8/8/8/6K1/8 w - - 0 1")
+        .unwrap()
+        .with_variant(Variant::RacingKings);
+    assert_eq!(state.outcome(), Some(Outcome::Win(Player::Black)));
+}
@@ -0,0 +1,62 @@
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+
+#[test]
+fn zobrist_pawn_key() {
+    let state = GameState::start_position();
+    assert_ne!(state.pawn_key(), 0);
+    assert_eq!(GameState::default().pawn_key(), 0);
+
+    // the key depends only on the pawns
+    let other_pieces = parse_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 b - - 0 1").unwrap();
+    assert_eq!(other_pieces.pawn_key(), state.pawn_key());
+
+    let moved = parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+    assert_ne!(moved.pawn_key(), state.pawn_key());
+
+    // the key is updated as pawns move, are captured and promote
+    let mut made = state.clone();
+    let mv = state
+        .legal_moves()
+        .into_iter()
+        .find(|mv| mv.target.to_string() == "E4")
+        .unwrap();
+    let undo = made.make_move(&mv);
+    assert_eq!(made.pawn_key(), moved.pawn_key());
+    made.unmake_move(&mv, &undo);
+    assert_eq!(made.pawn_key(), state.pawn_key());
+    let promotion = parse_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    for mv in promotion.legal_moves() {
+        let mut made = promotion.clone();
+        made.make_move(&mv);
+        assert!(made.is_mailbox_consistent(), "{} desynced the pawn key", mv);
+    }
+
+    // pawns of each player have different keys
+    let white = parse_fen("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1").unwrap();
+    let black = parse_fen("4k3/8/8/8/4p3/8/8/4K3 w - - 0 1").unwrap();
+    assert_ne!(white.pawn_key(), black.pawn_key());
+}
+
+#[test]
+fn zobrist_polyglot_key() {
+    // the examples given with the Polyglot book format
+    let examples = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+        ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+        ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+        ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+        ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+        ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+    ];
+    for &(fen, key) in examples.iter() {
+        assert_eq!(parse_fen(fen).unwrap().polyglot_key(), key, "{}", fen);
+    }
+
+    // the en-passant file only counts if a pawn could capture there
+    let no_capture = parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+    assert_eq!(no_capture.unwrap().polyglot_key(), 0x823c9b50fd114196);
+}
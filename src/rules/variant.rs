@@ -0,0 +1,202 @@
+//! Chess variants, which alter the rules of standard chess.
+
+use board::bitboard::BitBoard;
+use board::piece::Piece;
+use board::player::Player;
+use board::rank::Rank;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+use std::vec::IntoIter;
+
+/// The squares a king must reach to win King of the Hill: d4, e4, d5 and e5.
+const HILL: u64 = 0x0000_0018_1800_0000;
+
+/// The number of checks which wins a game of Three-check.
+pub const CHECKS_TO_WIN: u8 = 3;
+
+/// A set of rules which the game is played under.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Variant {
+    /// Standard chess.
+    #[default]
+    Standard,
+
+    /// A player also wins by moving their king to one of the four centre squares.
+    KingOfTheHill,
+
+    /// A player also wins by checking the opponent three times.
+    ThreeCheck,
+
+    /// Captured pieces are held in hand, and may be dropped back onto the board as a move.
+    Crazyhouse,
+
+    /// Captures explode, removing the capturing piece and all non-pawn pieces around the
+    /// target square. A player wins by exploding the opponent's king.
+    Atomic,
+
+    /// Captures are compulsory and the king is an ordinary piece. A player wins by losing
+    /// all their pieces or having no legal moves.
+    Antichess,
+
+    /// White has 36 pawns and no king, and wins by checkmate. Black wins by capturing
+    /// all of white's pieces.
+    Horde,
+
+    /// Checks are not allowed, and a player wins by moving their king to the eighth rank.
+    /// If white gets there first, black may still draw by doing so on the next move.
+    RacingKings,
+}
+
+impl Variant {
+    /// Returns an iterator over all the variants.
+    pub fn iter() -> IntoIter<Variant> {
+        use self::Variant::*;
+        vec![
+            Standard,
+            KingOfTheHill,
+            ThreeCheck,
+            Crazyhouse,
+            Atomic,
+            Antichess,
+            Horde,
+            RacingKings,
+        ].into_iter()
+    }
+
+    /// Returns the name of the variant, as used by the UCI_Variant option.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Variant::Standard => "chess",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::ThreeCheck => "3check",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+            Variant::Horde => "horde",
+            Variant::RacingKings => "racingkings",
+        }
+    }
+
+    /// Returns the FEN of the start position of the variant.
+    pub fn start_fen(&self) -> &'static str {
+        match *self {
+            Variant::Standard | Variant::KingOfTheHill | Variant::Atomic => {
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            }
+            Variant::ThreeCheck => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +0+0",
+            Variant::Crazyhouse => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+            Variant::Antichess => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            Variant::Horde => {
+                "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+            }
+            Variant::RacingKings => "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1",
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, <Self as FromStr>::Err> {
+        Variant::iter()
+            .find(|variant| variant.name().eq_ignore_ascii_case(s))
+            .ok_or(format!("{} is not a known variant", s))
+    }
+}
+
+/// The result of a game which has ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The given player has won.
+    Win(Player),
+
+    /// The game is drawn.
+    Draw,
+}
+
+impl GameState {
+    /// Returns the start position of the given variant.
+    pub fn variant_start_position(variant: Variant) -> Self {
+        parse_fen(variant.start_fen())
+            .expect("variant start positions are valid")
+            .with_variant(variant)
+    }
+
+    /// Returns the outcome of the game through a rule of the variant, if any.
+    ///
+    /// This does not include checkmate or stalemate.
+    pub fn variant_outcome(&self) -> Option<Outcome> {
+        let players = [Player::White, Player::Black];
+        match self.variant {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::KingOfTheHill => players
+                .iter()
+                .cloned()
+                .find(|&player| !(self.player_board(player).king & BitBoard::new(HILL)).is_empty())
+                .map(Outcome::Win),
+            Variant::ThreeCheck => players
+                .iter()
+                .cloned()
+                .find(|&player| self.checks(player) >= CHECKS_TO_WIN)
+                .map(Outcome::Win),
+            Variant::Atomic => players
+                .iter()
+                .cloned()
+                .find(|&player| self.player_board(player.other()).king.is_empty())
+                .map(Outcome::Win),
+            Variant::Antichess => players
+                .iter()
+                .cloned()
+                .find(|&player| self.player_board(player).all().is_empty())
+                .map(Outcome::Win),
+            Variant::Horde => players
+                .iter()
+                .cloned()
+                .find(|&player| self.player_board(player.other()).all().is_empty())
+                .map(Outcome::Win),
+            Variant::RacingKings => {
+                let goal = Rank::Eight.to_bitboard();
+                let reached = |player: Player| !(self.player_board(player).king & goal).is_empty();
+                match (reached(Player::White), reached(Player::Black)) {
+                    (true, true) => Some(Outcome::Draw),
+                    (false, true) => Some(Outcome::Win(Player::Black)),
+                    // black may still draw by reaching the goal with the following move
+                    (true, false) if self.player_turn == Player::Black
+                        && self.generate_legal_moves().iter().any(|mv| {
+                            mv.piece == Piece::King && goal.is_square_set(mv.target)
+                        }) =>
+                    {
+                        None
+                    }
+                    (true, false) => Some(Outcome::Win(Player::White)),
+                    (false, false) => None,
+                }
+            }
+        }
+    }
+
+    /// Returns the outcome of the game, or `None` if the game is still in progress.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(outcome) = self.variant_outcome() {
+            return Some(outcome);
+        }
+        if !self.legal_moves().is_empty() {
+            return None;
+        }
+        if self.variant == Variant::Antichess {
+            Some(Outcome::Win(self.player_turn))
+        } else if self.is_check(self.player_turn) {
+            Some(Outcome::Win(self.player_turn.other()))
+        } else {
+            Some(Outcome::Draw)
+        }
+    }
+}
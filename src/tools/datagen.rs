@@ -0,0 +1,201 @@
+//! Generation of training data through self-play.
+//!
+//! The engine plays games against itself from openings chosen at random, either from a
+//! list of opening positions or by playing random moves from the start position, and
+//! records the quiet positions it reaches with its search score and the final result of
+//! the game. A position is quiet if the side to move is not in check and the move found
+//! is not a capture or promotion, so that its static evaluation should be close to its
+//! search score.
+//!
+//! Positions are written one per line as:
+//!
+//! ```text
+//! <fen> [<result>] <score>
+//! ```
+//!
+//! where the result is 1.0, 0.5 or 0.0 for a white win, draw or black win, and the score
+//! is the search score in centipawns from white's point of view. This is read directly
+//! by the tuner, which ignores the score.
+//!
+//! Each game is played with its own generator seeded from the base seed and the game's
+//! number, so the same options always produce the same data, whatever the number of
+//! threads used.
+
+use board::player::Player;
+use engine::calculator;
+use engine::calculator::{SearchLimit, WIN_SCORE};
+use engine::evaluator::Evaluator;
+use engine::nnue::Network;
+use engine::params::EvalParams;
+use random::Random;
+use rules::fen_parser::to_fen;
+use rules::game_state::GameState;
+use rules::variant::Outcome;
+use std::fmt;
+use std::thread;
+
+/// The plies without a capture or pawn move after which a game is drawn.
+const FIFTY_MOVE_PLIES: u8 = 100;
+
+/// The openings tried before giving up if each one is already decided.
+const MAX_OPENING_TRIES: usize = 1000;
+
+/// The options for generating training data.
+#[derive(Clone, Debug)]
+pub struct DatagenOptions {
+    /// The number of games to play.
+    pub games: usize,
+
+    /// How far to search each move.
+    pub limit: SearchLimit,
+
+    /// The number of random moves played from the opening before the engine takes over.
+    pub random_plies: usize,
+
+    /// The positions to choose openings from, or the start position if empty.
+    pub openings: Vec<GameState>,
+
+    /// The plies after which an unfinished game is drawn.
+    pub max_plies: usize,
+
+    /// The base seed of the random choices.
+    pub seed: u64,
+
+    /// The number of threads playing games.
+    pub threads: usize,
+
+    /// The evaluation weights, used unless a network is given.
+    pub params: EvalParams,
+
+    /// The network evaluation to use, if any.
+    pub network: Option<Network>,
+}
+
+impl Default for DatagenOptions {
+    fn default() -> Self {
+        DatagenOptions {
+            games: 100,
+            limit: SearchLimit::Depth(2),
+            random_plies: 8,
+            openings: Vec::new(),
+            max_plies: 300,
+            seed: 1,
+            threads: 1,
+            params: EvalParams::default(),
+            network: None,
+        }
+    }
+}
+
+/// A position recorded during self-play.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingPosition {
+    /// The position, as a FEN string.
+    pub fen: String,
+
+    /// The search score in centipawns. Positive values favour white.
+    pub score: i32,
+
+    /// The result of the game: 1 for a white win, 0.5 for a draw and 0 for a black win.
+    pub result: f64,
+}
+
+impl fmt::Display for TrainingPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{:.1}] {}", self.fen, self.result, self.score)
+    }
+}
+
+/// Plays the given number of games, returning the positions recorded in the order of
+/// the games they came from, or an error if no undecided opening can be found.
+pub fn generate(options: &DatagenOptions) -> Result<Vec<TrainingPosition>, String> {
+    let threads = options.threads.max(1);
+    let mut games = thread::scope(|scope| -> Result<Vec<_>, String> {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread| {
+                scope.spawn(move || {
+                    (thread..options.games)
+                        .step_by(threads)
+                        .map(|game| play_game(options, game).map(|positions| (game, positions)))
+                        .collect::<Result<Vec<_>, String>>()
+                })
+            })
+            .collect();
+        let mut games = Vec::new();
+        for handle in handles {
+            games.extend(handle.join().expect("Self-play thread panicked")?);
+        }
+        Ok(games)
+    })?;
+    games.sort_by_key(|&(game, _)| game);
+    Ok(games.into_iter().flat_map(|(_, positions)| positions).collect())
+}
+
+/// Plays the game with the given number, returning the quiet positions reached, or an
+/// error if no undecided opening can be found.
+pub fn play_game(options: &DatagenOptions, game: usize) -> Result<Vec<TrainingPosition>, String> {
+    let mut random = Random::new(options.seed ^ (game as u64).wrapping_mul(0x9E37_79B9));
+    let mut state = opening(options, &mut random)?;
+    let mut recorded = Vec::new();
+    let mut plies = 0;
+
+    let result = loop {
+        match state.outcome() {
+            Some(Outcome::Win(Player::White)) => break 1.0,
+            Some(Outcome::Win(Player::Black)) => break 0.0,
+            Some(Outcome::Draw) => break 0.5,
+            None => {}
+        }
+        if plies >= options.max_plies || state.draw_plies >= FIFTY_MOVE_PLIES {
+            break 0.5;
+        }
+
+        let mut evaluator = match options.network {
+            Some(ref network) => Evaluator::network(network, &state),
+            None => Evaluator::handcrafted(&options.params, &state),
+        };
+        let (mv, score) = match calculator::search_limited(&state, &mut evaluator, options.limit)
+        {
+            Some(best) => best,
+            None => break 0.5,
+        };
+        // a forced win ends the game early
+        if score.abs() >= WIN_SCORE {
+            break if score > 0 { 1.0 } else { 0.0 };
+        }
+        if !state.is_check(state.player_turn) && !mv.capture && mv.promotion.is_none() {
+            recorded.push((to_fen(&state), score));
+        }
+        state.make_move(&mv);
+        plies += 1;
+    };
+
+    info!("Game {}: {} plies, result {}", game + 1, plies, result);
+    Ok(recorded
+        .into_iter()
+        .map(|(fen, score)| TrainingPosition { fen, score, result })
+        .collect())
+}
+
+// chooses an opening and plays random moves from it, retrying a limited number of
+// times if the game ends
+fn opening(options: &DatagenOptions, random: &mut Random) -> Result<GameState, String> {
+    for _ in 0..MAX_OPENING_TRIES {
+        let mut state = if options.openings.is_empty() {
+            GameState::start_position()
+        } else {
+            options.openings[random.below(options.openings.len())].clone()
+        };
+        for _ in 0..options.random_plies {
+            let moves = state.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            state.make_move(&moves[random.below(moves.len())]);
+        }
+        if state.outcome().is_none() {
+            return Ok(state);
+        }
+    }
+    Err(format!("No undecided opening found in {} tries", MAX_OPENING_TRIES))
+}
@@ -0,0 +1,270 @@
+//! Command line tools.
+//!
+//! Running the engine with arguments invokes one of these tools instead of starting a
+//! UCI session. The first argument names the tool, and the rest are passed to it.
+
+use engine::book::BookBuilder;
+use engine::calculator::SearchLimit;
+use engine::heuristic;
+use engine::nnue::Network;
+use engine::params::EvalParams;
+use engine::tablebase;
+use engine::tablebase::{Material, Tablebase};
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use std::fs;
+use std::str::FromStr;
+use std::thread;
+
+pub mod datagen;
+pub mod pgn;
+pub mod tuner;
+
+#[cfg(test)]
+mod test;
+
+/// The number of tuning passes made when `tune` does not specify one.
+const DEFAULT_TUNING_PASSES: usize = 100;
+
+/// The number of games a move must be played in to enter a book, when `book` does not
+/// specify one.
+const DEFAULT_BOOK_MIN_COUNT: u32 = 3;
+
+/// The plies of each game added to a book, when `book` does not specify a limit.
+const DEFAULT_BOOK_MAX_PLIES: usize = 40;
+
+/// Runs the tool named by the first argument, returning its output.
+///
+/// The tools are:
+/// - `trace [--params <file>] [fen]`: breaks down the evaluation of the given position,
+///   or of the start position if none is given.
+/// - `params [file]`: prints the evaluation weights as TOML, either the defaults or those
+///   loaded from the given file with the defaults filled in.
+/// - `tune <positions> <output> [--params <file>] [--passes <n>]`: tunes the evaluation
+///   weights against a file of positions labelled with game results, starting from the
+///   given weights or the defaults, and writes the tuned weights to the output file.
+/// - `datagen <output> [--games <n>] [--depth <n> | --nodes <n>] [--random-plies <n>]
+///   [--openings <file>] [--max-plies <n>] [--seed <n>] [--threads <n>] [--params <file>]
+///   [--network <file>]`: plays games against itself and writes the quiet positions
+///   reached, labelled with their search scores and game results, to the output file.
+///   Openings are read one FEN per line.
+/// - `tablebase <directory> [material...]`: generates the endgame tables of the given
+///   materials, such as "KQvKR", or of every material with up to four pieces, along with
+///   the smaller tables they depend on, and writes them to the directory.
+/// - `book <output> <pgn>... [--min-count <n>] [--max-plies <n>]`: builds a Polyglot
+///   opening book from the first plies of the games in the given PGN files, keeping the
+///   moves played in at least the given number of games, and writes it to the output file.
+pub fn run(args: &[String]) -> Result<String, String> {
+    match args.split_first() {
+        Some((command, rest)) if command == "trace" => trace(rest),
+        Some((command, rest)) if command == "params" => params(rest),
+        Some((command, rest)) if command == "tune" => tune(rest),
+        Some((command, rest)) if command == "datagen" => datagen(rest),
+        Some((command, rest)) if command == "tablebase" => generate_tablebase(rest),
+        Some((command, rest)) if command == "book" => build_book(rest),
+        Some((command, _)) => Err(format!("Unknown command: '{}'", command)),
+        None => Err("No command given".to_string()),
+    }
+}
+
+// prints the evaluation trace of a position, given as a FEN which may span several
+// arguments
+fn trace(args: &[String]) -> Result<String, String> {
+    let (fen, options) = split_options(args)?;
+    let mut params = EvalParams::default();
+    for &(name, value) in options.iter() {
+        match name {
+            "--params" => params = EvalParams::load(value)?,
+            _ => return Err(format!("Unknown option: '{}'", name)),
+        }
+    }
+    let state = if fen.is_empty() {
+        GameState::start_position()
+    } else {
+        let fen: Vec<&str> = fen.iter().map(|field| field.as_str()).collect();
+        parse_fen(&fen.join(" "))?
+    };
+    Ok(heuristic::trace(&state, &params).to_string())
+}
+
+// prints the default weights, or those loaded from a file
+fn params(args: &[String]) -> Result<String, String> {
+    let params = match args.first() {
+        Some(path) => EvalParams::load(path)?,
+        None => EvalParams::default(),
+    };
+    params.to_toml()
+}
+
+// tunes the weights against a file of labelled positions, reporting the error before and
+// after
+fn tune(args: &[String]) -> Result<String, String> {
+    let (paths, options) = split_options(args)?;
+    let (positions_path, output_path) = match paths.as_slice() {
+        [positions, output] => (positions, output),
+        _ => return Err(format!("Expected a positions file and an output file: {:?}", paths)),
+    };
+    let mut params = EvalParams::default();
+    let mut passes = DEFAULT_TUNING_PASSES;
+    for &(name, value) in options.iter() {
+        match name {
+            "--params" => params = EvalParams::load(value)?,
+            "--passes" => passes = parse_number(name, value)?,
+            _ => return Err(format!("Unknown option: '{}'", name)),
+        }
+    }
+
+    let text = fs::read_to_string(positions_path)
+        .map_err(|e| format!("{}: {}", positions_path, e))?;
+    let positions = tuner::parse_positions(&text)?;
+    let k = tuner::optimal_k(&positions, &params);
+    let before = tuner::error(&positions, &params, k);
+    let tuned = tuner::tune(&positions, &params, k, passes);
+    let after = tuner::error(&positions, &tuned, k);
+    fs::write(output_path, tuned.to_toml()?).map_err(|e| format!("{}: {}", output_path, e))?;
+
+    Ok(format!(
+        "Positions: {}\nK: {:.3}\nError before: {:.8}\nError after: {:.8}\nWrote {}",
+        positions.len(),
+        k,
+        before,
+        after,
+        output_path
+    ))
+}
+
+// generates training data through self-play
+fn datagen(args: &[String]) -> Result<String, String> {
+    let (paths, options) = split_options(args)?;
+    let output_path = match paths.as_slice() {
+        [output] => output,
+        _ => return Err(format!("Expected an output file: {:?}", paths)),
+    };
+    let mut datagen_options = datagen::DatagenOptions {
+        threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        ..datagen::DatagenOptions::default()
+    };
+    for &(name, value) in options.iter() {
+        match name {
+            "--games" => datagen_options.games = parse_number(name, value)?,
+            "--depth" => datagen_options.limit = SearchLimit::Depth(parse_number(name, value)?),
+            "--nodes" => datagen_options.limit = SearchLimit::Nodes(parse_number(name, value)?),
+            "--random-plies" => datagen_options.random_plies = parse_number(name, value)?,
+            "--openings" => datagen_options.openings = read_openings(value)?,
+            "--max-plies" => datagen_options.max_plies = parse_number(name, value)?,
+            "--seed" => datagen_options.seed = parse_number(name, value)?,
+            "--threads" => datagen_options.threads = parse_number(name, value)?,
+            "--params" => datagen_options.params = EvalParams::load(value)?,
+            "--network" => datagen_options.network = Some(Network::load(value)?),
+            _ => return Err(format!("Unknown option: '{}'", name)),
+        }
+    }
+
+    let positions = datagen::generate(&datagen_options)?;
+    let text: String = positions.iter().map(|position| format!("{}\n", position)).collect();
+    fs::write(output_path, text).map_err(|e| format!("{}: {}", output_path, e))?;
+    Ok(format!(
+        "Games: {}\nPositions: {}\nWrote {}",
+        datagen_options.games,
+        positions.len(),
+        output_path
+    ))
+}
+
+// generates endgame tables and writes them to a directory
+fn generate_tablebase(args: &[String]) -> Result<String, String> {
+    let (paths, options) = split_options(args)?;
+    if let Some(&(name, _)) = options.first() {
+        return Err(format!("Unknown option: '{}'", name));
+    }
+    let (directory, names) = match paths.split_first() {
+        Some((directory, names)) => (directory, names),
+        None => return Err("Expected an output directory".to_string()),
+    };
+    let materials = if names.is_empty() {
+        tablebase::all_materials(tablebase::MAX_PIECES)
+    } else {
+        names
+            .iter()
+            .map(|name| Material::from_name(name))
+            .collect::<Result<Vec<Material>, String>>()?
+    };
+
+    let mut tablebase = Tablebase::new();
+    let mut generated = Vec::new();
+    for material in materials.iter() {
+        generated.extend(tablebase.generate(material)?);
+    }
+    let written = tablebase.save_dir(directory)?;
+    let names: Vec<String> = generated.iter().map(|material| material.name()).collect();
+    Ok(format!("Generated: {}\nWrote {} tables to {}", names.join(" "), written.len(), directory))
+}
+
+// builds an opening book from PGN files
+fn build_book(args: &[String]) -> Result<String, String> {
+    let (paths, options) = split_options(args)?;
+    let (output_path, pgn_paths) = match paths.split_first() {
+        Some((output, pgns)) if !pgns.is_empty() => (output, pgns),
+        _ => return Err(format!("Expected an output file and PGN files: {:?}", paths)),
+    };
+    let mut min_count = DEFAULT_BOOK_MIN_COUNT;
+    let mut max_plies = DEFAULT_BOOK_MAX_PLIES;
+    for &(name, value) in options.iter() {
+        match name {
+            "--min-count" => min_count = parse_number(name, value)?,
+            "--max-plies" => max_plies = parse_number(name, value)?,
+            _ => return Err(format!("Unknown option: '{}'", name)),
+        }
+    }
+
+    let mut builder = BookBuilder::new();
+    let mut games = 0;
+    for path in pgn_paths.iter() {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        for game in pgn::parse_games(&text).map_err(|e| format!("{}: {}", path, e))? {
+            builder.add_game(&game.start, &game.moves, game.outcome, max_plies);
+            games += 1;
+        }
+    }
+    let book = builder.build(min_count);
+    book.save(output_path)?;
+    Ok(format!("Games: {}\nEntries: {}\nWrote {}", games, book.len(), output_path))
+}
+
+// reads opening positions from a file with one FEN per line, which may omit its move
+// clocks
+fn read_openings(path: &str) -> Result<Vec<GameState>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_whitespace().count() {
+            4 => parse_fen(&format!("{} 0 1", line)),
+            _ => parse_fen(line),
+        })
+        .collect()
+}
+
+// parses the numeric value of an option
+fn parse_number<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+// the "--name value" options given to a tool
+type Options<'a> = Vec<(&'a str, &'a String)>;
+
+// splits arguments into positional arguments and options
+fn split_options(args: &[String]) -> Result<(Vec<&String>, Options<'_>), String> {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+            options.push((arg.as_str(), value));
+        } else {
+            positional.push(arg);
+        }
+    }
+    Ok((positional, options))
+}
@@ -0,0 +1,213 @@
+//! Reading of games in Portable Game Notation.
+//!
+//! Only what is needed to replay the main line of each game is read: the FEN tag, if the
+//! game starts from a set-up position, its moves in standard algebraic notation and its
+//! result. Other tags, comments, variations, move numbers and annotations are skipped.
+
+use board::piece::Piece;
+use board::player::Player;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::move_application::{CastleMove, Move};
+use rules::variant::Outcome;
+
+/// A game read from a PGN file.
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    /// The position the game starts from.
+    pub start: GameState,
+
+    /// The moves of the main line.
+    pub moves: Vec<Move>,
+
+    /// The result of the game, if it is known.
+    pub outcome: Option<Outcome>,
+}
+
+/// Reads every game from the text of a PGN file.
+///
+/// A game ends at its result, or where the tags of the next game begin. A game with a
+/// move that cannot be read or is not legal, such as a null move or a move of a variant
+/// game, is skipped with a warning.
+pub fn parse_games(text: &str) -> Result<Vec<PgnGame>, String> {
+    let mut games = Vec::new();
+    let mut start = GameState::start_position();
+    // the state reached and the moves played in the game being read, if it has begun
+    let mut game: Option<(GameState, Vec<Move>)> = None;
+    // whether the rest of a game that cannot be replayed is being skipped
+    let mut skipping = false;
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                chars.by_ref().find(|&c| c == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth > 0 || c.is_whitespace() => {}
+            '[' => {
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if let Some((_, moves)) = game.take() {
+                    games.push(PgnGame {
+                        start,
+                        moves,
+                        outcome: None,
+                    });
+                    start = GameState::start_position();
+                } else if skipping {
+                    skipping = false;
+                    start = GameState::start_position();
+                }
+                if let Some(fen) = tag_value(&tag, "FEN") {
+                    start = parse_fen(&fen)?;
+                }
+            }
+            _ => {
+                let mut token = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{};()[".contains(c) {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                if skipping {
+                    if parse_result(&token).is_some() {
+                        skipping = false;
+                        start = GameState::start_position();
+                    }
+                    continue;
+                }
+                let (mut state, mut moves) =
+                    game.take().unwrap_or_else(|| (start.clone(), Vec::new()));
+                if let Some(outcome) = parse_result(&token) {
+                    games.push(PgnGame {
+                        start,
+                        moves,
+                        outcome,
+                    });
+                    start = GameState::start_position();
+                    continue;
+                }
+                // move numbers may be joined to the move that follows them
+                let san = match token.rfind('.') {
+                    Some(index) => &token[index + 1..],
+                    None => &token,
+                };
+                if !san.is_empty() && !san.starts_with('$') {
+                    match parse_san(&state, san) {
+                        Ok(mv) => {
+                            state.make_move(&mv);
+                            moves.push(mv);
+                        }
+                        Err(e) => {
+                            warn!("Skipping game: {} after {} plies: {}", token, moves.len(), e);
+                            skipping = true;
+                            continue;
+                        }
+                    }
+                }
+                game = Some((state, moves));
+            }
+        }
+    }
+    if let Some((_, moves)) = game {
+        games.push(PgnGame {
+            start,
+            moves,
+            outcome: None,
+        });
+    }
+    Ok(games)
+}
+
+/// Parses a move in standard algebraic notation, such as "Nbd7", "exd8=Q+" or "O-O",
+/// returning it if it is legal in the given state.
+pub fn parse_san(state: &GameState, text: &str) -> Result<Move, String> {
+    let san = text.trim_end_matches(|c| "+#!?".contains(c));
+    let castle = match san {
+        "O-O" | "0-0" => Some(CastleMove::KingSide),
+        "O-O-O" | "0-0-0" => Some(CastleMove::QueenSide),
+        _ => None,
+    };
+    let (piece, rest) = match san.chars().next().and_then(piece_of) {
+        Some(piece) => (piece, &san[1..]),
+        None => (Piece::Pawn, san),
+    };
+    let (rest, promotion) = match rest.char_indices().last() {
+        Some((index, c)) if piece == Piece::Pawn && piece_of(c).is_some() => {
+            (rest[..index].trim_end_matches('='), piece_of(c))
+        }
+        _ => (rest, None),
+    };
+    let squares: Vec<char> = rest.chars().filter(|&c| c != 'x' && c != '-').collect();
+    if castle.is_none() && squares.len() < 2 {
+        return Err(format!("{} is not a valid move", text));
+    }
+
+    let matches: Vec<Move> = state
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| match castle {
+            Some(castle_move) => mv.castle == Some(castle_move),
+            None => {
+                let (from, to) = squares.split_at(squares.len() - 2);
+                let target: String = to.iter().collect();
+                mv.castle.is_none()
+                    && !mv.drop
+                    && mv.piece == piece
+                    && mv.promotion == promotion
+                    && mv.target.to_string().eq_ignore_ascii_case(&target)
+                    && from.iter().all(|&c| {
+                        mv.origin
+                            .to_string()
+                            .to_lowercase()
+                            .contains(c.to_ascii_lowercase())
+                    })
+            }
+        })
+        .collect();
+    match matches.len() {
+        1 => Ok(matches.into_iter().next().unwrap()),
+        0 => Err(format!("{} is not a legal move", text)),
+        _ => Err(format!("{} is ambiguous", text)),
+    }
+}
+
+// the piece named by an upper case letter
+fn piece_of(c: char) -> Option<Piece> {
+    match c {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+// the outcome of a result token, or None if the token is not a result
+fn parse_result(token: &str) -> Option<Option<Outcome>> {
+    match token {
+        "1-0" => Some(Some(Outcome::Win(Player::White))),
+        "0-1" => Some(Some(Outcome::Win(Player::Black))),
+        "1/2-1/2" => Some(Some(Outcome::Draw)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+// the value of a tag such as `FEN "8/8/8/8/8/8/8/8 w - - 0 1"`, if it has the given name
+fn tag_value(tag: &str, name: &str) -> Option<String> {
+    let mut parts = tag.trim().splitn(2, char::is_whitespace);
+    match (parts.next(), parts.next()) {
+        (Some(tag_name), Some(value)) if tag_name == name => {
+            Some(value.trim().trim_matches('"').to_string())
+        }
+        _ => None,
+    }
+}
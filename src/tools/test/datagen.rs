@@ -0,0 +1,63 @@
+use engine::calculator::SearchLimit;
+use rules::fen_parser::parse_fen;
+use tools::datagen;
+use tools::datagen::DatagenOptions;
+use tools::tuner;
+
+fn options() -> DatagenOptions {
+    DatagenOptions {
+        games: 4,
+        limit: SearchLimit::Depth(1),
+        max_plies: 40,
+        ..DatagenOptions::default()
+    }
+}
+
+#[test]
+fn datagen_deterministic() {
+    let positions = datagen::generate(&options()).unwrap();
+    assert!(!positions.is_empty());
+    let threaded = DatagenOptions {
+        threads: 3,
+        ..options()
+    };
+    assert_eq!(datagen::generate(&threaded).unwrap(), positions);
+
+    let reseeded = DatagenOptions {
+        seed: 2,
+        ..options()
+    };
+    assert_ne!(datagen::generate(&reseeded).unwrap(), positions);
+}
+
+#[test]
+fn datagen_positions() {
+    let options = DatagenOptions {
+        limit: SearchLimit::Nodes(50),
+        openings: vec![
+            parse_fen("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap(),
+            parse_fen("4k3/ppp5/8/8/8/8/8/4K3 w - - 0 1").unwrap(),
+        ],
+        random_plies: 2,
+        ..options()
+    };
+    let positions = datagen::generate(&options).unwrap();
+    assert!(!positions.is_empty());
+
+    // every position can be read by the tuner and is quiet
+    for position in positions.iter() {
+        let labelled = tuner::parse_position(&position.to_string()).unwrap();
+        assert_eq!(labelled.result, position.result);
+        assert!(!labelled.state.is_check(labelled.state.player_turn));
+    }
+}
+
+#[test]
+fn datagen_decided_openings() {
+    // every opening is already checkmate, so no game can be played
+    let options = DatagenOptions {
+        openings: vec![parse_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").unwrap()],
+        ..options()
+    };
+    assert!(datagen::generate(&options).is_err());
+}
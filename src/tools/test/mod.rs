@@ -0,0 +1,136 @@
+//! Tests for the tools module.
+
+use engine::book::Book;
+use engine::params::EvalParams;
+use rules::game_state::GameState;
+use std::env;
+use std::fs;
+use tools;
+
+mod datagen;
+mod pgn;
+mod tuner;
+
+// runs a tool from a command line split on spaces
+fn run(line: &str) -> Result<String, String> {
+    let args: Vec<String> = line.split(' ').map(|arg| arg.to_string()).collect();
+    tools::run(&args)
+}
+
+#[test]
+fn tools_trace() {
+    let output = run("trace").unwrap();
+    assert!(output.contains("Material"));
+    assert!(output.ends_with("Score: 0 (white side)"));
+
+    let output = run("trace 4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    assert!(output.contains("Phase: 4/24"));
+    assert!(run("trace 4k3/8/8 w").is_err());
+}
+
+#[test]
+fn tools_params() {
+    let output = run("params").unwrap();
+    assert!(output.contains("[pawns]"));
+    assert!(run("params /nonexistent/gladius.toml").is_err());
+    assert!(run("trace --params").is_err());
+}
+
+#[test]
+fn tools_tune() {
+    let positions = env::temp_dir().join("gladius_tools_tune_positions.txt");
+    let output = env::temp_dir().join("gladius_tools_tune_output.toml");
+    fs::write(
+        &positions,
+        "4k3/8/8/8/8/8/PP6/4K3 w - - 0 1 [1.0]\n4k3/pp6/8/8/8/8/8/4K3 w - - 0 1 [0.0]\n",
+    ).unwrap();
+    let report = run(&format!(
+        "tune {} {} --passes 1",
+        positions.display(),
+        output.display()
+    ));
+    let tuned = EvalParams::load(output.to_str().unwrap());
+    fs::remove_file(&positions).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    let report = report.unwrap();
+    assert!(report.starts_with("Positions: 2\n"));
+    assert!(report.contains("Error before: "));
+    assert!(report.contains("Error after: "));
+    assert_ne!(tuned.unwrap(), EvalParams::default());
+
+    assert!(run("tune").is_err());
+    assert!(run("tune /nonexistent/positions.txt out.toml").is_err());
+    assert!(run("tune a b --unknown 1").is_err());
+}
+
+#[test]
+fn tools_datagen() {
+    let output = env::temp_dir().join("gladius_tools_datagen_output.txt");
+    let report = run(&format!(
+        "datagen {} --games 2 --depth 1 --max-plies 10 --threads 2",
+        output.display()
+    ));
+    let text = fs::read_to_string(&output);
+    fs::remove_file(&output).unwrap();
+
+    let report = report.unwrap();
+    assert!(report.starts_with("Games: 2\n"));
+    assert_eq!(
+        report.lines().nth(1),
+        Some(format!("Positions: {}", text.unwrap().lines().count()).as_str())
+    );
+
+    assert!(run("datagen").is_err());
+    assert!(run("datagen out.txt --games many").is_err());
+    assert!(run("datagen out.txt --openings /nonexistent/openings.txt").is_err());
+}
+
+#[test]
+fn tools_tablebase() {
+    let output = env::temp_dir().join("gladius_tools_tablebase");
+    let report = run(&format!("tablebase {} KvKN", output.display()));
+    let written = fs::read_dir(&output).map(|entries| entries.count());
+    fs::remove_dir_all(&output).unwrap();
+    assert_eq!(
+        report.unwrap(),
+        format!("Generated: KNvK\nWrote 1 tables to {}", output.display())
+    );
+    assert_eq!(written.unwrap(), 1);
+
+    assert!(run("tablebase").is_err());
+    assert!(run("tablebase out KXvK").is_err());
+    assert!(run("tablebase out KQRvKR").is_err());
+    assert!(run("tablebase out --pieces 5").is_err());
+}
+
+#[test]
+fn tools_book() {
+    let pgn = env::temp_dir().join("gladius_tools_book.pgn");
+    let output = env::temp_dir().join("gladius_tools_book.bin");
+    fs::write(&pgn, "1. e4 e5 2. Nf3 1-0\n1. e4 c5 1/2-1/2\n1. d4 d5 1-0\n").unwrap();
+    let report = run(&format!(
+        "book {} {} --min-count 2 --max-plies 1",
+        output.display(),
+        pgn.display()
+    ));
+    let book = Book::load(&output.display().to_string());
+    fs::remove_file(&pgn).unwrap();
+    fs::remove_file(&output).unwrap();
+    assert_eq!(report.unwrap(), format!("Games: 3\nEntries: 1\nWrote {}", output.display()));
+    let start = GameState::start_position();
+    let moves = book.unwrap().moves(&start);
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].0.to_string(), ": E2 -> E4");
+    assert_eq!(moves[0].1, 3);
+
+    assert!(run("book out.bin").is_err());
+    assert!(run("book out.bin /nonexistent/gladius.pgn").is_err());
+    assert!(run("book out.bin games.pgn --min-count few").is_err());
+}
+
+#[test]
+fn tools_unknown() {
+    assert!(run("unknown").is_err());
+    assert!(tools::run(&[]).is_err());
+}
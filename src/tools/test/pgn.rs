@@ -0,0 +1,125 @@
+use board::piece::Piece;
+use board::player::Player;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::move_application::CastleMove;
+use rules::variant::Outcome;
+use tools::pgn::{parse_games, parse_san};
+
+// the origin and target of a move in lower case, such as "e2e4"
+fn squares(state: &GameState, san: &str) -> String {
+    let mv = parse_san(state, san).unwrap();
+    format!("{}{}", mv.origin, mv.target).to_lowercase()
+}
+
+#[test]
+fn pgn_parse_san() {
+    let state = GameState::start_position();
+    assert_eq!(squares(&state, "e4"), "e2e4");
+    assert_eq!(squares(&state, "Nf3"), "g1f3");
+    assert_eq!(squares(&state, "Nc3!?"), "b1c3");
+    assert!(parse_san(&state, "e5").is_err());
+    assert!(parse_san(&state, "Qd3").is_err());
+    assert!(parse_san(&state, "x").is_err());
+
+    // disambiguation by file, rank or both
+    let state = parse_fen("4k3/8/8/8/8/2N3N1/8/N3K1N1 w - - 0 1").unwrap();
+    assert!(parse_san(&state, "Ne2").is_err());
+    assert_eq!(squares(&state, "Nce2"), "c3e2");
+    assert!(parse_san(&state, "Nge2").is_err());
+    assert_eq!(squares(&state, "Ng3e2"), "g3e2");
+    assert_eq!(squares(&state, "N1xe2"), "g1e2");
+
+    let state = parse_fen("r3k3/1P6/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+    let promotion = parse_san(&state, "bxa8=N+").unwrap();
+    assert_eq!(promotion.promotion, Some(Piece::Knight));
+    assert!(parse_san(&state, "bxa8Q").unwrap().capture);
+    assert!(parse_san(&state, "bxa8").is_err());
+    assert_eq!(
+        parse_san(&state, "O-O").unwrap().castle,
+        Some(CastleMove::KingSide)
+    );
+    assert_eq!(
+        parse_san(&state, "0-0").unwrap().castle,
+        Some(CastleMove::KingSide)
+    );
+    assert!(parse_san(&state, "O-O-O").is_err());
+}
+
+#[test]
+fn pgn_parse_games() {
+    let text = r#"
+[Event "First"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) Nc6 $1 ; a comment
+3.Bb5 a6 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"]
+
+1. O-O-O Kf7 1/2-1/2
+
+[Event "Unfinished"]
+
+1. d4 d5 2. c4
+"#;
+    let games = parse_games(text).unwrap();
+    assert_eq!(games.len(), 3);
+
+    assert_eq!(games[0].start, GameState::start_position());
+    assert_eq!(games[0].moves.len(), 6);
+    assert_eq!(games[0].outcome, Some(Outcome::Win(Player::White)));
+    let mut state = games[0].start.clone();
+    for mv in games[0].moves.iter() {
+        state.make_move(mv);
+    }
+    assert_eq!(
+        state,
+        parse_fen("r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4").unwrap()
+    );
+
+    assert_eq!(
+        games[1].start,
+        parse_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap()
+    );
+    assert_eq!(games[1].moves[0].castle, Some(CastleMove::QueenSide));
+    assert_eq!(games[1].outcome, Some(Outcome::Draw));
+
+    assert_eq!(games[2].start, GameState::start_position());
+    assert_eq!(games[2].moves.len(), 3);
+    assert_eq!(games[2].outcome, None);
+
+    assert!(parse_games("1. e4 e4").unwrap().is_empty());
+    assert!(parse_games("").unwrap().is_empty());
+}
+
+#[test]
+fn pgn_skip_games() {
+    // games with null or illegal moves are skipped, whether they end with a result or
+    // with the tags of the next game
+    let text = r#"
+[Event "Null move"]
+
+1. e4 -- 2. d4 e5 1-0
+
+[Event "Illegal"]
+
+1. e4 e4 2. d4
+
+[Event "Legal"]
+
+1. d4 d5 0-1
+
+1. Ke2 Kd7 2. Zz9 *
+
+1. c4 *
+"#;
+    let games = parse_games(text).unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].moves.len(), 2);
+    assert_eq!(games[0].outcome, Some(Outcome::Win(Player::Black)));
+    assert_eq!(games[1].moves.len(), 1);
+    assert_eq!(games[1].outcome, None);
+}
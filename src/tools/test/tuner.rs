@@ -0,0 +1,67 @@
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use tools::tuner;
+use tools::tuner::LabelledPosition;
+
+const POSITIONS: &str = "
+4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1 [1.0]
+4k3/ppp5/8/8/8/8/8/4K3 w - - 0 1 [0.0]
+4k3/8/8/8/8/8/P7/4K3 w - - 0 1 [1.0]
+4k3/p7/8/8/8/8/8/4K3 w - - 0 1 [0.0]
+4k3/p7/8/8/8/8/P7/4K3 w - - 0 1 [0.5]
+";
+
+#[test]
+fn tuner_parse_position() {
+    let fen = "4k3/8/8/8/8/8/P7/4K3 w - - 0 1";
+    let state = parse_fen(fen).unwrap();
+
+    let position = tuner::parse_position(&format!("{} [0.5]", fen)).unwrap();
+    assert_eq!(position.state, state);
+    assert_eq!(position.result, 0.5);
+
+    let position = tuner::parse_position("4k3/8/8/8/8/8/P7/4K3 w - - c9 \"1-0\";").unwrap();
+    assert_eq!(position.state, state);
+    assert_eq!(position.result, 1.0);
+
+    let position = tuner::parse_position(&format!("{} 1/2-1/2", fen)).unwrap();
+    assert_eq!(position.result, 0.5);
+    let position = tuner::parse_position(&format!("{} 0-1", fen)).unwrap();
+    assert_eq!(position.result, 0.0);
+
+    assert!(tuner::parse_position(fen).is_err());
+    assert!(tuner::parse_position(&format!("{} [2.0]", fen)).is_err());
+    assert!(tuner::parse_position("4k3/8/8 w - - [1.0]").is_err());
+
+    assert_eq!(tuner::parse_positions(POSITIONS).unwrap().len(), 5);
+    let error = tuner::parse_positions("\n4k3/8/8/8/8/8/P7/4K3 w - - 0 1 [1.0]\nfoo").unwrap_err();
+    assert!(error.starts_with("Line 3"));
+}
+
+#[test]
+fn tuner_error() {
+    assert_eq!(tuner::sigmoid(0, 1.0), 0.5);
+    assert!(tuner::sigmoid(100, 1.0) > 0.6);
+    assert!(tuner::sigmoid(-100, 1.0) < 0.4);
+    assert!((tuner::sigmoid(400, 1.0) - 10.0 / 11.0).abs() < 1e-9);
+
+    let params = EvalParams::default();
+    let positions: Vec<LabelledPosition> = tuner::parse_positions(POSITIONS).unwrap();
+    assert_eq!(tuner::error(&[], &params, 1.0), 0.0);
+    let k = tuner::optimal_k(&positions, &params);
+    assert!(k > 0.0 && k <= 3.0);
+    assert!(tuner::error(&positions, &params, k) <= tuner::error(&positions, &params, 1.0));
+}
+
+#[test]
+fn tuner_tune() {
+    let params = EvalParams::default();
+    let positions = tuner::parse_positions(POSITIONS).unwrap();
+    let k = 0.5;
+    let tuned = tuner::tune(&positions, &params, k, 2);
+    assert!(tuner::error(&positions, &tuned, k) < tuner::error(&positions, &params, k));
+    assert!(tuned.material.pawn.eg > params.material.pawn.eg);
+
+    // no passes leaves the weights unchanged
+    assert_eq!(tuner::tune(&positions, &params, k, 0), params);
+}
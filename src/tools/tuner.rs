@@ -0,0 +1,183 @@
+//! Texel-style tuning of the evaluation weights.
+//!
+//! Each position in the training set is labelled with the result of the game it was
+//! taken from. The static evaluation is mapped to an expected result with a sigmoid, and
+//! the tuner minimises the mean squared error between the expected and actual results
+//! by nudging each weight up or down in turn for as long as the error keeps falling.
+
+use engine::heuristic;
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+
+/// A position labelled with the result of its game.
+#[derive(Clone, Debug)]
+pub struct LabelledPosition {
+    /// The position.
+    pub state: GameState,
+
+    /// The result of the game from white's point of view: 1 for a win, 0.5 for a draw
+    /// and 0 for a loss.
+    pub result: f64,
+}
+
+/// Parses a position from a line holding a FEN and a game result.
+///
+/// The result may be written as "1-0", "0-1" or "1/2-1/2", optionally quoted as in EPD
+/// files, or as a number in brackets such as "[0.5]". The FEN may omit its move clocks.
+pub fn parse_position(line: &str) -> Result<LabelledPosition, String> {
+    let (index, result) = find_result(line)
+        .ok_or(format!("No game result found in '{}'", line))?;
+
+    // drop the EPD opcode and punctuation before the result
+    let mut fields: Vec<&str> = line[..index]
+        .trim_end_matches(|c: char| c.is_whitespace() || c == '"' || c == '[')
+        .split_whitespace()
+        .collect();
+    if fields.last() == Some(&"c9") {
+        fields.pop();
+    }
+    let fen = match fields.len() {
+        4 => format!("{} 0 1", fields.join(" ")),
+        _ => fields.join(" "),
+    };
+    Ok(LabelledPosition {
+        state: parse_fen(&fen)?,
+        result,
+    })
+}
+
+/// Parses a training set with one labelled position per line, skipping blank lines.
+pub fn parse_positions(text: &str) -> Result<Vec<LabelledPosition>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            parse_position(line).map_err(|e| format!("Line {}: {}", number + 1, e))
+        })
+        .collect()
+}
+
+/// Returns the expected result for white of a position with the given score in
+/// centipawns, where the scaling constant `k` sets how decisive a given score is.
+pub fn sigmoid(score: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+/// Returns the mean squared error between the results of the given positions and those
+/// expected from their evaluation.
+pub fn error(positions: &[LabelledPosition], params: &EvalParams, k: f64) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = positions
+        .iter()
+        .map(|position| {
+            let expected = sigmoid(heuristic::score(&position.state, params), k);
+            (position.result - expected).powi(2)
+        })
+        .sum();
+    total / positions.len() as f64
+}
+
+/// The largest scaling constant considered by [optimal_k].
+const MAX_K: f64 = 3.0;
+
+/// Returns the scaling constant which best fits the given positions with the given
+/// weights, between 0 and [MAX_K].
+///
+/// This is found before tuning, so that the weights rather than the constant absorb
+/// the changes.
+pub fn optimal_k(positions: &[LabelledPosition], params: &EvalParams) -> f64 {
+    let scores: Vec<(i32, f64)> = positions
+        .iter()
+        .map(|position| (heuristic::score(&position.state, params), position.result))
+        .collect();
+    let error = |k: f64| {
+        scores
+            .iter()
+            .map(|&(score, result)| (result - sigmoid(score, k)).powi(2))
+            .sum::<f64>()
+    };
+
+    // scan the range, then repeatedly scan more finely around the best value found
+    let (mut start, mut end, mut step) = (0.0, MAX_K, 0.1);
+    let mut best = 1.0;
+    for _ in 0..4 {
+        let steps = ((end - start) / step).round() as usize;
+        best = (0..steps + 1)
+            .map(|index| start + step * index as f64)
+            .fold((best, error(best)), |(best, best_error), k| {
+                let k_error = error(k);
+                if k_error < best_error {
+                    (k, k_error)
+                } else {
+                    (best, best_error)
+                }
+            })
+            .0;
+        start = (best - step).max(0.0);
+        end = (best + step).min(MAX_K);
+        step /= 10.0;
+    }
+    best
+}
+
+/// Tunes the weights to fit the given positions, making at most the given number of
+/// passes over them and returning the tuned weights.
+///
+/// Each pass tries raising then lowering each weight by one, keeping any change which
+/// reduces the error. Tuning stops early once a pass makes no changes.
+pub fn tune(
+    positions: &[LabelledPosition],
+    params: &EvalParams,
+    k: f64,
+    max_passes: usize,
+) -> EvalParams {
+    let mut params = params.clone();
+    let mut weights = params.weights();
+    let mut best_error = error(positions, &params, k);
+
+    for pass in 0..max_passes {
+        let mut improved = false;
+        for index in 0..weights.len() {
+            for &delta in [1, -1].iter() {
+                weights[index] += delta;
+                params.set_weights(&weights);
+                let new_error = error(positions, &params, k);
+                if new_error < best_error {
+                    best_error = new_error;
+                    improved = true;
+                    break;
+                }
+                weights[index] -= delta;
+            }
+        }
+        params.set_weights(&weights);
+        info!("Tuning pass {}: error {:.8}", pass + 1, best_error);
+        if !improved {
+            break;
+        }
+    }
+    params
+}
+
+// finds the game result in a line, returning where it starts and its value for white
+fn find_result(line: &str) -> Option<(usize, f64)> {
+    // the draw is checked first, as it contains the other results
+    let results = [("1/2-1/2", 0.5), ("1-0", 1.0), ("0-1", 0.0)];
+    for &(text, result) in results.iter() {
+        if let Some(index) = line.find(text) {
+            return Some((index, result));
+        }
+    }
+
+    let open = line.rfind('[')?;
+    let close = line[open..].find(']')? + open;
+    let result = line[open + 1..close].trim().parse::<f64>().ok()?;
+    if (0.0..=1.0).contains(&result) {
+        Some((open, result))
+    } else {
+        None
+    }
+}
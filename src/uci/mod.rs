@@ -0,0 +1,728 @@
+//! Universal Chess Interface protocol.
+//!
+//! This module reads commands from a GUI and writes the engine's responses, as
+//! described in the UCI specification.
+
+use board::piece::Piece;
+use board::player::Player;
+use engine::book::{Book, BookSelection};
+use engine::calculator;
+use engine::calculator::{ScoredSequence, SearchLimit, SearchSignals};
+use engine::evaluator::Evaluator;
+use engine::kpk;
+use engine::nnue::Network;
+use engine::params::EvalParams;
+use engine::syzygy::Syzygy;
+use engine::tablebase::Tablebase;
+use engine::time_manager::{TimeControl, TimeManager};
+use random::Random;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::move_application::Move;
+use rules::variant::Variant;
+use std::io;
+use std::io::BufRead;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+mod test;
+
+/// The search depth used when `go` specifies neither a depth nor a time.
+const DEFAULT_DEPTH: usize = 3;
+
+/// The move overhead used unless the Move Overhead option sets one, in milliseconds.
+const DEFAULT_MOVE_OVERHEAD: u64 = 10;
+
+/// The greatest move overhead the Move Overhead option accepts, in milliseconds.
+const MAX_MOVE_OVERHEAD: u64 = 5000;
+
+/// A search running on its own thread, which sends its best move to the GUI once it ends.
+#[derive(Debug)]
+struct Search {
+    /// The signals through which the search is stopped or taken off pondering.
+    signals: Arc<SearchSignals>,
+
+    /// True if the search ends by itself once it is no longer pondering.
+    limited: bool,
+
+    /// The thread running the search.
+    thread: JoinHandle<()>,
+}
+
+/// The most principal variations the MultiPV option accepts.
+const MAX_MULTI_PV: usize = 256;
+
+/// The state of a UCI session.
+#[derive(Debug)]
+pub struct Uci {
+    /// The position to search from.
+    state: GameState,
+
+    /// True if the GUI enabled Chess960 castling notation.
+    chess960: bool,
+
+    /// The variant to play, as chosen with the UCI_Variant option.
+    variant: Variant,
+
+    /// The evaluation weights, loaded from the file given by the EvalFile option.
+    params: Arc<EvalParams>,
+
+    /// The network loaded from the file given by the NetFile option, if any.
+    network: Option<Arc<Network>>,
+
+    /// True if the UseNetwork option selects the network evaluation.
+    use_network: bool,
+
+    /// The tables loaded from the directory given by the TablebasePath option.
+    tablebase: Arc<Tablebase>,
+
+    /// The Syzygy tables found in the directories given by the SyzygyPath option.
+    syzygy: Arc<Syzygy>,
+
+    /// The least remaining depth at which searches probe the Syzygy tables, as set by the
+    /// SyzygyProbeDepth option.
+    syzygy_probe_depth: usize,
+
+    /// The opening book loaded from the file given by the BookFile option.
+    book: Book,
+
+    /// True if the OwnBook option lets the engine play moves from its book.
+    own_book: bool,
+
+    /// How moves are chosen from the book, as set by the BookSelection option.
+    book_selection: BookSelection,
+
+    /// The generator used to choose book moves.
+    random: Random,
+
+    /// The time kept in hand for each move, as set by the Move Overhead option.
+    move_overhead: Duration,
+
+    /// True if the Ponder option lets the engine name the reply it expects along with its
+    /// best move.
+    ponder: bool,
+
+    /// The number of principal variations searches report, as set by the MultiPV option.
+    multi_pv: usize,
+
+    /// The last search started, which may still be running.
+    search: Option<Search>,
+
+    /// Where searches send the lines for the GUI.
+    output: Sender<String>,
+
+    /// The receiving end of the output, until it is taken to be written out.
+    output_receiver: Option<Receiver<String>>,
+
+    /// True once the GUI has asked the engine to exit.
+    quit: bool,
+}
+
+impl Uci {
+    /// Returns a new session, set up at the standard start position.
+    pub fn new() -> Self {
+        let (output, output_receiver) = mpsc::channel();
+        Uci {
+            state: GameState::start_position(),
+            chess960: false,
+            variant: Variant::Standard,
+            params: Arc::new(EvalParams::default()),
+            network: None,
+            use_network: false,
+            tablebase: Arc::new(Tablebase::new()),
+            syzygy: Arc::new(Syzygy::new()),
+            syzygy_probe_depth: 1,
+            book: Book::new(),
+            own_book: false,
+            book_selection: BookSelection::Weighted,
+            random: Random::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+            ),
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD),
+            ponder: false,
+            multi_pv: 1,
+            search: None,
+            output,
+            output_receiver: Some(output_receiver),
+            quit: false,
+        }
+    }
+
+    /// Returns the current position.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Returns true if Chess960 castling notation is enabled.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Returns the variant to play.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Returns the evaluation weights.
+    pub fn params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    /// Returns the loaded network, if any.
+    pub fn network(&self) -> Option<&Network> {
+        self.network.as_deref()
+    }
+
+    /// Returns true if searches use the network evaluation.
+    pub fn uses_network(&self) -> bool {
+        self.use_network
+    }
+
+    /// Returns the loaded endgame tables.
+    pub fn tablebase(&self) -> &Tablebase {
+        &self.tablebase
+    }
+
+    /// Returns the Syzygy tables found.
+    pub fn syzygy(&self) -> &Syzygy {
+        &self.syzygy
+    }
+
+    /// Returns the least remaining depth at which searches probe the Syzygy tables.
+    pub fn syzygy_probe_depth(&self) -> usize {
+        self.syzygy_probe_depth
+    }
+
+    /// Returns the loaded opening book.
+    pub fn book(&self) -> &Book {
+        &self.book
+    }
+
+    /// Returns true if the engine plays moves from its book.
+    pub fn uses_book(&self) -> bool {
+        self.own_book
+    }
+
+    /// Returns how moves are chosen from the book.
+    pub fn book_selection(&self) -> BookSelection {
+        self.book_selection
+    }
+
+    /// Returns the time kept in hand for each move.
+    pub fn move_overhead(&self) -> Duration {
+        self.move_overhead
+    }
+
+    /// Returns true if the engine names the reply it expects along with its best move.
+    pub fn uses_ponder(&self) -> bool {
+        self.ponder
+    }
+
+    /// Returns the number of principal variations searches report.
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    /// Returns true while a search is running.
+    pub fn is_searching(&self) -> bool {
+        self.search
+            .as_ref()
+            .is_some_and(|search| !search.thread.is_finished())
+    }
+
+    /// Takes the receiving end of the lines searches send to the GUI, each search sending
+    /// its best move once it ends. Returns `None` once it has been taken.
+    pub fn take_output(&mut self) -> Option<Receiver<String>> {
+        self.output_receiver.take()
+    }
+
+    /// Waits for the running search to end, as when the input ends during a search. A
+    /// pondering or infinite search, which would not end by itself, is stopped first.
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            if !search.limited || search.signals.is_pondering() {
+                search.signals.stop();
+            }
+            Self::join(search);
+        }
+    }
+
+    /// Returns true if the GUI has asked the engine to exit.
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Handles a single command line, returning the lines to send back to the GUI.
+    ///
+    /// Unknown commands are ignored, as the specification requires. Searches run on their
+    /// own thread, so that commands are still read while they run, and send their best
+    /// move through the output once they end. A new position or search stops the running
+    /// search first.
+    pub fn handle(&mut self, line: &str) -> Result<Vec<String>, String> {
+        let mut tokens = line.split_whitespace();
+        let command = match tokens.next() {
+            Some(command) => command,
+            None => return Ok(vec![]),
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match command {
+            "uci" => Ok(vec![
+                "id name Gladius".to_string(),
+                "id author Rowmance".to_string(),
+                "option name UCI_Chess960 type check default false".to_string(),
+                format!(
+                    "option name UCI_Variant type combo default {}{}",
+                    Variant::Standard,
+                    Variant::iter().map(|variant| format!(" var {}", variant)).collect::<String>()
+                ),
+                "option name EvalFile type string default <empty>".to_string(),
+                "option name NetFile type string default <empty>".to_string(),
+                "option name UseNetwork type check default false".to_string(),
+                "option name TablebasePath type string default <empty>".to_string(),
+                "option name SyzygyPath type string default <empty>".to_string(),
+                "option name SyzygyProbeDepth type spin default 1 min 1 max 100".to_string(),
+                "option name OwnBook type check default false".to_string(),
+                "option name BookFile type string default <empty>".to_string(),
+                "option name BookSelection type combo default Weighted var Weighted var Best"
+                    .to_string(),
+                format!(
+                    "option name Move Overhead type spin default {} min 0 max {}",
+                    DEFAULT_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD
+                ),
+                "option name Ponder type check default false".to_string(),
+                format!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV),
+                "uciok".to_string(),
+            ]),
+            "isready" => Ok(vec!["readyok".to_string()]),
+            "setoption" => self.set_option(&args).map(|_| vec![]),
+            "ucinewgame" => {
+                self.stop_search();
+                self.state = GameState::variant_start_position(self.variant);
+                Ok(vec![])
+            }
+            "position" => {
+                self.stop_search();
+                self.set_position(&args).map(|_| vec![])
+            }
+            "go" => {
+                self.stop_search();
+                self.go(&args)
+            }
+            "ponderhit" => {
+                if let Some(ref search) = self.search {
+                    search.signals.ponderhit();
+                }
+                Ok(vec![])
+            }
+            "stop" => {
+                self.stop_search();
+                Ok(vec![])
+            }
+            "quit" => {
+                self.stop_search();
+                self.quit = true;
+                Ok(vec![])
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    // handles "setoption name <id> [value <x>]"
+    fn set_option(&mut self, args: &[&str]) -> Result<(), String> {
+        if args.first() != Some(&"name") {
+            return Err(format!("invalid setoption command {:?}", args.join(" ")));
+        }
+        let value_index = args.iter().position(|&arg| arg == "value");
+        let name = args[1..value_index.unwrap_or(args.len())].join(" ");
+        let value = value_index.map(|index| args[index + 1..].join(" "));
+        if name.eq_ignore_ascii_case("UCI_Chess960") {
+            self.chess960 = match value.as_ref().map(|value| value.as_ref()) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => return Err(format!("invalid UCI_Chess960 value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("UCI_Variant") {
+            self.variant = value.unwrap_or_default().parse()?;
+        } else if name.eq_ignore_ascii_case("EvalFile") {
+            // an empty path restores the default weights
+            self.params = Arc::new(match value.as_ref().map(|value| value.as_ref()) {
+                None | Some("") | Some("<empty>") => EvalParams::default(),
+                Some(path) => EvalParams::load(path)?,
+            });
+        } else if name.eq_ignore_ascii_case("NetFile") {
+            self.network = match value.as_ref().map(|value| value.as_ref()) {
+                None | Some("") | Some("<empty>") => None,
+                Some(path) => Some(Arc::new(Network::load(path)?)),
+            };
+        } else if name.eq_ignore_ascii_case("UseNetwork") {
+            self.use_network = match value.as_ref().map(|value| value.as_ref()) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => return Err(format!("invalid UseNetwork value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("TablebasePath") {
+            self.tablebase = Arc::new(match value.as_ref().map(|value| value.as_ref()) {
+                None | Some("") | Some("<empty>") => Tablebase::new(),
+                Some(path) => Tablebase::load_dir(path)?,
+            });
+        } else if name.eq_ignore_ascii_case("SyzygyPath") {
+            self.syzygy = Arc::new(match value.as_ref().map(|value| value.as_ref()) {
+                None | Some("") | Some("<empty>") => Syzygy::new(),
+                Some(path) => Syzygy::load_dir(path)?,
+            });
+        } else if name.eq_ignore_ascii_case("SyzygyProbeDepth") {
+            self.syzygy_probe_depth = match value.as_ref().map(|value| value.parse()) {
+                Some(Ok(depth)) if (1..=100).contains(&depth) => depth,
+                _ => return Err(format!("invalid SyzygyProbeDepth value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("OwnBook") {
+            self.own_book = match value.as_ref().map(|value| value.as_ref()) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => return Err(format!("invalid OwnBook value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("BookFile") {
+            self.book = match value.as_ref().map(|value| value.as_ref()) {
+                None | Some("") | Some("<empty>") => Book::new(),
+                Some(path) => Book::load(path)?,
+            };
+        } else if name.eq_ignore_ascii_case("BookSelection") {
+            self.book_selection = match value.as_ref().map(|value| value.to_lowercase()) {
+                Some(ref value) if value == "weighted" => BookSelection::Weighted,
+                Some(ref value) if value == "best" => BookSelection::Best,
+                _ => return Err(format!("invalid BookSelection value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("Move Overhead") {
+            self.move_overhead = match value.as_ref().map(|value| value.parse()) {
+                Some(Ok(overhead)) if overhead <= MAX_MOVE_OVERHEAD => {
+                    Duration::from_millis(overhead)
+                }
+                _ => return Err(format!("invalid Move Overhead value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("Ponder") {
+            self.ponder = match value.as_ref().map(|value| value.as_ref()) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => return Err(format!("invalid Ponder value {:?}", value)),
+            };
+        } else if name.eq_ignore_ascii_case("MultiPV") {
+            self.multi_pv = match value.as_ref().map(|value| value.parse()) {
+                Some(Ok(multi_pv)) if (1..=MAX_MULTI_PV).contains(&multi_pv) => multi_pv,
+                _ => return Err(format!("invalid MultiPV value {:?}", value)),
+            };
+        }
+        Ok(())
+    }
+
+    // handles "position [startpos | fen <fen>] [moves <move>...]", playing the variant set
+    // by the UCI_Variant option unless the FEN names its own
+    fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_index = args.iter().position(|&arg| arg == "moves").unwrap_or(args.len());
+        let mut state = match args.first() {
+            Some(&"startpos") => GameState::variant_start_position(self.variant),
+            Some(&"fen") => {
+                let state = parse_fen(&args[1..moves_index].join(" "))?;
+                // a FEN with check counts or pockets keeps the variant it names
+                match state.variant {
+                    Variant::Standard => state.with_variant(self.variant),
+                    _ => state,
+                }
+            }
+            _ => return Err(format!("invalid position command {:?}", args.join(" "))),
+        };
+        for text in args.iter().skip(moves_index + 1) {
+            let mv = self.parse_move(&state, text)?;
+            state.make_move(&mv);
+        }
+        self.state = state;
+        Ok(())
+    }
+
+    // handles "go [ponder] [infinite] [depth <n>] [nodes <n>] [movetime <ms>] [wtime <ms>]
+    // [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <n>]", starting a search unless the
+    // book has a move
+    fn go(&mut self, args: &[&str]) -> Result<Vec<String>, String> {
+        let ponder = args.contains(&"ponder");
+        let infinite = args.contains(&"infinite");
+        if !ponder && !infinite {
+            if let Some(mv) = self.book_move() {
+                return Ok(vec![format!("bestmove {}", self.format_move(&self.state, &mv))]);
+            }
+        }
+        let limit = if infinite { SearchLimit::Infinite } else { self.search_limit(args)? };
+        let signals = if ponder { SearchSignals::pondering() } else { SearchSignals::new() };
+        self.start_search(limit, signals)?;
+        Ok(vec![])
+    }
+
+    // stops the running search, if there is one, waiting for it to send its best move
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.signals.stop();
+            Self::join(search);
+        }
+    }
+
+    // waits for a search to end
+    fn join(search: Search) {
+        if search.thread.join().is_err() {
+            error!("The search thread panicked");
+        }
+    }
+
+    // starts searching the current position within the given limit on its own thread,
+    // which sends the best move, and the reply it expects if the Ponder option is set, once
+    // the search ends and is neither pondering nor infinite, or is stopped
+    //
+    // With the MultiPV option above 1, each principal variation is sent first.
+    fn start_search(&mut self, limit: SearchLimit, signals: SearchSignals) -> Result<(), String> {
+        let network = if self.use_network {
+            Some(self.network.clone().ok_or("UseNetwork is set without a NetFile")?)
+        } else {
+            None
+        };
+        let params = Arc::clone(&self.params);
+        let tablebase = Arc::clone(&self.tablebase);
+        let syzygy = Arc::clone(&self.syzygy);
+        let syzygy_probe_depth = self.syzygy_probe_depth;
+        let (chess960, ponder, multi_pv) = (self.chess960, self.ponder, self.multi_pv);
+        let infinite = limit == SearchLimit::Infinite;
+        let output = self.output.clone();
+        let state = self.state.clone();
+        let signals = Arc::new(signals);
+        let search_signals = Arc::clone(&signals);
+        let thread = thread::spawn(move || {
+            let mut evaluator = match network {
+                Some(ref network) => Evaluator::network(network, &state),
+                None => Evaluator::handcrafted(&params, &state),
+            };
+            if !tablebase.is_empty() {
+                evaluator = evaluator.with_tablebase(&tablebase);
+            }
+            if !syzygy.is_empty() {
+                evaluator = evaluator.with_syzygy(&syzygy, syzygy_probe_depth);
+            }
+            let lines = calculator::search_multi_pv(
+                &state,
+                &mut evaluator,
+                limit,
+                &search_signals,
+                multi_pv,
+            );
+            // the best move of a pondering or infinite search is only sent once the GUI
+            // asks for it, even if the search ended by itself
+            search_signals.wait(infinite);
+            let mut responses = Vec::new();
+            if multi_pv > 1 {
+                for line in lines.iter() {
+                    responses.push(format_info(&state, line, chess960));
+                }
+            }
+            responses.push(match lines.first().map(ScoredSequence::moves) {
+                Some(moves) => {
+                    let best = format_move(&state, &moves[0], chess960);
+                    match moves.get(1) {
+                        Some(reply) if ponder => {
+                            let state = state.apply_move(&moves[0]);
+                            let reply = format_move(&state, reply, chess960);
+                            format!("bestmove {} ponder {}", best, reply)
+                        }
+                        _ => format!("bestmove {}", best),
+                    }
+                }
+                None => "bestmove 0000".to_string(),
+            });
+            for response in responses {
+                // the receiver is only gone once the session has ended
+                let _ = output.send(response);
+            }
+        });
+        self.search = Some(Search {
+            signals,
+            limited: limit != SearchLimit::Infinite,
+            thread,
+        });
+        Ok(())
+    }
+
+    // returns the limit of the search asked for by the arguments of "go", preferring a
+    // depth, then a node count, then a fixed time, then the clock of the player to move
+    fn search_limit(&self, args: &[&str]) -> Result<SearchLimit, String> {
+        let value = |name: &str| -> Result<Option<i64>, String> {
+            match args.iter().position(|&arg| arg == name) {
+                Some(index) => args
+                    .get(index + 1)
+                    .ok_or(format!("missing {}", name))?
+                    .parse()
+                    .map(Some)
+                    .map_err(|e| format!("invalid {}: {}", name, e)),
+                None => Ok(None),
+            }
+        };
+        // some GUIs send negative times once a clock has run out
+        let millis = |value: i64| Duration::from_millis(value.max(0) as u64);
+
+        if let Some(depth) = value("depth")? {
+            return Ok(SearchLimit::Depth(depth.max(1) as usize));
+        }
+        if let Some(nodes) = value("nodes")? {
+            return Ok(SearchLimit::Nodes(nodes.max(1) as u64));
+        }
+        let (time, increment) = match self.state.player_turn {
+            Player::White => ("wtime", "winc"),
+            Player::Black => ("btime", "binc"),
+        };
+        let control = match (value("movetime")?, value(time)?) {
+            (Some(move_time), _) => TimeControl::MoveTime(millis(move_time)),
+            (None, Some(time)) => TimeControl::Clock {
+                time: millis(time),
+                increment: millis(value(increment)?.unwrap_or(0)),
+                moves_to_go: value("movestogo")?.map(|moves| moves.max(1) as u32),
+            },
+            (None, None) => return Ok(SearchLimit::Depth(DEFAULT_DEPTH)),
+        };
+        Ok(SearchLimit::Time(TimeManager::new(control, self.move_overhead)))
+    }
+
+    // returns a move from the book for the current position, if it is in use and has one
+    fn book_move(&mut self) -> Option<Move> {
+        // books only cover standard chess
+        if !self.own_book || self.book.is_empty() || self.state.variant != Variant::Standard {
+            return None;
+        }
+        let random = self.random.next_u64();
+        self.book.choose_move(&self.state, self.book_selection, random)
+    }
+
+    /// Formats a move in the long algebraic notation used by UCI.
+    ///
+    /// Castling is written as the king moving to its target square, or in Chess960
+    /// mode as the king moving onto the castling rook. Drops are written as the piece
+    /// letter and the target square, such as "N@f3".
+    pub fn format_move(&self, state: &GameState, mv: &Move) -> String {
+        format_move(state, mv, self.chess960)
+    }
+
+    /// Parses a move in UCI notation, returning it if it is legal in the given state.
+    pub fn parse_move(&self, state: &GameState, text: &str) -> Result<Move, String> {
+        state
+            .legal_moves()
+            .into_iter()
+            .find(|mv| self.format_move(state, mv).eq_ignore_ascii_case(text))
+            .ok_or(format!("{} is not a legal move", text))
+    }
+}
+
+// formats a move in UCI notation as [Uci::format_move], for the given castling notation
+fn format_move(state: &GameState, mv: &Move, chess960: bool) -> String {
+    if mv.drop {
+        let piece = match mv.piece {
+            Piece::Pawn => "P",
+            Piece::Rook => "R",
+            Piece::Knight => "N",
+            Piece::Bishop => "B",
+            Piece::Queen => "Q",
+            Piece::King => "K",
+        };
+        return format!("{}@{}", piece, mv.target.to_string().to_lowercase());
+    }
+    let target = match mv.castle {
+        Some(castle_move) if chess960 => {
+            state.castle_rook_squares(castle_move, state.player_turn).0
+        }
+        _ => mv.target,
+    };
+    let promotion = match mv.promotion {
+        Some(Piece::Queen) => "q",
+        Some(Piece::Rook) => "r",
+        Some(Piece::Bishop) => "b",
+        Some(Piece::Knight) => "n",
+        Some(Piece::King) => "k",
+        _ => "",
+    };
+    format!("{}{}{}", mv.origin, target, promotion).to_lowercase()
+}
+
+// formats a principal variation from the given state as an info line, scored for the
+// player to move in centipawns, or in moves to mate with a negative count if the player is
+// mated
+fn format_info(state: &GameState, line: &ScoredSequence, chess960: bool) -> String {
+    let score = match (line.mate(), state.player_turn) {
+        (Some(mate), player) if mate.player() == player => format!("mate {}", mate.turns()),
+        (Some(mate), _) => format!("mate -{}", mate.turns()),
+        (None, Player::White) => format!("cp {}", line.score()),
+        (None, Player::Black) => format!("cp {}", -line.score()),
+    };
+    let mut state = state.clone();
+    let mut pv = Vec::with_capacity(line.moves().len());
+    for mv in line.moves() {
+        pv.push(format_move(&state, mv, chess960));
+        state.make_move(mv);
+    }
+    format!(
+        "info depth {} multipv {} score {} pv {}",
+        line.depth(),
+        line.rank(),
+        score,
+        pv.join(" ")
+    )
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Uci::new()
+    }
+}
+
+/// Runs the UCI loop on standard input and output until the GUI quits.
+///
+/// Lines are written out by a thread of their own, so that a search can send its best move
+/// while the loop waits for the next command.
+pub fn run() {
+    // build the endgame bitbase now rather than during the first search
+    kpk::init();
+    let mut uci = Uci::new();
+    let output = uci.take_output().expect("the output of a new session is not taken");
+    // the responses to commands go through the same channel as those of searches, so that
+    // they are written in the order they were sent
+    let responses = uci.output.clone();
+    let writer = thread::spawn(move || {
+        for line in output {
+            println!("{}", line);
+        }
+    });
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to read from standard input: {}", e);
+                break;
+            }
+        };
+        match uci.handle(&line) {
+            Ok(lines) => for response in lines {
+                let _ = responses.send(response);
+            },
+            Err(e) => warn!("Failed to handle {:?}: {}", line, e),
+        }
+        if uci.is_quit() {
+            break;
+        }
+    }
+    uci.wait();
+    // the writer ends once every sender is gone
+    drop(uci);
+    drop(responses);
+    if writer.join().is_err() {
+        error!("The output thread panicked");
+    }
+}
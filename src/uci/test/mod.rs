@@ -0,0 +1,451 @@
+//! Tests for the uci module.
+
+use engine::book::{BookBuilder, BookSelection};
+use engine::heuristic::Score;
+use engine::params::EvalParams;
+use rules::fen_parser::parse_fen;
+use rules::game_state::GameState;
+use rules::variant::{Outcome, Variant};
+use std::env;
+use std::fs;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::{Duration, Instant};
+use uci::Uci;
+
+// handles a command, then waits for any search it starts, returning every line sent back
+fn handle_and_wait(
+    uci: &mut Uci,
+    output: &Receiver<String>,
+    line: &str,
+) -> Result<Vec<String>, String> {
+    let mut responses = uci.handle(line)?;
+    uci.wait();
+    responses.extend(output.try_iter());
+    Ok(responses)
+}
+
+#[test]
+fn uci_handshake() {
+    let mut uci = Uci::new();
+    let responses = uci.handle("uci").unwrap();
+    assert_eq!(responses.last().unwrap(), "uciok");
+    assert!(responses.contains(&"option name UCI_Chess960 type check default false".to_string()));
+    assert_eq!(uci.handle("isready").unwrap(), vec!["readyok".to_string()]);
+    assert!(uci.handle("").unwrap().is_empty());
+    assert!(uci.handle("unknowncommand").unwrap().is_empty());
+
+    uci.handle("quit").unwrap();
+    assert!(uci.is_quit());
+}
+
+#[test]
+fn uci_position() {
+    let mut uci = Uci::new();
+    uci.handle("position startpos moves e2e4 e7e5 g1f3").unwrap();
+    assert_eq!(
+        uci.state(),
+        &parse_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").unwrap()
+    );
+
+    uci.handle("position fen 4k3/1P6/8/8/8/8/8/4K3 w - - 0 1 moves b7b8n").unwrap();
+    assert_eq!(
+        uci.state(),
+        &parse_fen("1N2k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap()
+    );
+
+    assert!(uci.handle("position startpos moves e2e5").is_err());
+    assert!(uci.handle("position").is_err());
+
+    uci.handle("ucinewgame").unwrap();
+    assert_eq!(uci.state(), &GameState::start_position());
+}
+
+#[test]
+fn uci_chess960_castling() {
+    let mut uci = Uci::new();
+    assert!(!uci.is_chess960());
+    uci.handle("setoption name UCI_Chess960 value true").unwrap();
+    assert!(uci.is_chess960());
+    assert!(uci.handle("setoption name UCI_Chess960 value maybe").is_err());
+
+    // standard castling is written as the king moving onto its rook
+    uci.handle("position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1h1 e8a8").unwrap();
+    assert_eq!(
+        uci.state(),
+        &parse_fen("2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2").unwrap()
+    );
+
+    // the king does not move when castling from its target square
+    uci.handle("position fen 1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1 moves g1h1").unwrap();
+    assert_eq!(
+        uci.state(),
+        &parse_fen("1r4kr/8/8/8/8/8/8/1R3RK1 b hb - 1 1").unwrap()
+    );
+
+    uci.handle("setoption name UCI_Chess960 value false").unwrap();
+    uci.handle("position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1").unwrap();
+    assert_eq!(
+        uci.state(),
+        &parse_fen("r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1").unwrap()
+    );
+    assert!(uci.handle("position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1h1").is_err());
+}
+
+#[test]
+fn uci_go() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go depth 2").unwrap(),
+        vec!["bestmove a1a8".to_string()]
+    );
+
+    uci.handle("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go").unwrap(),
+        vec!["bestmove 0000".to_string()]
+    );
+}
+
+#[test]
+fn uci_eval_file() {
+    let mut uci = Uci::new();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(&"option name EvalFile type string default <empty>".to_string()));
+
+    let path = env::temp_dir().join("gladius_uci_eval_file.toml");
+    fs::write(&path, "[material]\nqueen = [1000, 1000]\n").unwrap();
+    let result = uci.handle(&format!("setoption name EvalFile value {}", path.display()));
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+    assert_eq!(uci.params().material.queen, Score::new(1000, 1000));
+
+    assert!(uci.handle("setoption name EvalFile value /nonexistent/gladius.toml").is_err());
+    uci.handle("setoption name EvalFile value <empty>").unwrap();
+    assert_eq!(uci.params(), &EvalParams::default());
+}
+
+#[test]
+fn uci_network() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(&"option name NetFile type string default <empty>".to_string()));
+    assert!(responses.contains(&"option name UseNetwork type check default false".to_string()));
+
+    // the network must be loaded before it can be used
+    uci.handle("setoption name UseNetwork value true").unwrap();
+    assert!(uci.uses_network());
+    assert!(uci.handle("go depth 1").is_err());
+
+    let mut bytes = b"GNN1".to_vec();
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.resize(bytes.len() + 2 * 771 + 4, 0);
+    let path = env::temp_dir().join("gladius_uci_network.nnue");
+    fs::write(&path, &bytes).unwrap();
+    let result = uci.handle(&format!("setoption name NetFile value {}", path.display()));
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+    assert_eq!(uci.network().map(|network| network.hidden()), Some(1));
+    assert_eq!(handle_and_wait(&mut uci, &output, "go depth 1").unwrap().len(), 1);
+
+    assert!(uci.handle("setoption name NetFile value /nonexistent/gladius.nnue").is_err());
+    assert!(uci.handle("setoption name UseNetwork value maybe").is_err());
+    uci.handle("setoption name UseNetwork value false").unwrap();
+    uci.handle("setoption name NetFile value <empty>").unwrap();
+    assert!(uci.network().is_none());
+    assert!(!uci.uses_network());
+}
+
+#[test]
+fn uci_variant() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(
+        &("option name UCI_Variant type combo default chess var chess var kingofthehill "
+            .to_string() + "var 3check var crazyhouse var atomic var antichess var horde "
+            + "var racingkings")
+    ));
+
+    uci.handle("setoption name UCI_Variant value kingofthehill").unwrap();
+    assert_eq!(uci.variant(), Variant::KingOfTheHill);
+    assert!(uci.handle("setoption name UCI_Variant value fourcheck").is_err());
+
+    // moving the king onto the only safe hill square wins immediately
+    uci.handle("position fen 4k3/8/8/8/8/4K3/8/q7 w - - 0 1").unwrap();
+    assert_eq!(uci.state().variant, Variant::KingOfTheHill);
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go depth 1").unwrap(),
+        vec!["bestmove e3e4".to_string()]
+    );
+
+    // drops are written with the piece letter
+    uci.handle("setoption name UCI_Variant value crazyhouse").unwrap();
+    uci.handle("position fen 4k3/8/8/8/8/8/8/4K3[Nn] w - - 0 1 moves N@e4 n@d5").unwrap();
+    assert_eq!(
+        uci.state(),
+        &parse_fen("4k3/8/8/3n4/4N3/8/8/4K3[] w - - 2 2").unwrap()
+    );
+
+    // a FEN with check counts or pockets keeps its own variant
+    uci.handle("setoption name UCI_Variant value chess").unwrap();
+    uci.handle("position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1 +2+0").unwrap();
+    assert_eq!(uci.state().variant, Variant::ThreeCheck);
+    assert_eq!(uci.state().white_checks, 2);
+    uci.handle("position fen 4k3/8/8/8/8/8/8/4K3[Q] w - - 0 1").unwrap();
+    assert_eq!(uci.state().variant, Variant::Crazyhouse);
+    uci.handle("setoption name UCI_Variant value atomic").unwrap();
+    uci.handle("position fen 4k3/8/8/8/8/8/8/4K3[Q] w - - 0 1").unwrap();
+    assert_eq!(uci.state().variant, Variant::Crazyhouse);
+    uci.handle("position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(uci.state().variant, Variant::Atomic);
+}
+
+#[test]
+fn uci_tablebase() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(&"option name TablebasePath type string default <empty>".to_string()));
+
+    let path = env::temp_dir().join("gladius_uci_tablebase");
+    fs::create_dir_all(&path).unwrap();
+    let result = uci.handle(&format!("setoption name TablebasePath value {}", path.display()));
+    fs::remove_dir_all(&path).unwrap();
+    result.unwrap();
+    assert!(uci.tablebase().is_empty());
+    assert_eq!(handle_and_wait(&mut uci, &output, "go depth 1").unwrap().len(), 1);
+
+    assert!(uci.handle("setoption name TablebasePath value /nonexistent/gladius").is_err());
+    uci.handle("setoption name TablebasePath value <empty>").unwrap();
+}
+
+#[test]
+fn uci_syzygy() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(&"option name SyzygyPath type string default <empty>".to_string()));
+    assert!(responses.contains(
+        &"option name SyzygyProbeDepth type spin default 1 min 1 max 100".to_string()
+    ));
+
+    let path = env::temp_dir().join("gladius_uci_syzygy");
+    fs::create_dir_all(&path).unwrap();
+    fs::write(path.join("KRvK.rtbw"), b"").unwrap();
+    let result = uci.handle(&format!("setoption name SyzygyPath value {}", path.display()));
+    fs::remove_dir_all(&path).unwrap();
+    result.unwrap();
+    assert_eq!(uci.syzygy().len(), 1);
+    uci.handle("position fen k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+    assert_eq!(handle_and_wait(&mut uci, &output, "go depth 1").unwrap().len(), 1);
+
+    uci.handle("setoption name SyzygyProbeDepth value 4").unwrap();
+    assert_eq!(uci.syzygy_probe_depth(), 4);
+    assert!(uci.handle("setoption name SyzygyProbeDepth value 0").is_err());
+    assert!(uci.handle("setoption name SyzygyProbeDepth value deep").is_err());
+    assert!(uci.handle("setoption name SyzygyPath value /nonexistent/gladius").is_err());
+    uci.handle("setoption name SyzygyPath value <empty>").unwrap();
+    assert!(uci.syzygy().is_empty());
+}
+
+#[test]
+fn uci_book() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(&"option name OwnBook type check default false".to_string()));
+    assert!(responses.contains(&"option name BookFile type string default <empty>".to_string()));
+
+    // a book which only knows 1. e4 c5
+    let start = GameState::start_position();
+    let e4 = uci.parse_move(&start, "e2e4").unwrap();
+    let c5 = uci.parse_move(&start.apply_move(&e4), "c7c5").unwrap();
+    let mut builder = BookBuilder::new();
+    builder.add_game(&start, &[e4, c5], Some(Outcome::Draw), 10);
+    let path = env::temp_dir().join("gladius_uci_book.bin");
+    builder.build(1).save(&path.display().to_string()).unwrap();
+    let result = uci.handle(&format!("setoption name BookFile value {}", path.display()));
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+    assert_eq!(uci.book().len(), 2);
+
+    // the book is only used once enabled
+    uci.handle("setoption name OwnBook value true").unwrap();
+    assert!(uci.uses_book());
+    uci.handle("position startpos").unwrap();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go depth 1").unwrap(),
+        vec!["bestmove e2e4".to_string()]
+    );
+    uci.handle("setoption name BookSelection value Best").unwrap();
+    assert_eq!(uci.book_selection(), BookSelection::Best);
+    uci.handle("position startpos moves e2e4").unwrap();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go depth 1").unwrap(),
+        vec!["bestmove c7c5".to_string()]
+    );
+
+    // positions out of the book are searched
+    uci.handle("position startpos moves d2d4").unwrap();
+    assert_eq!(handle_and_wait(&mut uci, &output, "go depth 1").unwrap().len(), 1);
+
+    assert!(uci.handle("setoption name OwnBook value maybe").is_err());
+    assert!(uci.handle("setoption name BookSelection value worst").is_err());
+    assert!(uci.handle("setoption name BookFile value /nonexistent/gladius.bin").is_err());
+    uci.handle("setoption name BookFile value <empty>").unwrap();
+    assert!(uci.book().is_empty());
+}
+
+#[test]
+fn uci_time_control() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(
+        &"option name Move Overhead type spin default 10 min 0 max 5000".to_string()
+    ));
+    uci.handle("setoption name Move Overhead value 50").unwrap();
+    assert_eq!(uci.move_overhead(), Duration::from_millis(50));
+    assert!(uci.handle("setoption name Move Overhead value 5001").is_err());
+    assert!(uci.handle("setoption name Move Overhead value soon").is_err());
+
+    // searches on the clock of the player to move, even once it has run out
+    uci.handle("position startpos moves e2e4").unwrap();
+    let start = Instant::now();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go wtime 0 btime 1000 winc 0 binc 100").unwrap().len(),
+        1
+    );
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go wtime 60000 btime -20 movestogo 5").unwrap().len(),
+        1
+    );
+    assert_eq!(handle_and_wait(&mut uci, &output, "go movetime 50").unwrap().len(), 1);
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // a single legal move is played at once
+    uci.handle("position fen k7/8/8/8/8/8/8/KR6 b - - 0 1").unwrap();
+    let start = Instant::now();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go btime 600000").unwrap(),
+        vec!["bestmove a8a7".to_string()]
+    );
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    assert_eq!(handle_and_wait(&mut uci, &output, "go nodes 10").unwrap().len(), 1);
+    assert!(uci.handle("go btime").is_err());
+    assert!(uci.handle("go movetime soon").is_err());
+}
+
+#[test]
+fn uci_ponder() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(&"option name Ponder type check default false".to_string()));
+    uci.handle("setoption name Ponder value true").unwrap();
+    assert!(uci.uses_ponder());
+    assert!(uci.handle("setoption name Ponder value maybe").is_err());
+
+    // a pondering search waits for the pondered move, then searches on the clock
+    uci.handle("position startpos moves e2e4").unwrap();
+    assert!(uci.handle("go ponder movetime 50").unwrap().is_empty());
+    thread::sleep(Duration::from_millis(200));
+    assert!(uci.is_searching());
+    assert!(output.try_recv().is_err());
+    assert!(uci.handle("ponderhit").unwrap().is_empty());
+    let response = output.recv_timeout(Duration::from_secs(10)).unwrap();
+    let words: Vec<&str> = response.split_whitespace().collect();
+    assert_eq!(words.len(), 4);
+    assert_eq!((words[0], words[2]), ("bestmove", "ponder"));
+    let state = uci.state().clone();
+    let mv = uci.parse_move(&state, words[1]).unwrap();
+    assert!(uci.parse_move(&state.apply_move(&mv), words[3]).is_ok());
+
+    // commands are still read during a search, which stops at once when asked
+    let start = Instant::now();
+    assert!(uci.handle("go movetime 60000").unwrap().is_empty());
+    assert_eq!(uci.handle("isready").unwrap(), vec!["readyok".to_string()]);
+    assert!(uci.is_searching());
+    assert!(uci.handle("stop").unwrap().is_empty());
+    assert!(!uci.is_searching());
+    assert!(output.try_recv().unwrap().starts_with("bestmove "));
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // an infinite search runs until it is stopped
+    assert!(uci.handle("go infinite").unwrap().is_empty());
+    assert!(uci.handle("ponderhit").unwrap().is_empty());
+    thread::sleep(Duration::from_millis(100));
+    assert!(uci.is_searching());
+    uci.handle("stop").unwrap();
+    assert!(output.try_recv().unwrap().starts_with("bestmove "));
+    uci.handle("stop").unwrap();
+    assert!(output.try_recv().is_err());
+
+    // a pondering search sends nothing until it is stopped, even once it is complete
+    assert!(uci.handle("go ponder depth 1").unwrap().is_empty());
+    thread::sleep(Duration::from_millis(200));
+    assert!(uci.is_searching());
+    assert!(output.try_recv().is_err());
+    uci.handle("stop").unwrap();
+    assert!(output.try_recv().unwrap().starts_with("bestmove "));
+
+    // and so does an infinite search with no moves to search
+    uci.handle("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(uci.handle("go infinite").unwrap().is_empty());
+    thread::sleep(Duration::from_millis(100));
+    assert!(output.try_recv().is_err());
+    uci.handle("stop").unwrap();
+    assert_eq!(output.try_recv().unwrap(), "bestmove 0000");
+    uci.handle("position startpos").unwrap();
+
+    // a new position stops the search, which still sends its best move
+    uci.handle("go ponder").unwrap();
+    uci.handle("position startpos").unwrap();
+    assert!(!uci.is_searching());
+    assert!(output.try_recv().unwrap().starts_with("bestmove "));
+    uci.handle("setoption name Ponder value false").unwrap();
+    let responses = handle_and_wait(&mut uci, &output, "go depth 1").unwrap();
+    assert_eq!(responses[0].split_whitespace().count(), 2);
+}
+
+#[test]
+fn uci_multi_pv() {
+    let mut uci = Uci::new();
+    let output = uci.take_output().unwrap();
+    let responses = uci.handle("uci").unwrap();
+    assert!(responses.contains(
+        &"option name MultiPV type spin default 1 min 1 max 256".to_string()
+    ));
+    assert!(uci.handle("setoption name MultiPV value 0").is_err());
+    uci.handle("setoption name MultiPV value 3").unwrap();
+    assert_eq!(uci.multi_pv(), 3);
+
+    // each variation is reported, scored for the player to move, before the best move
+    uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let responses = handle_and_wait(&mut uci, &output, "go depth 2").unwrap();
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[0], "info depth 2 multipv 1 score mate 1 pv a1a8");
+    assert!(responses[1].starts_with("info depth 2 multipv 2 score "));
+    assert!(responses[2].starts_with("info depth 2 multipv 3 score "));
+    assert_eq!(responses[3], "bestmove a1a8");
+
+    // any rook move along the back rank mates
+    uci.handle("position fen 6k1/8/8/8/8/8/5PPP/r5K1 b - - 0 1").unwrap();
+    let responses = handle_and_wait(&mut uci, &output, "go depth 2").unwrap();
+    assert!(responses[0].starts_with("info depth 2 multipv 1 score mate 1 pv a1"));
+
+    // mated lines count down with a negative number of moves
+    uci.handle("position fen k7/8/8/8/8/1r6/r7/7K w - - 0 1").unwrap();
+    assert_eq!(
+        handle_and_wait(&mut uci, &output, "go depth 3").unwrap(),
+        vec![
+            "info depth 3 multipv 1 score mate -1 pv h1g1 b3b1".to_string(),
+            "bestmove h1g1".to_string(),
+        ]
+    );
+}